@@ -3,15 +3,28 @@ use anchor_lang::prelude::AccountsClose;
 use anchor_lang::system_program::{System};
 use anchor_lang::solana_program::sysvar::clock::Clock;
 use anchor_lang::solana_program::account_info::AccountInfo;
-use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint};
+use anchor_spl::token::{self, Token, TokenAccount, Transfer, Mint, Burn, Approve};
 use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{self, TokenInterface, TransferChecked};
 use serde_json;
 use mpl_token_metadata::instructions::CreateMetadataAccountV3Cpi;
 use mpl_token_metadata::types::{Creator, DataV2};
 use chainlink_solana as chainlink;
 declare_id!("FTMjvTiyNviVXUkEumWUA4s7SWuE37Bsvh9k5SFmQSR4");
-pub const CHAINLINK_SOL_USD_FEED_DEVNET: Pubkey = pubkey!("99B2bTijsU6f1GCT73HmdR7HCFFjGMBcPZY6jZ96ynrR");// mainenet -> CHAINLINK_SOL_USD_FEED_DEVNET: Pubkey = pubkey!("CH31Xns5z3M1cTAbKW34jcxPPciazARpijcHj9rxtemt");
+// Valores iniciales del oráculo; cada cluster los cambia con set_oracle_config
+pub const CHAINLINK_SOL_USD_FEED_DEVNET: Pubkey = pubkey!("99B2bTijsU6f1GCT73HmdR7HCFFjGMBcPZY6jZ96ynrR");
+pub const CHAINLINK_SOL_USD_FEED_MAINNET: Pubkey = pubkey!("CH31Xns5z3M1cTAbKW34jcxPPciazARpijcHj9rxtemt");
 pub const CHAINLINK_PROGRAM_ID: Pubkey =  pubkey!("HEvSKofvBgfaexv23kMabbYqxasxU3mQ4ibBMEmJWHny");
+// Antigüedad máxima por defecto de la ronda SOL/USD
+pub const DEFAULT_MAX_PRICE_STALENESS_SECS: i64 = 300;
+// Precio máximo de un pack en micro-USDC ($1,000), común a todas las validaciones de precio
+pub const MAX_PACKAGE_PRICE_USDC: u64 = 1_000_000_000;
+// Aviso mínimo de un cambio de precio programado
+pub const MIN_PRICE_CHANGE_DELAY_SECS: i64 = 60 * 60;
+// `mint` de una WithdrawalProposal que retira SOL nativo de la sol_treasury
+pub const NATIVE_SOL_MINT: Pubkey = anchor_lang::system_program::ID;
+// Cuentas fijas por equipo en los remaining_accounts de refund_teams (antes de sus Player PDAs)
+pub const REFUND_GROUP_ACCOUNTS: usize = 13;
 
 
 
@@ -44,17 +57,17 @@ pub mod sports {
         );
         game_state.mint_usdc = mint_usdc;
         
-        // Validate prices are reasonable (non-zero and less than $1000)
+        // Validate prices are reasonable (non-zero and at most MAX_PACKAGE_PRICE_USDC)
         require!(
-            team_price_a > 0 && team_price_a <= 1_000_000_000, // Max $1000
+            team_price_a > 0 && team_price_a <= MAX_PACKAGE_PRICE_USDC,
             SportsError::InvalidPrice
         );
         require!(
-            team_price_b > 0 && team_price_b <= 1_000_000_000,
+            team_price_b > 0 && team_price_b <= MAX_PACKAGE_PRICE_USDC,
             SportsError::InvalidPrice
         );
         require!(
-            team_price_c > 0 && team_price_c <= 1_000_000_000,
+            team_price_c > 0 && team_price_c <= MAX_PACKAGE_PRICE_USDC,
             SportsError::InvalidPrice
         );
         
@@ -67,6 +80,9 @@ pub mod sports {
         game_state.current_report_start = Clock::get()?.unix_timestamp;
         game_state.is_report_open = true;
         game_state.current_report_revenue = 0;
+        game_state.current_report_sol_revenue = 0;
+        game_state.current_report_coupon_redemptions = 0;
+        game_state.current_report_coupon_discount = 0;
         game_state.current_report_teams = 0;
         game_state.current_report_tokens = 0;
         game_state.is_paused = false;
@@ -78,6 +94,27 @@ pub mod sports {
         }else{
             game_state.time_lock = 24 * 60 * 60;//default 24 hours
         }
+        game_state.pity_threshold = 0; // Pity guarantee disabled until configured
+        game_state.reveal_window_slots = 150; // ~1 minute
+        game_state.expired_commit_policy = ExpiredCommitPolicy::AutoReveal;
+        game_state.entropy_source = EntropySourceKind::Chainlink;
+        game_state.entropy_oracle = Pubkey::default();
+        game_state.sol_usd_feed = CHAINLINK_SOL_USD_FEED_DEVNET;
+        game_state.oracle_program = CHAINLINK_PROGRAM_ID;
+        game_state.max_price_staleness_secs = DEFAULT_MAX_PRICE_STALENESS_SECS;
+        game_state.withdrawal_threshold = 2; // Igual que el antiguo esquema de dos firmas
+        game_state.next_withdrawal_proposal_id = 1;
+        game_state.treasury_received = 0;
+        game_state.treasury_withdrawn = 0;
+        game_state.treasury_reserved = 0;
+        game_state.accepted_mint_count = 0;
+        game_state.treasury_ledger_seeded = true; // Un despliegue nuevo no tiene saldo previo que cargar
+        game_state.presale_merkle_root = [0; 32]; // Sin presale hasta configurarla
+        game_state.presale_end = 0;
+        game_state.presale_wallet_cap = 0;
+        game_state.referral_commission_bps = 0; // Referidos desactivados hasta configurarlos
+        game_state.referral_commissions_owed = 0;
+        game_state.refund_window_secs = 0; // Sin reembolsos hasta configurar la ventana
 
         msg!("Game State initialized with owner: {}", ctx.accounts.user.key());
         msg!("NFT Update Authority: {}", nft_update_authority);
@@ -144,35 +181,117 @@ pub mod sports {
             SportsError::UnauthorizedAccess
         );
 
-        // SYNCHRONIZATION: First we update tokens_sold from GameState
-        // This ensures the PDA is synchronized before modifying
-        if let Some(player_summary) = game_state.players.iter().find(|p| p.id == player_id) {
-            // Calculate current tokens_sold based on GameState
-            let expected_tokens_sold = player_account.total_tokens - player_summary.available_tokens;
-            
-            // If out of sync, synchronize
-            if player_account.tokens_sold != expected_tokens_sold {
-                msg!("Synchronizing player {} PDA before adding tokens", player_id);
-                msg!("  current tokens_sold: {}, expected: {}", 
-                    player_account.tokens_sold, 
-                    expected_tokens_sold
-                );
-                player_account.tokens_sold = expected_tokens_sold;
-            }
+        add_player_supply(game_state, player_account, player_id, tokens_to_add)?;
+
+        msg!("Added {} tokens to player {}, new total: {}", 
+             tokens_to_add, player_id, player_account.total_tokens);
+        Ok(())
+    }
+
+    // Queue a supply tranche that unlocks at `unlock_timestamp`
+    pub fn schedule_supply_drop(
+        ctx: Context<ScheduleSupplyDrop>,
+        player_id: u16,
+        unlock_timestamp: i64,
+        amount: u32,
+    ) -> Result<()> {
+        let game_state = &ctx.accounts.game_state;
+        let supply_schedule = &mut ctx.accounts.supply_schedule;
+
+        // Only owner or staff can schedule supply
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        require!(amount > 0, SportsError::InvalidAmount);
+        require!(
+            unlock_timestamp > Clock::get()?.unix_timestamp,
+            SportsError::InvalidSupplySchedule
+        );
+        require!(
+            supply_schedule.tranches.len() < SupplySchedule::MAX_TRANCHES,
+            SportsError::SupplyScheduleFull
+        );
+
+        // First tranche for this player initializes the schedule
+        if !supply_schedule.initialized {
+            supply_schedule.player_id = player_id;
+            supply_schedule.total_released = 0;
+            supply_schedule.initialized = true;
         }
 
-        // Now we add the new tokens
-        player_account.total_tokens = player_account.total_tokens
-            .checked_add(tokens_to_add)
+        supply_schedule.tranches.push(SupplyTranche {
+            unlock_timestamp,
+            amount,
+        });
+
+        msg!("Supply drop scheduled for player {}: {} tokens at {}", 
+            player_id, amount, unlock_timestamp);
+        Ok(())
+    }
+
+    // Remove a tranche that has not been released yet
+    pub fn cancel_supply_drop(
+        ctx: Context<CancelSupplyDrop>,
+        player_id: u16,
+        index: u8,
+    ) -> Result<()> {
+        let game_state = &ctx.accounts.game_state;
+        let supply_schedule = &mut ctx.accounts.supply_schedule;
+
+        // Only owner or staff can cancel scheduled supply
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        require!(
+            (index as usize) < supply_schedule.tranches.len(),
+            SportsError::InvalidSupplySchedule
+        );
+
+        let tranche = supply_schedule.tranches.remove(index as usize);
+
+        msg!("Supply drop cancelled for player {}: {} tokens at {}", 
+            player_id, tranche.amount, tranche.unlock_timestamp);
+        Ok(())
+    }
+
+    // Permissionless crank: move every unlocked tranche into circulation
+    pub fn release_scheduled_supply(
+        ctx: Context<ReleaseScheduledSupply>,
+        player_id: u16,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+        let player_account = &mut ctx.accounts.player_account;
+        let supply_schedule = &mut ctx.accounts.supply_schedule;
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        // Released tranches are removed from the schedule, so they can never be released twice
+        let (released_amount, tranches_released) = take_unlocked_tranches(
+            &mut supply_schedule.tranches,
+            timestamp,
+        )?;
+        require!(tranches_released > 0, SportsError::NoSupplyUnlocked);
+
+        add_player_supply(game_state, player_account, player_id, released_amount)?;
+
+        supply_schedule.total_released = supply_schedule.total_released
+            .checked_add(released_amount as u64)
             .ok_or(SportsError::TokenOverflow)?;
 
-        // Update available_tokens in game state vec
-        if let Some(player_summary) = game_state.players.iter_mut().find(|p| p.id == player_id) {
-            player_summary.available_tokens = player_account.total_tokens - player_account.tokens_sold;
-        }
+        msg!("Released {} scheduled tokens for player {}, new total: {}", 
+            released_amount, player_id, player_account.total_tokens);
+
+        emit!(SupplyReleased {
+            player_id,
+            amount: released_amount,
+            tranches_released,
+            new_total_tokens: player_account.total_tokens,
+            timestamp,
+        });
 
-        msg!("Added {} tokens to player {}, new total: {}", 
-             tokens_to_add, player_id, player_account.total_tokens);
         Ok(())
     }
 
@@ -222,18 +341,7 @@ pub mod sports {
         );
 
         // SYNCHRONIZATION: First we synchronize tokens_sold from GameState
-        if let Some(player_summary) = game_state.players.iter().find(|p| p.id == player_id) {
-            let expected_tokens_sold = player_account.total_tokens - player_summary.available_tokens;
-            
-            if player_account.tokens_sold != expected_tokens_sold {
-                msg!("Synchronizing player {} PDA before updating", player_id);
-                msg!("  current tokens_sold: {}, expected: {}", 
-                    player_account.tokens_sold, 
-                    expected_tokens_sold
-                );
-                player_account.tokens_sold = expected_tokens_sold;
-            }
-        }
+        sync_player_from_summary(&game_state.players, player_account);
 
         // Use business logic function
         let updated_data = update_player_data(
@@ -247,8 +355,24 @@ pub mod sports {
             country,
         )?;
 
+        let changes = diff_player_updates(player_account, &updated_data);
+        if changes.is_empty() {
+            msg!("Player {} update is a no-op", player_id);
+            return Ok(());
+        }
+
+        // Once tokens are in circulation a category change needs a second approver
+        let category_changed = changes.iter().any(|c| matches!(c, PlayerFieldChange::Category { .. }));
+        require!(
+            !category_changed || player_account.tokens_sold == 0,
+            SportsError::CategoryChangeRequiresApproval
+        );
+
         // Apply updates to PDA
         apply_player_updates(player_account, &updated_data);
+        player_account.version = player_account.version
+            .checked_add(1)
+            .ok_or(SportsError::TokenOverflow)?;
 
         // Update summary in game state vec if category or tokens changed
         if category.is_some() || total_tokens.is_some() {
@@ -260,7 +384,202 @@ pub mod sports {
             }
         }
 
-        msg!("Player {} updated successfully", player_id);
+        msg!("Player {} updated successfully, version {}", player_id, player_account.version);
+
+        emit!(PlayerUpdated {
+            player_id,
+            version: player_account.version,
+            updated_by: ctx.accounts.user.key(),
+            approved_by: None,
+            changes,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // First step of a category change for a player whose tokens are already sold
+    pub fn propose_category_change(
+        ctx: Context<ProposeCategoryChange>,
+        player_id: u16,
+        new_category: PlayerCategory,
+    ) -> Result<()> {
+        let game_state = &ctx.accounts.game_state;
+        let player_account = &ctx.accounts.player_account;
+        let proposal = &mut ctx.accounts.category_change;
+
+        // Only owner or staff can propose
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        require!(
+            player_account.category != new_category,
+            SportsError::InvalidPlayerUpdate
+        );
+
+        proposal.player_id = player_id;
+        proposal.proposer = ctx.accounts.user.key();
+        proposal.new_category = new_category;
+        proposal.player_version = player_account.version;
+        proposal.timestamp = Clock::get()?.unix_timestamp;
+
+        msg!("Category change proposed for player {}: {:?} -> {:?}", 
+            player_id, 
+            player_account.category, 
+            new_category
+        );
+        msg!("⚠️ Awaiting approval from a different authorized account");
+        Ok(())
+    }
+
+    // Second step: a different owner/staff signer applies the proposed category
+    pub fn approve_category_change(
+        ctx: Context<ApproveCategoryChange>,
+        player_id: u16,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+        let player_account = &mut ctx.accounts.player_account;
+        let proposal = &ctx.accounts.category_change;
+        let approver = ctx.accounts.user.key();
+
+        require!(
+            is_authorized(&approver, game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        // Verificar que no es la misma persona
+        require!(
+            proposal.proposer != approver,
+            SportsError::CannotApproveOwnProposal
+        );
+
+        // The player must not have changed since the proposal was made
+        require!(
+            proposal.player_version == player_account.version,
+            SportsError::StaleProposal
+        );
+
+        let old_category = player_account.category;
+        player_account.category = proposal.new_category;
+        player_account.version = player_account.version
+            .checked_add(1)
+            .ok_or(SportsError::TokenOverflow)?;
+
+        if let Some(player_summary) = game_state.players.iter_mut().find(|p| p.id == player_id) {
+            player_summary.category = proposal.new_category;
+        }
+
+        msg!("Category change for player {} approved by: {} (proposed by: {})", 
+            player_id, 
+            approver, 
+            proposal.proposer
+        );
+
+        emit!(PlayerUpdated {
+            player_id,
+            version: player_account.version,
+            updated_by: proposal.proposer,
+            approved_by: Some(approver),
+            changes: vec![PlayerFieldChange::Category {
+                old: old_category,
+                new: proposal.new_category,
+            }],
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    // Drop a pending category change proposal
+    pub fn cancel_category_change(
+        ctx: Context<CancelCategoryChange>,
+        player_id: u16,
+    ) -> Result<()> {
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        msg!("Category change for player {} cancelled by: {}", player_id, ctx.accounts.user.key());
+        Ok(())
+    }
+
+    // Check Player PDAs (remaining_accounts) against GameState.players; staff can repair mismatches
+    pub fn audit_players<'info>(
+        ctx: Context<'_, '_, 'info, 'info, AuditPlayers<'info>>,
+        repair: bool,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+        let auditor = ctx.accounts.user.key();
+
+        // Anyone can audit, only owner or staff can repair
+        if repair {
+            require!(
+                is_authorized(&auditor, game_state),
+                SportsError::UnauthorizedAccess
+            );
+        }
+
+        require!(
+            !ctx.remaining_accounts.is_empty(),
+            SportsError::InvalidAccountsProvided
+        );
+
+        let game_state_key = game_state.key();
+        let mut mismatches: Vec<PlayerAuditMismatch> = Vec::new();
+
+        for player_info in ctx.remaining_accounts.iter() {
+            let mut player_account: Account<'info, Player> = Account::try_from(player_info)?;
+
+            // The PDA must be derived from the id stored inside it
+            let expected_pda = Pubkey::find_program_address(
+                &[
+                    b"player",
+                    player_account.id.to_le_bytes().as_ref(),
+                    game_state_key.as_ref(),
+                    crate::ID.as_ref(),
+                ],
+                &crate::ID,
+            ).0;
+            if player_info.key() != expected_pda {
+                mismatches.push(PlayerAuditMismatch {
+                    player_id: player_account.id,
+                    kind: AuditMismatchKind::IdMismatch,
+                    pda_value: player_account.id as u32,
+                    summary_value: 0,
+                });
+                // Nothing deterministic to repair: the account lives at the wrong address
+                continue;
+            }
+
+            let found = audit_player(&player_account, &game_state.players);
+            if found.is_empty() {
+                continue;
+            }
+
+            if repair {
+                require!(player_info.is_writable, SportsError::InvalidAccountsProvided);
+                repair_player(&mut player_account, &mut game_state.players)?;
+                player_account.exit(&crate::ID)?;
+                msg!("Player {} repaired", player_account.id);
+            }
+
+            mismatches.extend(found);
+        }
+
+        msg!("Audited {} players, {} mismatches found", 
+            ctx.remaining_accounts.len(), 
+            mismatches.len()
+        );
+
+        emit!(PlayerAuditReport {
+            auditor,
+            players_checked: ctx.remaining_accounts.len() as u16,
+            mismatches,
+            repaired: repair,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
         Ok(())
     }
 
@@ -321,11 +640,82 @@ pub mod sports {
         msg!("User stake state initialized for {}", ctx.accounts.user.key());
         Ok(())
     }
-    pub fn buy_team(ctx: Context<BuyTeam>, package: TeamPackage, terms_accepted: bool) -> Result<()> {
+    // Read-only: probability of each athlete appearing in a package, returned with set_return_data.
+    // Pages of PackOddsPage::MAX_ENTRIES players starting at `offset` (index in GameState.players).
+    pub fn quote_pack_odds(ctx: Context<QuotePackOdds>, package_id: u16, offset: u16) -> Result<()> {
+        let players = &ctx.accounts.game_state.players;
+        require!(
+            (offset as usize) < players.len().max(1),
+            SportsError::InvalidAmount
+        );
+
+        let odds = compute_pack_odds(players, &ctx.accounts.package_config)?;
+        let page = encode_pack_odds_page(package_id, players, &odds, offset);
+        anchor_lang::solana_program::program::set_return_data(&page);
+
+        msg!("Pack odds for package {}: players {}..{} of {}", 
+            package_id, 
+            offset, 
+            (offset as usize + PackOddsPage::MAX_ENTRIES).min(players.len()), 
+            players.len()
+        );
+        Ok(())
+    }
+
+    // Vista para el frontend: estado de venta (agotado, fuera de ventana...) de un paquete
+    pub fn get_package_sale_status(
+        ctx: Context<GetPackageSaleStatus>,
+        package_id: u16,
+    ) -> Result<PackageSaleInfo> {
+        let now = Clock::get()?.unix_timestamp;
+        let package_config = &ctx.accounts.package_config;
+        Ok(PackageSaleInfo {
+            package_id,
+            status: package_config.sale_status(now),
+            price_usdc: package_config.price_for(ctx.accounts.game_state.sale_phase(now), &ctx.accounts.game_state.players, now),
+            sold: package_config.sold,
+            max_supply: package_config.max_supply,
+            remaining_supply: package_config.remaining_supply(),
+            sale_start: package_config.sale_start,
+            sale_end: package_config.sale_end,
+            now,
+        })
+    }
+
+    // presale_proof: Merkle proof of the buyer in the allowlist (empty in the public phase)
+    // payment_mint: game_state.mint_usdc, or an enabled AcceptedMint (passed as accepted_mint)
+    // max_price: most the buyer accepts to pay in micro-USDC (the price can move with the pricing curve)
+    // Regalo: con la cuenta `recipient` el NFT va a su ATA y queda como first_buyer; `user` paga USDC y rent.
+    // gift_message_hash: hash del mensaje del regalo (off-chain), solo se emite en el evento
+    // Patrocinio: si fee_payer != user, fee_payer debe ser un Sponsor habilitado; paga rent y fees y
+    // cobra su rent_recovery_fee_usdc al comprador (incluido en max_price). user solo firma el pago.
+    // coupon_code: código del cupón en claro; la cuenta `coupon` es el PDA de su hash
+    pub fn buy_team<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyTeam<'info>>,
+        package_id: u16,
+        terms_accepted: bool,
+        presale_proof: Vec<[u8; 32]>,
+        payment_mint: Pubkey,
+        max_price: u64,
+        gift_message_hash: Option<[u8; 32]>,
+        coupon_code: Option<String>,
+    ) -> Result<()> {
         
+        // Cambio de precio programado que ya entró en vigor
+        apply_due_price_change(
+            &mut ctx.accounts.package_config,
+            &mut ctx.accounts.game_state,
+            ctx.accounts.price_history.as_deref_mut().map(|price_history| &mut **price_history),
+            ctx.accounts.clock.unix_timestamp,
+        )?;
+
+        // Un referido solo se vincula en la primera compra (todas las compras de packs pasan por PackHistory)
+        let is_first_purchase = ctx.accounts.pack_history.total_packs == 0;
         let game_state = &mut ctx.accounts.game_state;
-        let team_account = &mut ctx.accounts.team_account;
+        let package_config = &ctx.accounts.package_config;
         let user_key = ctx.accounts.user.key();
+        let recipient_key = ctx.accounts.recipient.as_ref().map_or(user_key, |recipient| recipient.key());
+        let fee_payer_key = ctx.accounts.fee_payer.key();
         let clock = &ctx.accounts.clock;
 
         // Check if contract is paused
@@ -340,48 +730,108 @@ pub mod sports {
         // Verificar que hay un reporte abierto
         require!(game_state.is_report_open, SportsError::NoOpenReport);
 
-        // Validate package prices are reasonable (prevent overflow attacks)
-        let price_paid_usdc = package.price_usdc(game_state);
-        require!(
-            price_paid_usdc > 0 && price_paid_usdc <= 10_000_000_000, // Max $10,000
-            SportsError::InvalidPrice
-        );
+        // Presale: solo wallets del allowlist, hasta presale_wallet_cap packs cada una
+        let phase = game_state.sale_phase(clock.unix_timestamp);
+        let mut presale_root = [0u8; 32];
+        if phase == SalePhase::Presale {
+            presale_root = game_state.presale_merkle_root;
+            require!(
+                verify_presale_proof(&presale_proof, &game_state.presale_merkle_root, &user_key),
+                SportsError::NotOnPresaleAllowlist
+            );
+            let presale_purchases = ctx.accounts.presale_purchases.as_ref()
+                .ok_or(SportsError::InvalidAccountsProvided)?;
+            record_presale_purchase(
+                presale_purchases,
+                game_state,
+                &game_state.key(),
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.fee_payer.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+        }
 
-        // Validate sufficient players available before proceeding
-        let available_players: Vec<(usize, &PlayerSummary)> = game_state.players
-            .iter()
-            .enumerate()
-            .filter(|(_, p)| p.available_tokens > 0)
-            .collect();
+        // Package must be on sale, at a reasonable price, with enough players available
+        let price_paid_usdc = validate_package_sale(package_config, phase, &game_state.players, clock.unix_timestamp, 1)?;
+        validate_package_availability(&game_state.players, package_config)?;
 
-        // Check minimum players available for any package (5 players needed)
+        // Cupón opcional: el descuento se aplica antes del cobro y cuenta en el reporte
         require!(
-            available_players.len() >= 5,
-            SportsError::InsufficientPlayersAvailable
+            coupon_code.is_some() == ctx.accounts.coupon.is_some(),
+            SportsError::InvalidCoupon
         );
-
-        // For packages B and C, check premium players availability
-        match package {
-            TeamPackage::B => {
-                let premium_count = available_players.iter()
-                    .filter(|(_, p)| p.category == PlayerCategory::Silver || p.category == PlayerCategory::Gold)
-                    .count();
+        let (price_paid_usdc, coupon_discount_usdc) = match ctx.accounts.coupon.as_mut() {
+            Some(coupon) => {
+                // Solo quien conoce el código puede canjearlo
                 require!(
-                    premium_count >= 1,
-                    SportsError::InsufficientPremiumPlayers
+                    coupon_code.as_deref().map(coupon_code_hash) == Some(coupon.code_hash),
+                    SportsError::InvalidCoupon
                 );
-            },
-            TeamPackage::C => {
-                let premium_count = available_players.iter()
-                    .filter(|(_, p)| p.category == PlayerCategory::Silver || p.category == PlayerCategory::Gold)
-                    .count();
-                require!(
-                    premium_count >= 2,
-                    SportsError::InsufficientPremiumPlayers
-                );
-            },
-            TeamPackage::A => {}, // No premium requirements
-        }
+                let coupon_redemption = ctx.accounts.coupon_redemption.as_ref()
+                    .ok_or(SportsError::InvalidAccountsProvided)?;
+                let discount = redeem_coupon(
+                    coupon,
+                    coupon_redemption,
+                    &ctx.accounts.user.to_account_info(),
+                    &ctx.accounts.fee_payer.to_account_info(),
+                    &ctx.accounts.system_program.to_account_info(),
+                    package_id,
+                    price_paid_usdc,
+                    clock.unix_timestamp,
+                )?;
+                game_state.current_report_coupon_redemptions = game_state.current_report_coupon_redemptions
+                    .checked_add(1)
+                    .ok_or(SportsError::TokenOverflow)?;
+                game_state.current_report_coupon_discount = game_state.current_report_coupon_discount
+                    .checked_add(discount)
+                    .ok_or(SportsError::TokenOverflow)?;
+                emit!(CouponRedeemed {
+                    code_hash: coupon.code_hash,
+                    buyer: user_key,
+                    package_id,
+                    discount_usdc: discount,
+                    price_paid_usdc: price_paid_usdc - discount,
+                    report_id: game_state.current_report_id,
+                    timestamp: clock.unix_timestamp,
+                });
+                (price_paid_usdc - discount, discount)
+            }
+            None => (price_paid_usdc, 0),
+        };
+
+        // Compra patrocinada: solo sponsors habilitados, su fee se suma a lo que acepta pagar el comprador
+        let sponsor_fee_usdc = if fee_payer_key != user_key {
+            let sponsor = ctx.accounts.sponsor.as_ref()
+                .filter(|sponsor| sponsor.enabled)
+                .ok_or(SportsError::SponsorNotAllowed)?;
+            sponsor.rent_recovery_fee_usdc
+        } else {
+            0
+        };
+        require_max_price(
+            price_paid_usdc.checked_add(sponsor_fee_usdc).ok_or(SportsError::TokenOverflow)?,
+            max_price,
+        )?;
+
+        // Cobro en la stablecoin elegida: mint_usdc va a los acumulados de GameState,
+        // el resto a los de su AcceptedMint (nunca se mezclan)
+        let is_primary_mint = payment_mint == game_state.mint_usdc;
+        let amount_paid = if is_primary_mint {
+            price_paid_usdc
+        } else {
+            let accepted_mint = ctx.accounts.accepted_mint.as_ref()
+                .ok_or(SportsError::PaymentMintNotAccepted)?;
+            require!(accepted_mint.enabled, SportsError::PaymentMintNotAccepted);
+            require!(
+                ctx.accounts.program_usdc_account.key() == accepted_mint.vault,
+                SportsError::InvalidTokenAccount
+            );
+            scale_usdc_amount(price_paid_usdc, accepted_mint.decimals)?
+        };
+        let sponsor_fee_amount = match ctx.accounts.accepted_mint.as_ref().filter(|_| !is_primary_mint) {
+            Some(accepted_mint) => scale_usdc_amount(sponsor_fee_usdc, accepted_mint.decimals)?,
+            None => sponsor_fee_usdc,
+        };
 
         // Validate clock timestamp is reasonable (prevent time manipulation)
         require!(
@@ -405,870 +855,3451 @@ pub mod sports {
 
         // ============ FIN VALIDACIONES DE SEGURIDAD ============
 
-        // Obtener el report_id actual
-        let report_id = game_state.current_report_id;
-
-        // Seleccionar jugadores aleatorios usando Chainlink como entropía
-        // Validación estricta de cuentas (igual que en presale)
-        require!(
-            ctx.accounts.sol_usd_feed.key() == CHAINLINK_SOL_USD_FEED_DEVNET,
-            SportsError::InvalidPriceFeed
-        );
-        require!(
-            ctx.accounts.chainlink_program.key() == CHAINLINK_PROGRAM_ID,
-            SportsError::InvalidPriceFeed
-        );
-
-        let entropy = generate_entropy_with_chainlink(
-            &user_key,
-            clock,
+        // Seleccionar jugadores aleatorios con la fuente de entropía configurada en GameState
+        let entropy_seed = EntropySeed {
+            buyer: user_key,
+            slot: clock.slot,
+            unix_timestamp: clock.unix_timestamp,
+            next_team_id: game_state.next_team_id,
+        };
+        let entropy = resolve_entropy_source(
+            game_state,
             &ctx.accounts.sol_usd_feed,
             &ctx.accounts.chainlink_program,
-            game_state.next_team_id,
+            ctx.remaining_accounts,
+        )?
+        .entropy(&entropy_seed)?;
+
+        // Seleccionar jugadores, actualizar tokens y acumulados, inicializar el equipo
+        let packs_without_gold_before = ctx.accounts.pack_history.packs_without_gold;
+        let purchase = assign_team(
+            game_state,
+            &mut ctx.accounts.team_account,
+            package_config,
+            &mut ctx.accounts.pack_history,
+            user_key,
+            recipient_key,
+            if is_primary_mint { price_paid_usdc } else { 0 },
+            terms_accepted,
+            &entropy,
+            clock,
         )?;
-        let selected_indices = select_team_players(&available_players, &package, &entropy)?;
-        
-        // Actualizar tokens vendidos y obtener IDs
-        let player_ids = update_team_tokens(game_state, &selected_indices)?;
-        
-        // Crear el equipo
-        let team_id = game_state.next_team_id;
-        
-        // Validate team_id won't overflow and is reasonable
-        require!(
-            team_id > 0 && team_id < 1_000_000_000, // Prevent unreasonable team IDs
-            SportsError::TeamIdOverflow
-        );
-        
-        game_state.next_team_id = game_state.next_team_id.checked_add(1)
-            .ok_or(SportsError::TokenOverflow)?;
-
-        // Validate team_account PDA derivation matches expected team_id
-        let expected_team_pda = anchor_lang::prelude::Pubkey::find_program_address(
-            &[
-                b"team",
-                team_id.to_le_bytes().as_ref(),
-                game_state.key().as_ref(),
-                crate::ID.as_ref(),
-            ],
-            &crate::ID,
-        ).0;
-        
-        require!(
-            team_account.key() == expected_team_pda,
-            SportsError::InvalidAccountsProvided
-        );
-
-        // Actualizar acumulados del reporte actual
-        game_state.current_report_revenue = game_state.current_report_revenue
-            .checked_add(price_paid_usdc)
-            .ok_or(SportsError::TokenOverflow)?;
-        game_state.current_report_teams = game_state.current_report_teams
-            .checked_add(1)
-            .ok_or(SportsError::TokenOverflow)?;
-        game_state.current_report_tokens = game_state.current_report_tokens
-            .checked_add(package.total_players() as u32)
-            .ok_or(SportsError::TokenOverflow)?;
-
-        // Inicializar el equipo
-        team_account.first_buyer = user_key;
-        team_account.player_ids = player_ids.clone();
-        team_account.category = package.clone();
-        team_account.created_at = clock.unix_timestamp;
-        team_account.transition_timestamp = clock.unix_timestamp;
-        team_account.nft_mint = Pubkey::default(); // Will be set when NFT is minted
-        team_account.state = TeamState::Free;
-        team_account.team_id = team_id;
-        team_account.terms_accepted = terms_accepted;
-
-        // Log team purchase info
-        msg!("Team purchased by: {}", user_key);
-        msg!("Package: {:?}", package);
-        msg!("Selected player IDs: {:?}", team_account.player_ids);
-        msg!("Terms accepted: {}", terms_accepted);
-        msg!("Price paid (USDC): ${}.{:02}", 
-            price_paid_usdc / 1_000_000,
-            (price_paid_usdc % 1_000_000) / 10_000
-        );
-
-        
-
-        // Emitir evento TokenSold para cada jugador vendido
-        for player_id in &player_ids {
-            emit!(TokenSold {
-                player_id: *player_id,
-                team_id,
-                timestamp: clock.unix_timestamp,
-                buyer: user_key,
-                report_id,
-            });
+        if let Some(accepted_mint) = ctx.accounts.accepted_mint.as_mut().filter(|_| !is_primary_mint) {
+            accepted_mint.record_revenue(ctx.accounts.game_state.current_report_id, amount_paid)?;
         }
-        let player_ids_for_log = player_ids.clone();
-        // Crear team purchase para NFT minting
-        let _team_purchase = TeamPurchase {
+        ctx.accounts.purchase_receipt.set_inner(PurchaseReceipt {
+            team_id: purchase.team_id,
             buyer: user_key,
-            package,
-            player_ids,
-            purchase_timestamp: clock.unix_timestamp,
-            purchase_slot: clock.slot,
+            recipient: recipient_key,
+            package_id,
             price_paid_usdc,
-        };
-        
-        
-        // Emitir evento para que el cliente sepa qué jugadores fueron seleccionados
-        // y pueda llamar a mint_team_nft_instruction con los accounts correctos
-        msg!("Team purchase created - call mint_team_nft_instruction with player accounts for IDs: {:?}", player_ids_for_log);
+            payment_mint,
+            amount_paid,
+            report_id: ctx.accounts.game_state.current_report_id,
+            purchased_at: clock.unix_timestamp,
+            referrer: Pubkey::default(),
+            referral_commission: 0,
+            coupon: ctx.accounts.coupon.as_ref().map_or(Pubkey::default(), |coupon| coupon.key()),
+            coupon_discount_usdc,
+            presale_root,
+            pack_number: ctx.accounts.pack_history.total_packs,
+            packs_without_gold_before,
+            gold_guaranteed: purchase.gold_guaranteed,
+            rent_payer: fee_payer_key,
+        });
 
-        // MINT DEL NFT DEL EQUIPO - METADATA SIMPLIFICADA
-        // Usar solo datos esenciales para evitar límite de URI
-        let game_state = &ctx.accounts.game_state;
-        
-        // Metadata mínima pero útil
-        let essential_metadata = serde_json::json!({
-            "name": format!("Team FR v1 #{}", team_id),
-            "symbol": "TEAM FR",
-            "description": format!("Team with {} players from package {:?}", 
-                team_account.player_ids.len(), team_account.category),
-            "image": format!("{}?ids={}", game_state.nft_image_url, 
-                team_account.player_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")),
-            "attributes": [
-                {
-                    "trait_type": "Package",
-                    "value": format!("{:?}", team_account.category)
-                },
-                {
-                    "trait_type": "Team ID",
-                    "value": team_id
+        // Referidos: vincular en la primera compra y acumular la comisión (solo pagos en mint_usdc).
+        // La cuenta referral es obligatoria: un comprador vinculado no puede esquivar la comisión
+        let bound_referrer = bind_referral(
+            &ctx.accounts.referral,
+            ctx.accounts.referrer_account.as_deref(),
+            ctx.accounts.referrer_referral.as_ref().map(|info| info.as_ref()),
+            is_first_purchase,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.fee_payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.game_state.key(),
+            clock.unix_timestamp,
+        )?;
+        if let Some((referrer, newly_bound)) = bound_referrer {
+            if newly_bound {
+                if let Some(referrer_account) = ctx.accounts.referrer_account.as_mut() {
+                    referrer_account.referred_buyers = referrer_account.referred_buyers.saturating_add(1);
                 }
-            ]
-        });
-        
-        // Convertir a string compacto
-        let metadata_json = serde_json::to_string(&essential_metadata)
-            .unwrap_or_else(|_| "Error serializing metadata".to_string());
-        
-        msg!("✅ Team NFT metadata: {}", metadata_json);
-        
-        // MINT REAL DEL NFT
-        // 1. Mint 1 token al usuario usando game_state como autoridad
-        let mint_accounts = token::MintTo {
-            mint: ctx.accounts.nft_mint.to_account_info(),
-            to: ctx.accounts.user_nft_account.to_account_info(),
-            authority: ctx.accounts.game_state.to_account_info(),
-        };
-        
-        let game_state_key = ctx.accounts.game_state.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[
-            b"game_state",
-            crate::ID.as_ref(),
-            &[ctx.bumps.game_state],
-        ]];
-        
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            mint_accounts,
-            signer_seeds,
-        );
-        token::mint_to(cpi_ctx, 1)?;
-        
-        // 2. Crear metadata del NFT con datos esenciales
-        let creators = Some(vec![
-            Creator {
-                address: game_state.nft_update_authority,
-                verified: false,
-                share: 100,
             }
-        ]);
+            let commission = referral_commission(price_paid_usdc, ctx.accounts.game_state.referral_commission_bps);
+            if is_primary_mint && commission > 0 {
+                let referrer_account = ctx.accounts.referrer_account.as_mut()
+                    .filter(|account| account.referrer == referrer)
+                    .ok_or(SportsError::InvalidReferrer)?;
+                referrer_account.accrued = referrer_account.accrued
+                    .checked_add(commission)
+                    .ok_or(SportsError::TokenOverflow)?;
+                let game_state = &mut ctx.accounts.game_state;
+                game_state.referral_commissions_owed = game_state.referral_commissions_owed
+                    .checked_add(commission)
+                    .ok_or(SportsError::TokenOverflow)?;
+                game_state.reserve_treasury(commission)?;
+                ctx.accounts.purchase_receipt.referrer = referrer;
+                ctx.accounts.purchase_receipt.referral_commission = commission;
+                emit!(ReferralCommissionAccrued {
+                    referrer,
+                    buyer: user_key,
+                    team_id: purchase.team_id,
+                    amount: commission,
+                    timestamp: clock.unix_timestamp,
+                });
+            }
+        }
 
-        // OPCIÓN 1: Almacenar metadata en el campo 'name' del DataV2
-        // Esto garantiza que toda la información esté completamente on-chain
-        let data = DataV2 {
-            name: format!("Team FR v1 #{}", team_id), // Nombre limpio para visualización
-            symbol: "TEAM FR v1".to_string(),
-            uri: format!("{}?ids={}", game_state.nft_image_url, 
-                team_account.player_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")), // URI simple con imagen
-            seller_fee_basis_points: 0,
-            creators: creators.clone(),
-            collection: None,
-            uses: None,
-        };
-
-        CreateMetadataAccountV3Cpi::new(
-            &ctx.accounts.metadata_program.to_account_info(),
-            mpl_token_metadata::instructions::CreateMetadataAccountV3CpiAccounts {
-                metadata: &ctx.accounts.metadata_account.to_account_info(),
-                mint: &ctx.accounts.nft_mint.to_account_info(),
-                mint_authority: &ctx.accounts.game_state.to_account_info(),
-                payer: &ctx.accounts.user.to_account_info(),
-                update_authority: (&ctx.accounts.update_authority.to_account_info(), false),
-                system_program: &ctx.accounts.system_program.to_account_info(),
-                rent: Some(&ctx.accounts.rent.to_account_info()),
+        // MINT DEL NFT DEL EQUIPO (ATA del destinatario, rent a cargo de fee_payer)
+        // El programa de ATA rechaza una dirección que no sea la ATA de (recipient, nft_mint)
+        anchor_spl::associated_token::create(CpiContext::new(
+            ctx.accounts.associated_token_program.to_account_info(),
+            anchor_spl::associated_token::Create {
+                payer: ctx.accounts.fee_payer.to_account_info(),
+                associated_token: ctx.accounts.user_nft_account.to_account_info(),
+                authority: ctx.accounts.recipient.as_ref()
+                    .map_or(ctx.accounts.user.to_account_info(), |recipient| recipient.to_account_info()),
+                mint: ctx.accounts.nft_mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
             },
-            mpl_token_metadata::instructions::CreateMetadataAccountV3InstructionArgs {
-                data,
-                is_mutable: true,
-                collection_details: None,
-            }
-        )
-        .invoke_signed(signer_seeds)?;
-        
-        // Guardar el mint address en el team_account
-        team_account.nft_mint = ctx.accounts.nft_mint.key();
-        
-        
-        msg!("NFT minted successfully! Mint address: {}", ctx.accounts.nft_mint.key());
-        msg!("Metadata account: {}", ctx.accounts.metadata_account.key());
-        //msg!("Metadata URI: {}", metadata_uri);
+        ))?;
+        let nft_accounts = TeamNftAccounts {
+            game_state: ctx.accounts.game_state.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            user_nft_account: ctx.accounts.user_nft_account.to_account_info(),
+            metadata_account: ctx.accounts.metadata_account.to_account_info(),
+            metadata_program: ctx.accounts.metadata_program.to_account_info(),
+            update_authority: ctx.accounts.update_authority.to_account_info(),
+            payer: ctx.accounts.fee_payer.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        mint_team_nft(
+            &ctx.accounts.game_state,
+            ctx.bumps.game_state,
+            &mut ctx.accounts.team_account,
+            &ctx.accounts.package_config.name,
+            nft_accounts,
+        )?;
+        // Un regalo no lo firma su destinatario, así que no se delega
+        let nft_delegate = if ctx.accounts.recipient.is_none() {
+            Some(delegate_team_nft(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.user_nft_account.to_account_info(),
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.game_state.to_account_info(),
+            )?)
+        } else {
+            None
+        };
+
+        emit!(TeamPurchased {
+            team_id: purchase.team_id,
+            buyer: user_key,
+            package_id,
+            player_ids: purchase.player_ids.clone(),
+            price_paid_usdc,
+            payment_mint,
+            amount_paid,
+            report_id: ctx.accounts.game_state.current_report_id,
+            gold_guaranteed: purchase.gold_guaranteed,
+            recipient: recipient_key,
+            gift_message_hash,
+            nft_delegate,
+            timestamp: purchase.purchase_timestamp,
+        });
+
+        msg!("Team ID: {}, package {}, State: {:?}", purchase.team_id, package_id, TeamState::Free);
+        record_package_sale(&mut ctx.accounts.package_config, 1)?;
+        // Transferir pago (USDC o stablecoin aceptada, SPL Token o Token-2022)
+        transfer_payment_checked(
+            &ctx.accounts.user_usdc_account.to_account_info(),
+            &ctx.accounts.program_usdc_account.to_account_info(),
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.payment_mint_account,
+            &ctx.accounts.payment_token_program.to_account_info(),
+            amount_paid,
+            &[],
+        )?;
 
-        msg!("Team ID: {}, State: {:?}", team_id, TeamState::Free);
-        // Transferir pago USDC
-        transfer_usdc_payment(ctx, price_paid_usdc)?;
+        // Fee de recuperación de rent para el sponsor (no cuenta como ingreso del reporte)
+        if fee_payer_key != user_key {
+            if sponsor_fee_amount > 0 {
+                let sponsor_fee_account = ctx.accounts.sponsor_fee_account.as_ref()
+                    .ok_or(SportsError::InvalidAccountsProvided)?;
+                transfer_payment_checked(
+                    &ctx.accounts.user_usdc_account.to_account_info(),
+                    &sponsor_fee_account.to_account_info(),
+                    &ctx.accounts.user.to_account_info(),
+                    &ctx.accounts.payment_mint_account,
+                    &ctx.accounts.payment_token_program.to_account_info(),
+                    sponsor_fee_amount,
+                    &[],
+                )?;
+            }
+            let sponsor = ctx.accounts.sponsor.as_mut().ok_or(SportsError::SponsorNotAllowed)?;
+            sponsor.purchases_sponsored = sponsor.purchases_sponsored.saturating_add(1);
+            sponsor.fees_collected_usdc = sponsor.fees_collected_usdc.saturating_add(sponsor_fee_usdc);
+            emit!(PurchaseSponsored {
+                sponsor: fee_payer_key,
+                buyer: user_key,
+                team_id: purchase.team_id,
+                fee_usdc: sponsor_fee_usdc,
+                payment_mint,
+                fee_amount: sponsor_fee_amount,
+                timestamp: clock.unix_timestamp,
+            });
+        }
         Ok(())
     }
 
-    pub fn update_team_prices(
-        ctx: Context<UpdateTeamPrices>,
-        price_a: u64,
-        price_b: u64,
-        price_c: u64,
+    // Comprar un equipo pagando en SOL al precio SOL/USD de Chainlink.
+    // max_lamports: máximo que el comprador acepta pagar (slippage entre cotización y ejecución)
+    pub fn buy_team_with_sol<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyTeamWithSol<'info>>,
+        package_id: u16,
+        terms_accepted: bool,
+        max_lamports: u64,
     ) -> Result<()> {
+        // Cambio de precio programado que ya entró en vigor
+        apply_due_price_change(
+            &mut ctx.accounts.package_config,
+            &mut ctx.accounts.game_state,
+            ctx.accounts.price_history.as_deref_mut().map(|price_history| &mut **price_history),
+            Clock::get()?.unix_timestamp,
+        )?;
+
         let game_state = &mut ctx.accounts.game_state;
+        let package_config = &ctx.accounts.package_config;
+        let user_key = ctx.accounts.user.key();
+        let clock = Clock::get()?;
 
-        // Only owner or staff can update prices
-        require!(
-            is_authorized(&ctx.accounts.user.key(), game_state),
-            SportsError::UnauthorizedAccess
-        );
+        // Check if contract is paused
+        require_not_paused(game_state)?;
 
-        // Validate prices are reasonable (non-zero and less than $1000)
-        require!(
-            price_a > 0 && price_a <= 1_000_000_000, // Max $1000
-            SportsError::InvalidPrice
-        );
-        require!(
-            price_b > 0 && price_b <= 1_000_000_000,
-            SportsError::InvalidPrice
-        );
+        // Verify terms and conditions are accepted
         require!(
-            price_c > 0 && price_c <= 1_000_000_000,
-            SportsError::InvalidPrice
-        );
-
-        game_state.team_price_a = price_a;
-        game_state.team_price_b = price_b;
-        game_state.team_price_c = price_c;
-
-        msg!("Team prices updated - A: ${}, B: ${}, C: ${}", 
-            price_a / 1_000_000,
-            price_b / 1_000_000,
-            price_c / 1_000_000
+            terms_accepted,
+            SportsError::TermsNotAccepted
         );
 
-        Ok(())
-    }
+        // Verificar que hay un reporte abierto
+        require!(game_state.is_report_open, SportsError::NoOpenReport);
 
-    pub fn set_nft_image_url(
-        ctx: Context<SetNftImageUrl>,
-        new_url: String,
-    ) -> Result<()> {
-        let game_state = &mut ctx.accounts.game_state;
+        // La presale se paga solo en USDC con buy_team
+        require_public_sale(game_state, clock.unix_timestamp)?;
+        let price_usdc = validate_package_sale(package_config, SalePhase::Public, &game_state.players, clock.unix_timestamp, 1)?;
+        validate_package_availability(&game_state.players, package_config)?;
 
-        // Only owner or staff can update
+        // Convertir el precio USDC a lamports con el feed SOL/USD
+        let sol_price_usdc = get_current_sol_usdc_price_from_chainlink(
+            &ctx.accounts.sol_usd_feed,
+            &ctx.accounts.chainlink_program,
+            game_state,
+            clock.unix_timestamp,
+        )?;
+        let price_paid_lamports = usdc_to_lamports(price_usdc, sol_price_usdc)?;
         require!(
-            is_authorized(&ctx.accounts.user.key(), game_state),
-            SportsError::UnauthorizedAccess
+            price_paid_lamports <= max_lamports,
+            SportsError::SolPriceSlippage
         );
 
-        // Basic sanity: avoid empty
-        require!(
-            !new_url.is_empty(),
-            SportsError::InvalidGameState
-        );
+        let entropy_seed = EntropySeed {
+            buyer: user_key,
+            slot: clock.slot,
+            unix_timestamp: clock.unix_timestamp,
+            next_team_id: game_state.next_team_id,
+        };
+        let entropy = resolve_entropy_source(
+            game_state,
+            &ctx.accounts.sol_usd_feed,
+            &ctx.accounts.chainlink_program,
+            ctx.remaining_accounts,
+        )?
+        .entropy(&entropy_seed)?;
+
+        // USDC revenue stays untouched (price 0); the lamports go to their own accumulator
+        let packs_without_gold_before = ctx.accounts.pack_history.packs_without_gold;
+        let purchase = assign_team(
+            game_state,
+            &mut ctx.accounts.team_account,
+            package_config,
+            &mut ctx.accounts.pack_history,
+            user_key,
+            user_key,
+            0,
+            terms_accepted,
+            &entropy,
+            &clock,
+        )?;
+        game_state.current_report_sol_revenue = game_state.current_report_sol_revenue
+            .checked_add(price_paid_lamports)
+            .ok_or(SportsError::TokenOverflow)?;
+        ctx.accounts.purchase_receipt.set_inner(PurchaseReceipt {
+            team_id: purchase.team_id,
+            buyer: user_key,
+            recipient: user_key,
+            package_id,
+            price_paid_usdc: price_usdc,
+            payment_mint: NATIVE_SOL_MINT,
+            amount_paid: price_paid_lamports,
+            report_id: ctx.accounts.game_state.current_report_id,
+            purchased_at: clock.unix_timestamp,
+            pack_number: ctx.accounts.pack_history.total_packs,
+            packs_without_gold_before,
+            gold_guaranteed: purchase.gold_guaranteed,
+            rent_payer: user_key,
+            ..Default::default()
+        });
 
-        game_state.nft_image_url = new_url.clone();
-        msg!("NFT image URL updated to: {}", new_url);
+        let nft_accounts = TeamNftAccounts {
+            game_state: ctx.accounts.game_state.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            user_nft_account: ctx.accounts.user_nft_account.to_account_info(),
+            metadata_account: ctx.accounts.metadata_account.to_account_info(),
+            metadata_program: ctx.accounts.metadata_program.to_account_info(),
+            update_authority: ctx.accounts.update_authority.to_account_info(),
+            payer: ctx.accounts.user.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        mint_team_nft(
+            &ctx.accounts.game_state,
+            ctx.bumps.game_state,
+            &mut ctx.accounts.team_account,
+            &ctx.accounts.package_config.name,
+            nft_accounts,
+        )?;
+        let nft_delegate = delegate_team_nft(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.user_nft_account.to_account_info(),
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.game_state.to_account_info(),
+        )?;
+
+        emit!(TeamPurchasedWithSol {
+            team_id: purchase.team_id,
+            buyer: user_key,
+            package_id,
+            player_ids: purchase.player_ids.clone(),
+            price_usdc,
+            sol_price_usdc,
+            price_paid_lamports,
+            report_id: ctx.accounts.game_state.current_report_id,
+            gold_guaranteed: purchase.gold_guaranteed,
+            nft_delegate: Some(nft_delegate),
+            timestamp: purchase.purchase_timestamp,
+        });
+
+        msg!("Team ID: {}, package {}, paid {} lamports", purchase.team_id, package_id, price_paid_lamports);
+        record_package_sale(&mut ctx.accounts.package_config, 1)?;
+        // Transferir el pago en SOL a la tesorería
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: ctx.accounts.sol_treasury.to_account_info(),
+                },
+            ),
+            price_paid_lamports,
+        )?;
         Ok(())
     }
 
-    // Stake a team (move NFT to contract and set to WarmingUp)
-    pub fn stake_team(
-        ctx: Context<StakeTeam>,
-        team_id: u64,
+    // Buy `count` teams of the same package in one transaction with a single USDC transfer.
+    // remaining_accounts: [entropy account, if the source needs one] followed by, per team,
+    // [team_account, nft_mint, metadata_account, user_nft_account, purchase_receipt] (all writable, uninitialized).
+    pub fn buy_teams<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyTeams<'info>>,
+        package_id: u16,
+        count: u8,
+        terms_accepted: bool,
+        max_price: u64,                  // Per pack, in micro-USDC
     ) -> Result<()> {
-        let team_account = &mut ctx.accounts.team_account;
-        let clock = &ctx.accounts.clock;
+        // Cambio de precio programado que ya entró en vigor
+        apply_due_price_change(
+            &mut ctx.accounts.package_config,
+            &mut ctx.accounts.game_state,
+            ctx.accounts.price_history.as_deref_mut().map(|price_history| &mut **price_history),
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let user_key = ctx.accounts.user.key();
+        let clock = Clock::get()?;
 
         // Check if contract is paused
         require_not_paused(&ctx.accounts.game_state)?;
 
-        // Verify team ID matches
+        // Verify terms and conditions are accepted
         require!(
-            team_account.team_id == team_id,
-            SportsError::InvalidTeamId
+            terms_accepted,
+            SportsError::TermsNotAccepted
         );
 
-        // Verify team is in Free state
+        // Verificar que hay un reporte abierto
+        require!(ctx.accounts.game_state.is_report_open, SportsError::NoOpenReport);
+
         require!(
-            team_account.state == TeamState::Free,
-            SportsError::InvalidTeamState
+            count > 0 && count as usize <= BuyTeams::MAX_TEAMS,
+            SportsError::InvalidAmount
         );
 
-        // Verify user actually holds the NFT (dynamic ownership check)
+        let entropy_accounts = ctx.accounts.game_state.entropy_source.needs_account() as usize;
+        let team_accounts = ctx.remaining_accounts
+            .get(entropy_accounts..)
+            .ok_or(SportsError::InvalidAccountsProvided)?;
         require!(
-            ctx.accounts.user_nft_account.amount == 1,
-            SportsError::UserDoesNotOwnNft
+            team_accounts.len() == count as usize * BuyTeams::ACCOUNTS_PER_TEAM,
+            SportsError::InvalidAccountsProvided
         );
 
-        // Transfer NFT from user to program
-        let transfer_accounts = Transfer {
-            from: ctx.accounts.user_nft_account.to_account_info(),
-            to: ctx.accounts.program_nft_account.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-
-        let cpi_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            transfer_accounts,
-        );
+        require_public_sale(&ctx.accounts.game_state, clock.unix_timestamp)?;
+        validate_package_sale(
+            &ctx.accounts.package_config,
+            SalePhase::Public,
+            &ctx.accounts.game_state.players,
+            clock.unix_timestamp,
+            count as u32,
+        )?;
 
-        token::transfer(cpi_ctx, 1)?;
+        let entropy_source = resolve_entropy_source(
+            &ctx.accounts.game_state,
+            &ctx.accounts.sol_usd_feed,
+            &ctx.accounts.chainlink_program,
+            ctx.remaining_accounts,
+        )?;
 
-        // Update team state to WarmingUp
-        team_account.state = TeamState::WarmingUp;
-        team_account.transition_timestamp = clock.unix_timestamp;
+        let game_state_key = ctx.accounts.game_state.key();
+        let game_state_info = ctx.accounts.game_state.to_account_info();
+        let mut team_ids = Vec::with_capacity(count as usize);
+        let mut player_ids = Vec::with_capacity(count as usize);
+        let mut prices_paid_usdc = Vec::with_capacity(count as usize);
+        let mut total_paid_usdc = 0u64;
+        let mut gold_guarantees = 0u8;
+
+        for accounts in team_accounts.chunks_exact(BuyTeams::ACCOUNTS_PER_TEAM) {
+            let [team_info, nft_mint, metadata_account, user_nft_account, purchase_receipt] = accounts else {
+                return err!(SportsError::InvalidAccountsProvided);
+            };
+            let game_state = &mut ctx.accounts.game_state;
+            validate_package_availability(&game_state.players, &ctx.accounts.package_config)?;
 
-        // Update user stake state (list of team IDs)
-        let user_stake_state = &mut ctx.accounts.user_stake_state;
-        
-        // Initialize if first time
-        if user_stake_state.user == Pubkey::default() {
-            user_stake_state.user = ctx.accounts.user.key();
-            user_stake_state.staked_team_ids = Vec::new();
+            // Con pricing_curve cada pack se cobra con el stock que queda antes de abrirlo,
+            // igual que una secuencia de buy_team
+            let price_paid_usdc = ctx.accounts.package_config.price_for(SalePhase::Public, &game_state.players, clock.unix_timestamp);
+            require!(
+                price_paid_usdc > 0 && price_paid_usdc <= MAX_PACKAGE_PRICE_USDC,
+                SportsError::InvalidPrice
+            );
+            require_max_price(price_paid_usdc, max_price)?;
+            total_paid_usdc = total_paid_usdc
+                .checked_add(price_paid_usdc)
+                .ok_or(SportsError::TokenOverflow)?;
+
+            // Entropía distinta por equipo: next_team_id cambia en cada iteración
+            let team_id = game_state.next_team_id;
+            let entropy = entropy_source.entropy(&EntropySeed {
+                buyer: user_key,
+                slot: clock.slot,
+                unix_timestamp: clock.unix_timestamp,
+                next_team_id: team_id,
+            })?;
+
+            // Crear las cuentas que buy_team inicializa con `init`
+            create_team_account(
+                team_info,
+                team_id,
+                &game_state_key,
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+            create_team_nft_mint(
+                nft_mint,
+                team_id,
+                &game_state_key,
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+            )?;
+            anchor_spl::associated_token::create(CpiContext::new(
+                ctx.accounts.associated_token_program.to_account_info(),
+                anchor_spl::associated_token::Create {
+                    payer: ctx.accounts.user.to_account_info(),
+                    associated_token: user_nft_account.clone(),
+                    authority: ctx.accounts.user.to_account_info(),
+                    mint: nft_mint.clone(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                },
+            ))?;
+
+            let mut team_account = Account::<Team>::try_from(team_info)?;
+            let packs_without_gold_before = ctx.accounts.pack_history.packs_without_gold;
+            let purchase = assign_team(
+                game_state,
+                &mut team_account,
+                &ctx.accounts.package_config,
+                &mut ctx.accounts.pack_history,
+                user_key,
+                user_key,
+                price_paid_usdc,
+                terms_accepted,
+                &entropy,
+                &clock,
+            )?;
+
+            let nft_accounts = TeamNftAccounts {
+                game_state: game_state_info.clone(),
+                nft_mint: nft_mint.clone(),
+                user_nft_account: user_nft_account.clone(),
+                metadata_account: metadata_account.clone(),
+                metadata_program: ctx.accounts.metadata_program.to_account_info(),
+                update_authority: ctx.accounts.update_authority.to_account_info(),
+                payer: ctx.accounts.user.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            };
+            mint_team_nft(
+                &ctx.accounts.game_state,
+                ctx.bumps.game_state,
+                &mut team_account,
+                &ctx.accounts.package_config.name,
+                nft_accounts,
+            )?;
+            team_account.exit(&crate::ID)?;
+            delegate_team_nft(
+                &ctx.accounts.token_program.to_account_info(),
+                user_nft_account,
+                &ctx.accounts.user.to_account_info(),
+                &game_state_info,
+            )?;
+            create_purchase_receipt(
+                purchase_receipt,
+                &PurchaseReceipt {
+                    team_id,
+                    buyer: user_key,
+                    recipient: user_key,
+                    package_id,
+                    price_paid_usdc,
+                    payment_mint: ctx.accounts.game_state.mint_usdc,
+                    amount_paid: price_paid_usdc,
+                    report_id: ctx.accounts.game_state.current_report_id,
+                    purchased_at: clock.unix_timestamp,
+                    pack_number: ctx.accounts.pack_history.total_packs,
+                    packs_without_gold_before,
+                    gold_guaranteed: purchase.gold_guaranteed,
+                    rent_payer: user_key,
+                    ..Default::default()
+                },
+                &game_state_key,
+                &ctx.accounts.user.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+            )?;
+
+            gold_guarantees += purchase.gold_guaranteed as u8;
+            team_ids.push(purchase.team_id);
+            player_ids.push(purchase.player_ids);
+            prices_paid_usdc.push(price_paid_usdc);
         }
 
-        // Add team ID to user's staked teams list
-        if !user_stake_state.staked_team_ids.contains(&team_id) {
-            user_stake_state.staked_team_ids.push(team_id);
-        }
+        record_package_sale(&mut ctx.accounts.package_config, count as u32)?;
 
-        // Initialize individual team stake state
-        let team_stake_state = &mut ctx.accounts.team_stake_state;
-        team_stake_state.user = ctx.accounts.user.key();
-        team_stake_state.team_id = team_id;
-        team_stake_state.state = TeamState::WarmingUp;
-        team_stake_state.transition_timestamp = clock.unix_timestamp;
-        team_stake_state.rewards_earned = 0;
+        // Un solo pago USDC por el total
+        transfer_usdc_payment(
+            ctx.accounts.user_usdc_account.to_account_info(),
+            ctx.accounts.program_usdc_account.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            total_paid_usdc,
+        )?;
 
-        msg!("Team {} staked by {}, NFT transferred to program, now in WarmingUp state", 
-            team_id, 
-            ctx.accounts.user.key()
+        msg!("{} teams purchased by: {} (package {}), total ${}.{:02}", 
+            count, 
+            user_key, 
+            package_id, 
+            total_paid_usdc / 1_000_000, 
+            (total_paid_usdc % 1_000_000) / 10_000
         );
-        
-        emit!(TeamStartedWarmup {
-            team_id,
+
+        emit!(TeamsPurchased {
+            buyer: user_key,
+            package_id,
+            team_ids,
+            player_ids,
+            prices_paid_usdc,
+            total_paid_usdc,
+            report_id: ctx.accounts.game_state.current_report_id,
+            gold_guarantees,
+            nft_delegate: Some(game_state_key),
             timestamp: clock.unix_timestamp,
         });
-
         Ok(())
     }
 
-    // Withdraw a team (initiate withdrawal process or complete if 24h passed)
-    pub fn withdraw_team(
-        ctx: Context<WithdrawTeam>,
-        team_id: u64,
+    // Draft: pay and reserve Draft::OFFERED weighted-random athletes (tokens held in escrow)
+    pub fn start_draft<'info>(
+        ctx: Context<'_, '_, 'info, 'info, StartDraft<'info>>,
+        package_id: u16,
+        terms_accepted: bool,
+        max_price: u64,
     ) -> Result<()> {
-        let team_account = &mut ctx.accounts.team_account;
-        let clock = &ctx.accounts.clock;
-        let time_lock = ctx.accounts.game_state.time_lock;
-        // Anti re-init guard for init_if_needed: if already initialized, must belong to signer
-        if ctx.accounts.team_stake_state.user != Pubkey::default() {
-            require!(
-                ctx.accounts.team_stake_state.user == ctx.accounts.user.key(),
-                SportsError::UnauthorizedAccess
-            );
-        }
+        // Cambio de precio programado que ya entró en vigor
+        apply_due_price_change(
+            &mut ctx.accounts.package_config,
+            &mut ctx.accounts.game_state,
+            ctx.accounts.price_history.as_deref_mut().map(|price_history| &mut **price_history),
+            Clock::get()?.unix_timestamp,
+        )?;
+
+        let game_state = &mut ctx.accounts.game_state;
+        let package_config = &ctx.accounts.package_config;
+        let user_key = ctx.accounts.user.key();
+        let clock = Clock::get()?;
+
         // Check if contract is paused
-        require_not_paused(&ctx.accounts.game_state)?;
+        require_not_paused(game_state)?;
 
-        // Verify team belongs to user
+        // Verify terms and conditions are accepted
         require!(
-            team_account.first_buyer == ctx.accounts.user.key(),
-            SportsError::UnauthorizedAccess
+            terms_accepted,
+            SportsError::TermsNotAccepted
         );
 
-        // Verify team ID matches
+        // Verificar que hay un reporte abierto
+        require!(game_state.is_report_open, SportsError::NoOpenReport);
+
+        // Sin ledger cargado, el saldo de la tesorería no distingue este escrow (ver seed_treasury_ledger)
+        require!(game_state.treasury_ledger_seeded, SportsError::TreasuryLedgerNotSeeded);
+
         require!(
-            team_account.team_id == team_id,
-            SportsError::InvalidTeamId
+            package_config.slot_count as usize == Draft::PICKS,
+            SportsError::InvalidPackage
         );
+        require_public_sale(game_state, clock.unix_timestamp)?;
+        let price_paid_usdc = validate_package_sale(package_config, SalePhase::Public, &game_state.players, clock.unix_timestamp, 1)?;
+        require_max_price(price_paid_usdc, max_price)?;
 
+        // La oferta usa los mínimos del paquete más slots generales extra
+        let mut offer_config = package_config.clone().into_inner();
+        offer_config.slot_count = Draft::OFFERED as u8;
+        validate_package_availability(&game_state.players, &offer_config)?;
 
-        match team_account.state {
-            TeamState::OnField => {
-                // Initiate withdrawal process
-                team_account.state = TeamState::ToWithdraw;
-                team_account.transition_timestamp = clock.unix_timestamp;
+        let entropy = resolve_entropy_source(
+            game_state,
+            &ctx.accounts.sol_usd_feed,
+            &ctx.accounts.chainlink_program,
+            ctx.remaining_accounts,
+        )?
+        .entropy(&EntropySeed {
+            buyer: user_key,
+            slot: clock.slot,
+            unix_timestamp: clock.unix_timestamp,
+            next_team_id: game_state.next_team_id,
+        })?;
 
-                msg!("Team {} withdrawal initiated, now in ToWithdraw state", team_id);
-                emit!(TeamStartedWithdrawal {
-                    team_id,
-                    timestamp: clock.unix_timestamp,
-                });
-            },
-            TeamState::ToWithdraw => {
-                // Check if 24 hours have passed
-                let time_elapsed = clock.unix_timestamp - team_account.transition_timestamp;
-                
-                if time_elapsed >= time_lock {
-                    // Complete withdrawal - transfer NFT back to user
-                    let transfer_accounts = Transfer {
-                        from: ctx.accounts.program_nft_account.to_account_info(),
-                        to: ctx.accounts.user_nft_account.to_account_info(),
-                        authority: ctx.accounts.program_nft_authority.to_account_info(),
-                    };
+        let available_players: Vec<(usize, &PlayerSummary)> = game_state.players
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.available_tokens > 0)
+            .collect();
+        let offered_indices = select_team_players(&available_players, &offer_config, &entropy)?;
 
-                    // Seeds para firmar con el PDA
-                    let game_state_key = ctx.accounts.game_state.key();
-                    let authority_seeds = &[
-                        b"nft_authority",
-                        game_state_key.as_ref(),
-                        &[ctx.bumps.program_nft_authority],
-                    ];
-                    let signer_seeds = &[&authority_seeds[..]];
+        // Escrow: los tokens ofrecidos salen de available_tokens hasta finish_draft
+        let offered_player_ids = update_team_tokens(game_state, &offered_indices)?;
 
-                    let cpi_ctx = CpiContext::new_with_signer(
-                        ctx.accounts.token_program.to_account_info(),
-                        transfer_accounts,
-                        signer_seeds,
-                    );
+        let deadline = clock.unix_timestamp
+            .checked_add(Draft::DURATION_SECS)
+            .ok_or(SportsError::TokenOverflow)?;
+        ctx.accounts.draft.set_inner(Draft {
+            buyer: user_key,
+            package_id,
+            offered_player_ids: offered_player_ids.clone(),
+            category_minimums: offer_config.category_minimums.clone(),
+            price_paid_usdc,
+            terms_accepted,
+            created_at: clock.unix_timestamp,
+            deadline,
+        });
+        record_package_sale(&mut ctx.accounts.package_config, 1)?;
 
-                    token::transfer(cpi_ctx, 1)?;
-                    
-                    team_account.state = TeamState::Free;
-                    team_account.transition_timestamp = clock.unix_timestamp;
-                    
-                    // Remove team ID from user stake state
-                    let user_stake_state = &mut ctx.accounts.user_stake_state;
-                    user_stake_state.staked_team_ids.retain(|&id| id != team_id);
-                    
-                    // Close team_stake_state account explicitly (credit lamports to user)
-                    ctx.accounts.team_stake_state.close(ctx.accounts.user.to_account_info())?;
-                    
-                    msg!("Team {} withdrawal completed, NFT returned to owner", team_id);
-                    emit!(TeamWithdrawn {
-                        team_id,
-                        timestamp: clock.unix_timestamp,
-                    });
-                } else {
-                    let time_remaining = time_lock - time_elapsed;
-                    msg!("Team {} still in withdrawal period. Time remaining: {} seconds", 
-                        team_id, 
-                        time_remaining
-                    );
-                    return Err(SportsError::WaitingPeriodNotComplete.into());
-                }
-            },
-            _ => {
-                // Team is not in a valid state for withdrawal
-                return Err(SportsError::InvalidTeamState.into());
-            }
-        }
+        transfer_usdc_payment(
+            ctx.accounts.user_usdc_account.to_account_info(),
+            ctx.accounts.program_usdc_account.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            price_paid_usdc,
+        )?;
+
+        msg!("Draft started by: {}, offered: {:?}, deadline: {}", 
+            user_key, 
+            offered_player_ids, 
+            deadline
+        );
 
+        emit!(DraftStarted {
+            buyer: user_key,
+            package_id,
+            offered_player_ids,
+            price_paid_usdc,
+            deadline,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
-    // Refresh team status based on time elapsed
-    pub fn refresh_team_status(
-        ctx: Context<RefreshTeamStatus>,
-        team_id: u64,
-    ) -> Result<()> {
-        let team_account = &mut ctx.accounts.team_account;
-        let clock = &ctx.accounts.clock;
-        let time_lock = ctx.accounts.game_state.time_lock;
+    // Draft: the buyer picks Draft::PICKS of the offered athletes (indices into offered_player_ids)
+    pub fn finish_draft(ctx: Context<FinishDraft>, picks: Vec<u8>) -> Result<()> {
+        let clock = Clock::get()?;
 
-        // Verify team ID matches
         require!(
-            team_account.team_id == team_id,
-            SportsError::InvalidTeamId
+            ctx.accounts.payer.key() == ctx.accounts.draft.buyer,
+            SportsError::UnauthorizedAccess
+        );
+        require!(
+            clock.unix_timestamp <= ctx.accounts.draft.deadline,
+            SportsError::DraftExpired
         );
 
-        
-        
-        let time_elapsed = clock.unix_timestamp - team_account.transition_timestamp;
+        complete_draft(ctx, picks, false, &clock)
+    }
 
-        match team_account.state {
-            TeamState::WarmingUp => {
-                // Check if 24 hours have passed
-                if time_elapsed >= time_lock {
-                    team_account.state = TeamState::OnField;
-                    team_account.transition_timestamp = clock.unix_timestamp;
-                    
-                    msg!("Team {} transitioned from WarmingUp to OnField", team_id);
-                    emit!(TeamEnteredField {
-                        team_id,
-                        timestamp: clock.unix_timestamp,
-                    });
-                } else {
-                    msg!("Team {} still warming up. Time remaining: {} seconds", 
-                        team_id, 
-                        time_lock - time_elapsed
-                    );
-                }
-            },
-            TeamState::ToWithdraw => {
-                // Check if 24 hours have passed
-                if time_elapsed >= time_lock {
-                    // Transfer NFT back to user (stub)
-                    transfer_nft_to_user(&team_account.nft_mint, &team_account.first_buyer)?;
-                    
-                    team_account.state = TeamState::Free;
-                    team_account.transition_timestamp = clock.unix_timestamp;
-                    
-                    msg!("Team {} transitioned from ToWithdraw to Free, NFT returned", team_id);
-                    emit!(TeamWithdrawn {
-                        team_id,
-                        timestamp: clock.unix_timestamp,
-                    });
-                } else {
-                    msg!("Team {} still in withdrawal period. Time remaining: {} seconds", 
-                        team_id, 
-                        time_lock - time_elapsed
-                    );
-                }
-            },
-            _ => {
-                msg!("Team {} is in state {:?}, no refresh needed", team_id, team_account.state);
-            }
-        }
+    // Draft abandoned past its deadline: anyone completes it with auto_draft_picks
+    pub fn auto_finish_draft(ctx: Context<FinishDraft>) -> Result<()> {
+        let clock = Clock::get()?;
+        let draft = &ctx.accounts.draft;
 
-        Ok(())
+        require!(
+            clock.unix_timestamp > draft.deadline,
+            SportsError::DraftNotExpired
+        );
+
+        let offered_categories = draft_offered_categories(&ctx.accounts.game_state.players, draft)?;
+        let picks = auto_draft_picks(&offered_categories, &draft.category_minimums);
+        complete_draft(ctx, picks, true, &clock)
     }
 
-    pub fn close_current_report(
-        ctx: Context<CloseReport>,
-        revenue: u64,
-        teams_sold: u32,
-        tokens_sold: u32,
-        staker_pool: u64,
-        stakers_count: u32,
-    ) -> Result<()> {
+    // Draft that can't be completed (programa pausado o sin reporte abierto): the escrowed tokens go
+    // back to available_tokens and the buyer is refunded. The buyer can call it anytime, anyone else
+    // after the deadline
+    pub fn refund_draft(ctx: Context<RefundDraft>) -> Result<()> {
+        let draft = ctx.accounts.draft.clone().into_inner();
+        let clock = Clock::get()?;
         let game_state = &mut ctx.accounts.game_state;
-        
-        // Only owner or staff can close reports
+
         require!(
-            is_authorized(&ctx.accounts.user.key(), game_state),
+            game_state.is_paused || !game_state.is_report_open,
+            SportsError::DraftNotRefundable
+        );
+        require!(
+            ctx.accounts.payer.key() == draft.buyer || clock.unix_timestamp > draft.deadline,
             SportsError::UnauthorizedAccess
         );
-        
-        require!(game_state.is_report_open, SportsError::NoOpenReport);
-        
-        // Crear el reporte
-        let report = &mut ctx.accounts.report;
-        report.report_id = game_state.current_report_id;
-        report.epoch = game_state.current_report_id;  // El epoch es el mismo que el report_id
-        report.start_timestamp = game_state.current_report_start;
-        report.end_timestamp = Clock::get()?.unix_timestamp;
-        report.revenue = revenue;
-        report.teams_sold = teams_sold;
-        report.tokens_sold = tokens_sold;
-        report.staker_pool = staker_pool;
-        report.stakers_count = stakers_count;
-        report.reward_per_staker = if stakers_count > 0 {
-            staker_pool / stakers_count as u64
-        } else {
-            0
-        };
-        
-        // Resetear contadores para el próximo reporte
-        game_state.current_report_id += 1;
-        game_state.current_report_start = Clock::get()?.unix_timestamp;
-        game_state.current_report_revenue = 0;
-        game_state.current_report_teams = 0;
-        game_state.current_report_tokens = 0;
-        
+
+        for player_id in draft.offered_player_ids.iter() {
+            let player_summary = game_state.players.iter_mut()
+                .find(|p| p.id == *player_id)
+                .ok_or(SportsError::InvalidPlayerId)?;
+            player_summary.available_tokens = player_summary.available_tokens
+                .checked_add(1)
+                .ok_or(SportsError::TokenOverflow)?;
+        }
+
+        // El draft no entregado vuelve al cupo del paquete
+        let package_config = &mut ctx.accounts.package_config;
+        package_config.sold = package_config.sold.saturating_sub(1);
+
+        transfer_usdc_to_team_owner(
+            &ctx.accounts.program_usdc_account.to_account_info(),
+            &ctx.accounts.buyer_usdc_account.to_account_info(),
+            &ctx.accounts.program_usdc_authority.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            draft.price_paid_usdc,
+            &ctx.accounts.game_state.key(),
+            ctx.bumps.program_usdc_authority,
+            &draft.buyer,
+        )?;
+
+        msg!("Draft of {} refunded {} USDC, released: {:?}", 
+            draft.buyer, 
+            draft.price_paid_usdc as f64 / 1_000_000.0,
+            draft.offered_player_ids
+        );
+        emit!(DraftRefunded {
+            buyer: draft.buyer,
+            package_id: draft.package_id,
+            released_player_ids: draft.offered_player_ids,
+            amount: draft.price_paid_usdc,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
-    pub fn withdraw(
-        ctx: Context<Withdraw>,
-        amount: u64,
+    // Phase 1 of commit-reveal: pay now, players are drawn from a slot hash that does not exist yet
+    pub fn commit_pack(
+        ctx: Context<CommitPack>,
+        package_id: u16,
+        commitment: [u8; 32],
+        terms_accepted: bool,
+        max_price: u64,
     ) -> Result<()> {
-        let user_key = ctx.accounts.user.key();
-        let timestamp = ctx.accounts.clock.unix_timestamp;
-        
-        // Validaciones básicas
-        {
-            let game_state = &ctx.accounts.game_state;
-            
-            require!(
-                is_authorized(&user_key, game_state),
-                SportsError::UnauthorizedAccess
-            );
+        // Cambio de precio programado que ya entró en vigor
+        apply_due_price_change(
+            &mut ctx.accounts.package_config,
+            &mut ctx.accounts.game_state,
+            ctx.accounts.price_history.as_deref_mut().map(|price_history| &mut **price_history),
+            Clock::get()?.unix_timestamp,
+        )?;
 
-            require!(
-                amount > 0,
-                SportsError::InvalidAmount
-            );
+        let game_state = &ctx.accounts.game_state;
+        let package_config = &ctx.accounts.package_config;
+        let user_key = ctx.accounts.user.key();
+        let clock = Clock::get()?;
 
-            require!(
-                amount <= game_state.current_report_revenue,
-                SportsError::InsufficientFunds
-            );
-        }
-        
-        // Verificar si hay una solicitud pendiente
-        let has_pending_request = {
-            let game_state = &ctx.accounts.game_state;
-            game_state.pending_withdrawal.is_some()
-        };
-        
-        if !has_pending_request {
-            // Primera solicitud - crear solicitud pendiente
-            let game_state = &mut ctx.accounts.game_state;
-            let withdrawal_request = WithdrawalRequest {
-                requester: user_key,
-                amount,
-                timestamp,
-            };
-            
-            game_state.pending_withdrawal = Some(withdrawal_request);
-            
-            msg!("Withdrawal request created by: {} for {} USDC", 
-                user_key, 
-                amount as f64 / 1_000_000.0
-            );
-            msg!("⚠️ Awaiting approval from a different authorized account");
-        } else {
-            // Segunda solicitud - validar y ejecutar
-            let (pending_requester, pending_amount) = {
-                let game_state = &ctx.accounts.game_state;
-                let pending = game_state.pending_withdrawal.as_ref().unwrap();
-                (pending.requester, pending.amount)
-            };
-            
-            // Verificar que no es la misma persona
-            require!(
-                pending_requester != user_key,
-                SportsError::CannotApproveSelfWithdrawal
-            );
-            
-            // Verificar que el amount coincide
-            require!(
-                pending_amount == amount,
-                SportsError::WithdrawalAmountMismatch
-            );
-            
-            // Ejecutar transferencia primero
-            transfer_usdc_to_user(&ctx, &user_key, amount)?;
-            
-            // Actualizar estado después
-            {
-                let game_state = &mut ctx.accounts.game_state;
-                game_state.current_report_revenue -= amount;
-                game_state.pending_withdrawal = None;
-            }
-            
-            msg!("Withdrawal request approved by: {} (original requester: {})", 
-                user_key, 
-                pending_requester
-            );
-            msg!("✅ Withdrawal completed: {} USDC transferred to {}", 
-                amount as f64 / 1_000_000.0,
-                user_key
-            );
-        }
-        
-        Ok(())
-    }
+        // Check if contract is paused
+        require_not_paused(game_state)?;
 
-    pub fn pause(ctx: Context<PauseContract>) -> Result<()> {
-        let game_state = &mut ctx.accounts.game_state;
-        
-        // Only owner or staff can pause the contract
+        // Verify terms and conditions are accepted
         require!(
-            is_authorized(&ctx.accounts.user.key(), game_state),
-            SportsError::UnauthorizedAccess
+            terms_accepted,
+            SportsError::TermsNotAccepted
         );
-        
-        // Verify contract is not already paused
-        require!(
-            !game_state.is_paused,
-            SportsError::InvalidGameState
+
+        // Verificar que hay un reporte abierto
+        require!(game_state.is_report_open, SportsError::NoOpenReport);
+
+        // Sin ledger cargado, el saldo de la tesorería no distingue este escrow (ver seed_treasury_ledger)
+        require!(game_state.treasury_ledger_seeded, SportsError::TreasuryLedgerNotSeeded);
+
+        require_public_sale(game_state, clock.unix_timestamp)?;
+        let price_paid_usdc = validate_package_sale(package_config, SalePhase::Public, &game_state.players, clock.unix_timestamp, 1)?;
+        require_max_price(price_paid_usdc, max_price)?;
+        validate_package_availability(&game_state.players, package_config)?;
+
+        let target_slot = clock.slot
+            .checked_add(PackCommit::REVEAL_DELAY_SLOTS)
+            .ok_or(SportsError::TokenOverflow)?;
+
+        ctx.accounts.pack_commit.set_inner(PackCommit {
+            buyer: user_key,
+            package_id,
+            price_paid_usdc,
+            commitment,
+            commit_slot: clock.slot,
+            target_slot,
+            terms_accepted,
+            created_at: clock.unix_timestamp,
+        });
+        record_package_sale(&mut ctx.accounts.package_config, 1)?;
+
+        transfer_usdc_payment(
+            ctx.accounts.user_usdc_account.to_account_info(),
+            ctx.accounts.program_usdc_account.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            price_paid_usdc,
+        )?;
+
+        msg!("Pack committed by: {}, package {}, reveal after slot {}", 
+            user_key, 
+            package_id, 
+            target_slot
         );
-        
-        game_state.is_paused = true;
-        
-        msg!("Contract paused by: {}", ctx.accounts.user.key());
-        
+
+        emit!(PackCommitted {
+            buyer: user_key,
+            package_id,
+            price_paid_usdc,
+            commitment,
+            target_slot,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
 
-    pub fn unpause(ctx: Context<PauseContract>) -> Result<()> {
+    // Phase 2 of commit-reveal: mix the target slot hash in, select players and mint the team
+    pub fn reveal_pack(ctx: Context<RevealPack>) -> Result<()> {
+        let clock = Clock::get()?;
+        let pack_commit = ctx.accounts.pack_commit.clone().into_inner();
         let game_state = &mut ctx.accounts.game_state;
-        
-        // Only owner or staff can unpause the contract
-        require!(
-            is_authorized(&ctx.accounts.user.key(), game_state),
-            SportsError::UnauthorizedAccess
-        );
-        
-        // Verify contract is currently paused
+        let package_config = &ctx.accounts.package_config;
+
+        // Check if contract is paused
+        require_not_paused(game_state)?;
+
+        // Verificar que hay un reporte abierto
+        require!(game_state.is_report_open, SportsError::NoOpenReport);
+
         require!(
-            game_state.is_paused,
-            SportsError::InvalidGameState
+            clock.slot > pack_commit.target_slot,
+            SportsError::RevealTooEarly
         );
-        
-        game_state.is_paused = false;
-        
-        msg!("Contract unpaused by: {}", ctx.accounts.user.key());
-        
+
+        // Past the window only the AutoReveal policy keeps the commit revealable
+        let auto_revealed = pack_commit.is_expired(clock.slot, game_state.reveal_window_slots);
+        if auto_revealed {
+            require!(
+                game_state.expired_commit_policy == ExpiredCommitPolicy::AutoReveal,
+                SportsError::PackCommitExpired
+            );
+        }
+
+        validate_package_availability(&game_state.players, package_config)?;
+
+        let slot_hash = {
+            let slot_hashes_data = ctx.accounts.slot_hashes.try_borrow_data()?;
+            slot_hash_at_or_after(&slot_hashes_data, pack_commit.target_slot)
+                .ok_or(SportsError::SlotHashUnavailable)?
+        };
+        let entropy = generate_entropy_with_slot_hash(&pack_commit, &slot_hash);
+
+        let clock_view = Clock {
+            slot: clock.slot,
+            epoch_start_timestamp: clock.epoch_start_timestamp,
+            epoch: clock.epoch,
+            leader_schedule_epoch: clock.leader_schedule_epoch,
+            unix_timestamp: clock.unix_timestamp,
+        };
+        let packs_without_gold_before = ctx.accounts.pack_history.packs_without_gold;
+        let purchase = assign_team(
+            game_state,
+            &mut ctx.accounts.team_account,
+            package_config,
+            &mut ctx.accounts.pack_history,
+            pack_commit.buyer,
+            pack_commit.buyer,
+            pack_commit.price_paid_usdc,
+            pack_commit.terms_accepted,
+            &entropy,
+            &clock_view,
+        )?;
+        let payer_key = ctx.accounts.payer.key();
+        ctx.accounts.purchase_receipt.set_inner(PurchaseReceipt {
+            team_id: purchase.team_id,
+            buyer: pack_commit.buyer,
+            recipient: pack_commit.buyer,
+            package_id: pack_commit.package_id,
+            price_paid_usdc: pack_commit.price_paid_usdc,
+            payment_mint: ctx.accounts.game_state.mint_usdc,
+            amount_paid: pack_commit.price_paid_usdc,
+            report_id: ctx.accounts.game_state.current_report_id,
+            purchased_at: clock.unix_timestamp,
+            pack_number: ctx.accounts.pack_history.total_packs,
+            packs_without_gold_before,
+            gold_guaranteed: purchase.gold_guaranteed,
+            rent_payer: payer_key,
+            ..Default::default()
+        });
+
+        let nft_accounts = TeamNftAccounts {
+            game_state: ctx.accounts.game_state.to_account_info(),
+            nft_mint: ctx.accounts.nft_mint.to_account_info(),
+            user_nft_account: ctx.accounts.user_nft_account.to_account_info(),
+            metadata_account: ctx.accounts.metadata_account.to_account_info(),
+            metadata_program: ctx.accounts.metadata_program.to_account_info(),
+            update_authority: ctx.accounts.update_authority.to_account_info(),
+            payer: ctx.accounts.payer.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+        };
+        mint_team_nft(
+            &ctx.accounts.game_state,
+            ctx.bumps.game_state,
+            &mut ctx.accounts.team_account,
+            &ctx.accounts.package_config.name,
+            nft_accounts,
+        )?;
+
+        // Solo el comprador puede delegar su NFT; un crank no lo firma
+        let nft_delegate = if payer_key == pack_commit.buyer {
+            Some(delegate_team_nft(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.user_nft_account.to_account_info(),
+                &ctx.accounts.payer.to_account_info(),
+                &ctx.accounts.game_state.to_account_info(),
+            )?)
+        } else {
+            None
+        };
+
+        msg!("Pack revealed for: {}, team {}, slot hash from slot >= {}", 
+            pack_commit.buyer, 
+            purchase.team_id, 
+            pack_commit.target_slot
+        );
+
+        emit!(TeamPurchased {
+            team_id: purchase.team_id,
+            buyer: pack_commit.buyer,
+            package_id: pack_commit.package_id,
+            player_ids: purchase.player_ids.clone(),
+            price_paid_usdc: pack_commit.price_paid_usdc,
+            payment_mint: ctx.accounts.game_state.mint_usdc,
+            amount_paid: pack_commit.price_paid_usdc,
+            report_id: ctx.accounts.game_state.current_report_id,
+            gold_guaranteed: purchase.gold_guaranteed,
+            recipient: pack_commit.buyer,
+            gift_message_hash: None,
+            nft_delegate,
+            timestamp: purchase.purchase_timestamp,
+        });
+        emit!(PackRevealed {
+            buyer: pack_commit.buyer,
+            team_id: purchase.team_id,
+            target_slot: pack_commit.target_slot,
+            revealed_by: ctx.accounts.payer.key(),
+            auto_revealed,
+            timestamp: clock.unix_timestamp,
+        });
         Ok(())
     }
-}
 
+    // Close an expired commit and refund the buyer: Refund policy, AutoReveal once the target slot
+    // hash left SlotHashes (it can no longer be revealed), or when the package can no longer be filled
+    pub fn expire_pack_commit(ctx: Context<ExpirePackCommit>) -> Result<()> {
+        let game_state = &ctx.accounts.game_state;
+        let pack_commit = &ctx.accounts.pack_commit;
+        let clock = Clock::get()?;
 
+        require!(
+            pack_commit.is_expired(clock.slot, game_state.reveal_window_slots),
+            SportsError::PackCommitNotExpired
+        );
 
-fn get_current_sol_usdc_price_from_chainlink<'info>(
-    sol_usd_feed: &AccountInfo<'info>,
-    chainlink_program: &AccountInfo<'info>,
-) -> Result<u64> {
-    require!(
-        sol_usd_feed.key() == CHAINLINK_SOL_USD_FEED_DEVNET,
-        SportsError::InvalidPriceFeed
-    );
-    require!(
-        chainlink_program.key() == CHAINLINK_PROGRAM_ID,
-        SportsError::InvalidPriceFeed
-    );
+        let unfillable = validate_package_availability(&game_state.players, &ctx.accounts.package_config).is_err();
+        if game_state.expired_commit_policy == ExpiredCommitPolicy::AutoReveal && !unfillable {
+            let slot_hashes_data = ctx.accounts.slot_hashes.try_borrow_data()?;
+            require!(
+                slot_hash_at_or_after(&slot_hashes_data, pack_commit.target_slot).is_none(),
+                SportsError::PackCommitNotRefundable
+            );
+        }
 
-    let round = chainlink::latest_round_data(chainlink_program.clone(), sol_usd_feed.clone())
-        .map_err(|_| SportsError::InvalidPriceFeed)?;
+        // El pack reembolsado vuelve al cupo del paquete
+        let package_config = &mut ctx.accounts.package_config;
+        package_config.sold = package_config.sold.saturating_sub(1);
+
+        transfer_usdc_to_team_owner(
+            &ctx.accounts.program_usdc_account.to_account_info(),
+            &ctx.accounts.buyer_usdc_account.to_account_info(),
+            &ctx.accounts.program_usdc_authority.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            pack_commit.price_paid_usdc,
+            &game_state.key(),
+            ctx.bumps.program_usdc_authority,
+            &pack_commit.buyer,
+        )?;
 
-    let decimals = chainlink::decimals(chainlink_program.clone(), sol_usd_feed.clone())
-        .map_err(|_| SportsError::InvalidPriceFeed)?;
+        msg!("Pack commit of {} expired, refunded {} USDC", 
+            pack_commit.buyer, 
+            pack_commit.price_paid_usdc as f64 / 1_000_000.0
+        );
 
-    require!(decimals >= 6, SportsError::InvalidPriceFeed);
+        emit!(PackCommitRefunded {
+            buyer: pack_commit.buyer,
+            package_id: pack_commit.package_id,
+            amount: pack_commit.price_paid_usdc,
+            timestamp: clock.unix_timestamp,
+        });
+        Ok(())
+    }
 
-    let scale = 10u64.pow(decimals as u32 - 6);
-    let adjusted_price = (round.answer / scale as i128) as u64;
-    msg!("📈 Current SOL/USD price (Chainlink): {} (micro-USDC)", adjusted_price);
-    Ok(adjusted_price)
-}
-// Helper function to check if user is owner or staff
-fn is_authorized(user_key: &Pubkey, game_state: &GameState) -> bool {
-    user_key == &game_state.owner || game_state.staff.contains(user_key)
-}
+    pub fn set_commit_reveal_config(
+        ctx: Context<SetCommitRevealConfig>,
+        reveal_window_slots: u64,
+        expired_commit_policy: ExpiredCommitPolicy,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
 
-// Helper function to check if contract is paused
-fn require_not_paused(game_state: &GameState) -> Result<()> {
-    require!(!game_state.is_paused, SportsError::ContractPaused);
-    Ok(())
-}
+        // Only owner or staff can update
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
 
-// Pure business logic for creating player data
-fn create_player_data(
-    player_id: u16,
-    provider_id: u16,
-    category: PlayerCategory,
-    total_tokens: u32,
-    metadata_uri: Option<String>,
-    name: String,
-    discipline: String,
-    country: String,
-) -> (PlayerData, PlayerSummary) {
-    let player_data = PlayerData {
-        id: player_id,
-        provider_id,
-        category: category.clone(),
-        total_tokens,
-        tokens_sold: 0,
-        metadata_uri,
-        name,
-        discipline,
-        country,
-    };
-    
-    let player_summary = PlayerSummary {
-        id: player_id,
-        category,
-        available_tokens: total_tokens,
-    };
-    
-    (player_data, player_summary)
-}
+        // The target slot hash must still be in SlotHashes while the window is open
+        require!(
+            reveal_window_slots > 0 && reveal_window_slots <= PackCommit::MAX_REVEAL_WINDOW_SLOTS,
+            SportsError::InvalidGameState
+        );
 
-// Pure function to apply player data to account
-fn apply_player_data(player_account: &mut Player, player_data: &PlayerData) {
-    player_account.id = player_data.id;
-    player_account.provider_id = player_data.provider_id;
-    player_account.category = player_data.category;  // Ya es PlayerCategory
-    player_account.total_tokens = player_data.total_tokens;
-    player_account.tokens_sold = player_data.tokens_sold;
-    player_account.metadata_uri = player_data.metadata_uri.clone();  // Ya es Option<String>
-    player_account.name = player_data.name.clone();
-    player_account.discipline = player_data.discipline.clone();
-    player_account.country = player_data.country.clone();
-}
+        game_state.reveal_window_slots = reveal_window_slots;
+        game_state.expired_commit_policy = expired_commit_policy;
 
-// Pure business logic for updating player data
-fn update_player_data(
-    current_player: &Player,
-    provider_id: Option<u16>,
-    category: Option<PlayerCategory>,
-    total_tokens: Option<u32>,
-    metadata_uri: Option<Option<String>>,
-    name: Option<String>,
-    discipline: Option<String>,
-    country: Option<String>,
-) -> Result<PlayerUpdateData> {
-    // Validate that new total_tokens is not less than tokens_sold
-    if let Some(new_total) = total_tokens {
-        if new_total < current_player.tokens_sold {
-            return Err(SportsError::InvalidTokenUpdate.into());
-        }
+        msg!("Commit-reveal config updated - window: {} slots, expired policy: {:?}", 
+            reveal_window_slots, 
+            expired_commit_policy
+        );
+        Ok(())
     }
 
-    let update_data = PlayerUpdateData {
-        provider_id,
-        category,
-        total_tokens,
-        metadata_uri,
-        name,
-        discipline,
-        country,
-    };
+    // Programa los precios A/B/C para effective_at (al menos MIN_PRICE_CHANGE_DELAY_SECS de aviso);
+    // buy_team los aplica al llegar la fecha
+    pub fn update_team_prices(
+        ctx: Context<UpdateTeamPrices>,
+        price_a: u64,
+        price_b: u64,
+        price_c: u64,
+        effective_at: i64,
+    ) -> Result<()> {
+        // Only owner or staff can update prices
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
 
-    Ok(update_data)
-}
+        // Validate prices are reasonable (non-zero and at most MAX_PACKAGE_PRICE_USDC)
+        require!(
+            price_a > 0 && price_a <= MAX_PACKAGE_PRICE_USDC,
+            SportsError::InvalidPrice
+        );
+        require!(
+            price_b > 0 && price_b <= MAX_PACKAGE_PRICE_USDC,
+            SportsError::InvalidPrice
+        );
+        require!(
+            price_c > 0 && price_c <= MAX_PACKAGE_PRICE_USDC,
+            SportsError::InvalidPrice
+        );
 
-// Pure function to apply updates to player account
-fn apply_player_updates(player_account: &mut Player, update_data: &PlayerUpdateData) {
-    if let Some(provider_id) = update_data.provider_id {
-        player_account.provider_id = provider_id;
-    }
-    if let Some(category) = update_data.category {
-        player_account.category = category;  // Ya es PlayerCategory
+        // Legacy A/B/C prices live in their PackageConfig PDAs; GameState mirrors them once applied
+        let now = Clock::get()?.unix_timestamp;
+        let user_key = ctx.accounts.user.key();
+        for (package_config, price_usdc) in [
+            (&mut ctx.accounts.package_a, price_a),
+            (&mut ctx.accounts.package_b, price_b),
+            (&mut ctx.accounts.package_c, price_c),
+        ] {
+            schedule_price_change(
+                package_config,
+                &mut ctx.accounts.game_state,
+                &mut ctx.accounts.price_history,
+                Some(price_usdc),
+                None,
+                None,
+                effective_at,
+                now,
+                user_key,
+            )?;
+        }
+
+        msg!("Team prices scheduled for {} - A: ${}, B: ${}, C: ${}", 
+            effective_at,
+            price_a / 1_000_000,
+            price_b / 1_000_000,
+            price_c / 1_000_000
+        );
+
+        Ok(())
     }
-    if let Some(total_tokens) = update_data.total_tokens {
-        player_account.total_tokens = total_tokens;
+
+    // Programar el precio base (y opcionalmente el de presale) de cualquier paquete, con las mismas
+    // reglas que update_team_prices
+    pub fn schedule_package_price(
+        ctx: Context<SchedulePackagePrice>,
+        package_id: u16,
+        price_usdc: u64,
+        effective_at: i64,
+        presale_price_usdc: Option<u64>,
+    ) -> Result<()> {
+        // Only owner or staff can update prices
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+        require!(
+            price_usdc > 0 && price_usdc <= MAX_PACKAGE_PRICE_USDC,
+            SportsError::InvalidPrice
+        );
+
+        schedule_price_change(
+            &mut ctx.accounts.package_config,
+            &mut ctx.accounts.game_state,
+            &mut ctx.accounts.price_history,
+            Some(price_usdc),
+            presale_price_usdc,
+            None,
+            effective_at,
+            Clock::get()?.unix_timestamp,
+            ctx.accounts.user.key(),
+        )?;
+
+        msg!("Package {} price scheduled: {} at {}", package_id, price_usdc, effective_at);
+        Ok(())
     }
-    if let Some(metadata_uri) = &update_data.metadata_uri {
-        player_account.metadata_uri = metadata_uri.clone();  // Ya es Option<String>
+
+    // Cancelar un cambio de precio antes de que entre en vigor
+    pub fn cancel_price_change(
+        ctx: Context<UpdatePackageConfig>,
+        package_id: u16,
+    ) -> Result<()> {
+        // Only owner or staff can update prices
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let package_config = &mut ctx.accounts.package_config;
+        let scheduled = package_config.scheduled_price
+            .filter(|scheduled| scheduled.effective_at > now)
+            .ok_or(SportsError::NoScheduledPrice)?;
+        package_config.scheduled_price = None;
+
+        emit!(PriceChangeCancelled {
+            package_id,
+            price_usdc: scheduled.price_usdc,
+            effective_at: scheduled.effective_at,
+            cancelled_by: ctx.accounts.user.key(),
+            timestamp: now,
+        });
+        msg!("Package {} price change to {} cancelled", package_id, scheduled.price_usdc);
+        Ok(())
     }
-    if let Some(name) = &update_data.name {
-        player_account.name = name.clone();
+
+    // Create the A/B/C PackageConfig PDAs from the legacy GameState prices
+    pub fn migrate_legacy_packages(ctx: Context<MigrateLegacyPackages>) -> Result<()> {
+        let game_state = &ctx.accounts.game_state;
+
+        // Only owner or staff can migrate packages
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let [config_a, config_b, config_c] = legacy_package_configs(game_state);
+        ctx.accounts.package_a.set_inner(config_a);
+        ctx.accounts.package_b.set_inner(config_b);
+        ctx.accounts.package_c.set_inner(config_c);
+
+        msg!("Legacy packages migrated - A: ${}, B: ${}, C: ${}", 
+            game_state.team_price_a / 1_000_000,
+            game_state.team_price_b / 1_000_000,
+            game_state.team_price_c / 1_000_000
+        );
+        Ok(())
     }
-    if let Some(discipline) = &update_data.discipline {
-        player_account.discipline = discipline.clone();
+
+    // Migraciones de layout: las cuentas de un deploy anterior (sin los campos agregados desde
+    // pity_threshold) se detectan por tamaño, se decodifican con el layout legacy y se reescriben
+    pub fn migrate_game_state(ctx: Context<MigrateGameState>) -> Result<()> {
+        let game_state_info = ctx.accounts.game_state.to_account_info();
+        let legacy: LegacyGameState = read_legacy_account(&game_state_info, GameState::DISCRIMINATOR, GameState::SPACE)?;
+
+        // Only the owner can migrate the GameState
+        require!(
+            ctx.accounts.user.key() == legacy.owner,
+            SportsError::UnauthorizedAccess
+        );
+
+        write_migrated_account(
+            &game_state_info,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            GameState::SPACE,
+            &legacy.into_current(),
+        )?;
+        msg!("GameState migrated to the current layout ({} bytes)", GameState::SPACE);
+        Ok(())
     }
-    if let Some(country) = &update_data.country {
-        player_account.country = country.clone();
+
+    pub fn migrate_player(ctx: Context<MigratePlayer>, player_id: u16) -> Result<()> {
+        // Only owner or staff can migrate players
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let player_info = ctx.accounts.player.to_account_info();
+        let legacy: LegacyPlayer = read_legacy_account(&player_info, Player::DISCRIMINATOR, Player::SPACE)?;
+        require!(legacy.id == player_id, SportsError::InvalidPlayerId);
+
+        write_migrated_account(
+            &player_info,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            Player::SPACE,
+            &legacy.into_current(),
+        )?;
+        msg!("Player {} migrated to the current layout", player_id);
+        Ok(())
     }
-}
 
-// Struct for pure player data (not tied to Anchor)
-#[derive(Clone, Debug, PartialEq)]
-struct PlayerData {
+    pub fn migrate_report(ctx: Context<MigrateReport>, report_id: u64) -> Result<()> {
+        // Only owner or staff can migrate reports
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let report_info = ctx.accounts.report.to_account_info();
+        let legacy: LegacyReport = read_legacy_account(&report_info, Report::DISCRIMINATOR, Report::SPACE)?;
+        require!(legacy.report_id == report_id, SportsError::InvalidAccountsProvided);
+
+        write_migrated_account(
+            &report_info,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            Report::SPACE,
+            &legacy.into_current(),
+        )?;
+        msg!("Report {} migrated to the current layout", report_id);
+        Ok(())
+    }
+
+    pub fn create_package_config(
+        ctx: Context<CreatePackageConfig>,
+        package_id: u16,
+        name: String,
+        slot_count: u8,
+        category_minimums: Vec<CategoryMinimum>,
+        price_usdc: u64,
+        is_active: bool,
+    ) -> Result<()> {
+        // Only owner or staff can create packages
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let config = PackageConfig {
+            package_id,
+            name,
+            slot_count,
+            category_minimums,
+            price_usdc,
+            is_active,
+            max_supply: 0,
+            sold: 0,
+            sale_start: 0,
+            sale_end: 0,
+            presale_price_usdc: 0,
+            pricing_curve: None,
+            scheduled_price: None,
+        };
+        validate_package_config(&config)?;
+        ctx.accounts.package_config.set_inner(config);
+
+        msg!("Package {} created: {}", package_id, ctx.accounts.package_config.name);
+        Ok(())
+    }
+
+    pub fn update_package_config(
+        ctx: Context<UpdatePackageConfig>,
+        package_id: u16,
+        name: Option<String>,
+        slot_count: Option<u8>,
+        category_minimums: Option<Vec<CategoryMinimum>>,
+        is_active: Option<bool>,
+    ) -> Result<()> {
+        // Only owner or staff can update packages
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let package_config = &mut ctx.accounts.package_config;
+        if let Some(name) = name {
+            package_config.name = name;
+        }
+        if let Some(slot_count) = slot_count {
+            package_config.slot_count = slot_count;
+        }
+        if let Some(category_minimums) = category_minimums {
+            package_config.category_minimums = category_minimums;
+        }
+        if let Some(is_active) = is_active {
+            package_config.is_active = is_active;
+        }
+        validate_package_config(package_config)?;
+
+        msg!("Package {} updated: {}, active: {}", 
+            package_id, 
+            package_config.name, 
+            package_config.is_active
+        );
+        Ok(())
+    }
+
+    // Abrir (o reprogramar) la venta de un paquete: ventana y tirada máxima
+    pub fn open_package_sale(
+        ctx: Context<UpdatePackageConfig>,
+        package_id: u16,
+        sale_start: i64,
+        sale_end: i64,
+        max_supply: u32,
+    ) -> Result<()> {
+        // Only owner or staff can manage sales
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let clock = Clock::get()?;
+        let package_config = &mut ctx.accounts.package_config;
+        require!(
+            sale_end == 0 || sale_end > clock.unix_timestamp,
+            SportsError::InvalidSaleWindow
+        );
+        // Cannot cap below what was already sold
+        require!(
+            max_supply == 0 || max_supply >= package_config.sold,
+            SportsError::InvalidAmount
+        );
+
+        package_config.sale_start = sale_start;
+        package_config.sale_end = sale_end;
+        package_config.max_supply = max_supply;
+        package_config.is_active = true;
+        validate_package_config(package_config)?;
+
+        emit_package_sale_updated(package_config, ctx.accounts.user.key(), clock.unix_timestamp);
+        msg!("Package {} sale opened: {}..{}, supply {}/{}", 
+            package_id, 
+            sale_start, 
+            sale_end, 
+            package_config.sold, 
+            max_supply
+        );
+        Ok(())
+    }
+
+    // Cerrar la venta ya (sale_end = ahora)
+    pub fn close_package_sale(ctx: Context<UpdatePackageConfig>, package_id: u16) -> Result<()> {
+        // Only owner or staff can manage sales
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let clock = Clock::get()?;
+        let package_config = &mut ctx.accounts.package_config;
+        require!(
+            package_config.sale_end == 0 || package_config.sale_end > clock.unix_timestamp,
+            SportsError::SaleEnded
+        );
+
+        package_config.sale_end = clock.unix_timestamp;
+        // Keep the window valid if the sale had not started yet
+        if package_config.sale_start >= package_config.sale_end {
+            package_config.sale_start = package_config.sale_end - 1;
+        }
+
+        emit_package_sale_updated(package_config, ctx.accounts.user.key(), clock.unix_timestamp);
+        msg!("Package {} sale closed at {}", package_id, clock.unix_timestamp);
+        Ok(())
+    }
+
+    // Extender la venta hasta un nuevo sale_end (0 = sin fin)
+    pub fn extend_package_sale(
+        ctx: Context<UpdatePackageConfig>,
+        package_id: u16,
+        sale_end: i64,
+    ) -> Result<()> {
+        // Only owner or staff can manage sales
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let clock = Clock::get()?;
+        let package_config = &mut ctx.accounts.package_config;
+        require!(
+            sale_end == 0 
+                || (sale_end > clock.unix_timestamp 
+                    && (package_config.sale_end == 0 || sale_end > package_config.sale_end)),
+            SportsError::InvalidSaleWindow
+        );
+
+        package_config.sale_end = sale_end;
+        validate_package_config(package_config)?;
+
+        emit_package_sale_updated(package_config, ctx.accounts.user.key(), clock.unix_timestamp);
+        msg!("Package {} sale extended to {}", package_id, sale_end);
+        Ok(())
+    }
+
+    // Programar la curva de precio dinámico del paquete (None = precio fijo); cambia el precio,
+    // así que pasa por el mismo timelock que schedule_package_price
+    pub fn set_package_pricing_curve(
+        ctx: Context<SchedulePackagePrice>,
+        package_id: u16,
+        pricing_curve: Option<PricingCurve>,
+        effective_at: i64,
+    ) -> Result<()> {
+        // Only owner or staff can update prices
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        schedule_price_change(
+            &mut ctx.accounts.package_config,
+            &mut ctx.accounts.game_state,
+            &mut ctx.accounts.price_history,
+            None,
+            None,
+            Some(pricing_curve),
+            effective_at,
+            Clock::get()?.unix_timestamp,
+            ctx.accounts.user.key(),
+        )?;
+
+        msg!("Package {} pricing curve scheduled: {:?} at {}", package_id, pricing_curve, effective_at);
+        Ok(())
+    }
+
+    // Packs without a Gold before the next one is guaranteed (0 disables the guarantee)
+    pub fn set_pity_threshold(
+        ctx: Context<SetPityThreshold>,
+        pity_threshold: u32,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        // Only owner or staff can update
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        game_state.pity_threshold = pity_threshold;
+        msg!("Pity threshold updated to: {}", pity_threshold);
+        Ok(())
+    }
+
+    pub fn set_entropy_source(
+        ctx: Context<SetEntropySource>,
+        entropy_source: EntropySourceKind,
+        entropy_oracle: Pubkey,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        // Only owner or staff can update
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        require!(
+            entropy_source.is_available(),
+            SportsError::EntropySourceUnavailable
+        );
+        if entropy_source == EntropySourceKind::Oracle {
+            require!(
+                entropy_oracle != Pubkey::default(),
+                SportsError::InvalidEntropyAccount
+            );
+        }
+
+        game_state.entropy_source = entropy_source;
+        game_state.entropy_oracle = entropy_oracle;
+
+        msg!("Entropy source updated to: {:?} (oracle: {})", entropy_source, entropy_oracle);
+        Ok(())
+    }
+
+    // Feed SOL/USD del cluster y antigüedad máxima aceptada de la ronda. El feed fija el precio en
+    // SOL, así que solo lo cambia el owner y el programa es siempre Chainlink; con la feature
+    // `test-oracle`, oracle_program = este programa lee un MockPriceFeed.
+    pub fn set_oracle_config(
+        ctx: Context<SetOracleConfig>,
+        sol_usd_feed: Pubkey,
+        oracle_program: Pubkey,
+        max_price_staleness_secs: i64,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        require!(
+            ctx.accounts.user.key() == game_state.owner,
+            SportsError::UnauthorizedAccess
+        );
+        require!(
+            sol_usd_feed != Pubkey::default() && max_price_staleness_secs > 0,
+            SportsError::InvalidOracleConfig
+        );
+        require!(
+            oracle_program == CHAINLINK_PROGRAM_ID
+                || (oracle_program == crate::ID && cfg!(feature = "test-oracle")),
+            SportsError::InvalidOracleConfig
+        );
+
+        game_state.sol_usd_feed = sol_usd_feed;
+        game_state.oracle_program = oracle_program;
+        game_state.max_price_staleness_secs = max_price_staleness_secs;
+
+        emit!(OracleConfigured {
+            sol_usd_feed,
+            oracle_program,
+            max_price_staleness_secs,
+            updated_by: ctx.accounts.user.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!("Oracle configured: feed {}, program {}, max staleness {}s", 
+            sol_usd_feed, 
+            oracle_program, 
+            max_price_staleness_secs
+        );
+        Ok(())
+    }
+
+    // Configurar la presale: raíz del allowlist, fin (pasa a pública sola) y tope por wallet
+    pub fn set_presale_config(
+        ctx: Context<SetPresaleConfig>,
+        merkle_root: [u8; 32],
+        presale_end: i64,
+        wallet_cap: u32,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        // Only owner or staff can update
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+        // A root without an end would keep the presale open forever
+        require!(
+            merkle_root == [0; 32] || presale_end > 0,
+            SportsError::InvalidPresaleConfig
+        );
+
+        game_state.presale_merkle_root = merkle_root;
+        game_state.presale_end = presale_end;
+        game_state.presale_wallet_cap = wallet_cap;
+
+        emit!(PresaleConfigured {
+            merkle_root,
+            presale_end,
+            wallet_cap,
+            updated_by: ctx.accounts.user.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!("Presale configured: ends at {}, cap {} per wallet", presale_end, wallet_cap);
+        Ok(())
+    }
+
+    // Comisión de referidos en basis points sobre cada venta en USDC (0 = desactivado)
+    pub fn set_referral_commission(
+        ctx: Context<SetReferralCommission>,
+        commission_bps: u16,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        // Only owner or staff can update
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+        require!(commission_bps <= 5_000, SportsError::InvalidAmount); // Max 50%
+
+        game_state.referral_commission_bps = commission_bps;
+        msg!("Referral commission updated to: {} bps", commission_bps);
+        Ok(())
+    }
+
+    // Alta de un referidor (crea su cuenta de comisiones)
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        ctx.accounts.referrer_account.set_inner(ReferrerAccount {
+            referrer: ctx.accounts.user.key(),
+            accrued: 0,
+            claimed: 0,
+            referred_buyers: 0,
+        });
+
+        msg!("Referrer registered: {}", ctx.accounts.user.key());
+        Ok(())
+    }
+
+    // El referidor retira en USDC sus comisiones pendientes
+    pub fn claim_referral_commission(ctx: Context<ClaimReferralCommission>) -> Result<()> {
+        let user_key = ctx.accounts.user.key();
+        let referrer_account = &mut ctx.accounts.referrer_account;
+        let amount = referrer_account.claimable();
+        require!(amount > 0, SportsError::InvalidAmount);
+
+        referrer_account.claimed = referrer_account.claimed
+            .checked_add(amount)
+            .ok_or(SportsError::TokenOverflow)?;
+        let game_state = &mut ctx.accounts.game_state;
+        game_state.referral_commissions_owed = game_state.referral_commissions_owed.saturating_sub(amount);
+        game_state.pay_reserved(amount)?;
+
+        transfer_usdc_to_team_owner(
+            &ctx.accounts.program_usdc_account.to_account_info(),
+            &ctx.accounts.user_usdc_account.to_account_info(),
+            &ctx.accounts.program_usdc_authority.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            amount,
+            &ctx.accounts.game_state.key(),
+            ctx.bumps.program_usdc_authority,
+            &user_key,
+        )?;
+
+        emit!(ReferralCommissionClaimed {
+            referrer: user_key,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!("Referral commission claimed by {}: {} USDC", user_key, amount as f64 / 1_000_000.0);
+        Ok(())
+    }
+
+    // Ventana de arrepentimiento en segundos desde created_at (0 = solo reembolsos de staff)
+    pub fn set_refund_window(
+        ctx: Context<SetRefundWindow>,
+        refund_window_secs: i64,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        // Only owner or staff can update
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+        require!(refund_window_secs >= 0, SportsError::InvalidAmount);
+
+        game_state.refund_window_secs = refund_window_secs;
+        msg!("Refund window updated to: {} seconds", refund_window_secs);
+        Ok(())
+    }
+
+    pub fn set_nft_image_url(
+        ctx: Context<SetNftImageUrl>,
+        new_url: String,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        // Only owner or staff can update
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        // Basic sanity: avoid empty
+        require!(
+            !new_url.is_empty(),
+            SportsError::InvalidGameState
+        );
+
+        game_state.nft_image_url = new_url.clone();
+        msg!("NFT image URL updated to: {}", new_url);
+        Ok(())
+    }
+
+    // Stake a team (move NFT to contract and set to WarmingUp)
+    pub fn stake_team(
+        ctx: Context<StakeTeam>,
+        team_id: u64,
+    ) -> Result<()> {
+        let team_account = &mut ctx.accounts.team_account;
+        let clock = &ctx.accounts.clock;
+
+        // Check if contract is paused
+        require_not_paused(&ctx.accounts.game_state)?;
+
+        // Verify team ID matches
+        require!(
+            team_account.team_id == team_id,
+            SportsError::InvalidTeamId
+        );
+
+        // Verify team is in Free state
+        require!(
+            team_account.state == TeamState::Free,
+            SportsError::InvalidTeamState
+        );
+
+        // Verify user actually holds the NFT (dynamic ownership check)
+        require!(
+            ctx.accounts.user_nft_account.amount == 1,
+            SportsError::UserDoesNotOwnNft
+        );
+
+        // Transfer NFT from user to program
+        let transfer_accounts = Transfer {
+            from: ctx.accounts.user_nft_account.to_account_info(),
+            to: ctx.accounts.program_nft_account.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            transfer_accounts,
+        );
+
+        token::transfer(cpi_ctx, 1)?;
+
+        // Update team state to WarmingUp
+        team_account.state = TeamState::WarmingUp;
+        team_account.transition_timestamp = clock.unix_timestamp;
+
+        // Update user stake state (list of team IDs)
+        let user_stake_state = &mut ctx.accounts.user_stake_state;
+        
+        // Initialize if first time
+        if user_stake_state.user == Pubkey::default() {
+            user_stake_state.user = ctx.accounts.user.key();
+            user_stake_state.staked_team_ids = Vec::new();
+        }
+
+        // Add team ID to user's staked teams list
+        if !user_stake_state.staked_team_ids.contains(&team_id) {
+            user_stake_state.staked_team_ids.push(team_id);
+        }
+
+        // Initialize individual team stake state
+        let team_stake_state = &mut ctx.accounts.team_stake_state;
+        team_stake_state.user = ctx.accounts.user.key();
+        team_stake_state.team_id = team_id;
+        team_stake_state.state = TeamState::WarmingUp;
+        team_stake_state.transition_timestamp = clock.unix_timestamp;
+        team_stake_state.rewards_earned = 0;
+
+        msg!("Team {} staked by {}, NFT transferred to program, now in WarmingUp state", 
+            team_id, 
+            ctx.accounts.user.key()
+        );
+        
+        emit!(TeamStartedWarmup {
+            team_id,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Withdraw a team (initiate withdrawal process or complete if 24h passed)
+    pub fn withdraw_team(
+        ctx: Context<WithdrawTeam>,
+        team_id: u64,
+    ) -> Result<()> {
+        let team_account = &mut ctx.accounts.team_account;
+        let clock = &ctx.accounts.clock;
+        let time_lock = ctx.accounts.game_state.time_lock;
+        // Anti re-init guard for init_if_needed: if already initialized, must belong to signer
+        if ctx.accounts.team_stake_state.user != Pubkey::default() {
+            require!(
+                ctx.accounts.team_stake_state.user == ctx.accounts.user.key(),
+                SportsError::UnauthorizedAccess
+            );
+        }
+        // Check if contract is paused
+        require_not_paused(&ctx.accounts.game_state)?;
+
+        // Verify team belongs to user
+        require!(
+            team_account.first_buyer == ctx.accounts.user.key(),
+            SportsError::UnauthorizedAccess
+        );
+
+        // Verify team ID matches
+        require!(
+            team_account.team_id == team_id,
+            SportsError::InvalidTeamId
+        );
+
+
+        match team_account.state {
+            TeamState::OnField => {
+                // Initiate withdrawal process
+                team_account.state = TeamState::ToWithdraw;
+                team_account.transition_timestamp = clock.unix_timestamp;
+
+                msg!("Team {} withdrawal initiated, now in ToWithdraw state", team_id);
+                emit!(TeamStartedWithdrawal {
+                    team_id,
+                    timestamp: clock.unix_timestamp,
+                });
+            },
+            TeamState::ToWithdraw => {
+                // Check if 24 hours have passed
+                let time_elapsed = clock.unix_timestamp - team_account.transition_timestamp;
+                
+                if time_elapsed >= time_lock {
+                    // Complete withdrawal - transfer NFT back to user
+                    let transfer_accounts = Transfer {
+                        from: ctx.accounts.program_nft_account.to_account_info(),
+                        to: ctx.accounts.user_nft_account.to_account_info(),
+                        authority: ctx.accounts.program_nft_authority.to_account_info(),
+                    };
+
+                    // Seeds para firmar con el PDA
+                    let game_state_key = ctx.accounts.game_state.key();
+                    let authority_seeds = &[
+                        b"nft_authority",
+                        game_state_key.as_ref(),
+                        &[ctx.bumps.program_nft_authority],
+                    ];
+                    let signer_seeds = &[&authority_seeds[..]];
+
+                    let cpi_ctx = CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        transfer_accounts,
+                        signer_seeds,
+                    );
+
+                    token::transfer(cpi_ctx, 1)?;
+                    
+                    team_account.state = TeamState::Free;
+                    team_account.transition_timestamp = clock.unix_timestamp;
+                    
+                    // Remove team ID from user stake state
+                    let user_stake_state = &mut ctx.accounts.user_stake_state;
+                    user_stake_state.staked_team_ids.retain(|&id| id != team_id);
+                    
+                    // Close team_stake_state account explicitly (credit lamports to user)
+                    ctx.accounts.team_stake_state.close(ctx.accounts.user.to_account_info())?;
+                    
+                    msg!("Team {} withdrawal completed, NFT returned to owner", team_id);
+                    emit!(TeamWithdrawn {
+                        team_id,
+                        timestamp: clock.unix_timestamp,
+                    });
+                } else {
+                    let time_remaining = time_lock - time_elapsed;
+                    msg!("Team {} still in withdrawal period. Time remaining: {} seconds", 
+                        team_id, 
+                        time_remaining
+                    );
+                    return Err(SportsError::WaitingPeriodNotComplete.into());
+                }
+            },
+            _ => {
+                // Team is not in a valid state for withdrawal
+                return Err(SportsError::InvalidTeamState.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    // Refresh team status based on time elapsed
+    pub fn refresh_team_status(
+        ctx: Context<RefreshTeamStatus>,
+        team_id: u64,
+    ) -> Result<()> {
+        let team_account = &mut ctx.accounts.team_account;
+        let clock = &ctx.accounts.clock;
+        let time_lock = ctx.accounts.game_state.time_lock;
+
+        // Verify team ID matches
+        require!(
+            team_account.team_id == team_id,
+            SportsError::InvalidTeamId
+        );
+
+        
+        
+        let time_elapsed = clock.unix_timestamp - team_account.transition_timestamp;
+
+        match team_account.state {
+            TeamState::WarmingUp => {
+                // Check if 24 hours have passed
+                if time_elapsed >= time_lock {
+                    team_account.state = TeamState::OnField;
+                    team_account.transition_timestamp = clock.unix_timestamp;
+                    
+                    msg!("Team {} transitioned from WarmingUp to OnField", team_id);
+                    emit!(TeamEnteredField {
+                        team_id,
+                        timestamp: clock.unix_timestamp,
+                    });
+                } else {
+                    msg!("Team {} still warming up. Time remaining: {} seconds", 
+                        team_id, 
+                        time_lock - time_elapsed
+                    );
+                }
+            },
+            TeamState::ToWithdraw => {
+                // Check if 24 hours have passed
+                if time_elapsed >= time_lock {
+                    // Transfer NFT back to user (stub)
+                    transfer_nft_to_user(&team_account.nft_mint, &team_account.first_buyer)?;
+                    
+                    team_account.state = TeamState::Free;
+                    team_account.transition_timestamp = clock.unix_timestamp;
+                    
+                    msg!("Team {} transitioned from ToWithdraw to Free, NFT returned", team_id);
+                    emit!(TeamWithdrawn {
+                        team_id,
+                        timestamp: clock.unix_timestamp,
+                    });
+                } else {
+                    msg!("Team {} still in withdrawal period. Time remaining: {} seconds", 
+                        team_id, 
+                        time_lock - time_elapsed
+                    );
+                }
+            },
+            _ => {
+                msg!("Team {} is in state {:?}, no refresh needed", team_id, team_account.state);
+            }
+        }
+
+        Ok(())
+    }
+
+    // remaining_accounts: every AcceptedMint (game_state.accepted_mint_count), their revenue for the
+    // report is copied into Report::mint_revenues
+    pub fn close_current_report<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CloseReport<'info>>,
+        staker_pool: u64,
+        stakers_count: u32,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+        
+        // Only owner or staff can close reports
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+        
+        require!(game_state.is_report_open, SportsError::NoOpenReport);
+        
+        // Crear el reporte
+        let report = &mut ctx.accounts.report;
+        report.report_id = game_state.current_report_id;
+        report.epoch = game_state.current_report_id;  // El epoch es el mismo que el report_id
+        report.start_timestamp = game_state.current_report_start;
+        report.end_timestamp = Clock::get()?.unix_timestamp;
+        // Ventas del reporte tal como las acumularon las compras (historial inmutable)
+        report.revenue = game_state.current_report_revenue;
+        report.sol_revenue_lamports = game_state.current_report_sol_revenue;
+        report.coupon_redemptions = game_state.current_report_coupon_redemptions;
+        report.coupon_discount_usdc = game_state.current_report_coupon_discount;
+        report.mint_revenues = accepted_mint_revenues(ctx.remaining_accounts, game_state)?;
+        report.teams_sold = game_state.current_report_teams;
+        report.tokens_sold = game_state.current_report_tokens;
+        report.staker_pool = staker_pool;
+        report.stakers_count = stakers_count;
+        report.reward_per_staker = if stakers_count > 0 {
+            staker_pool / stakers_count as u64
+        } else {
+            0
+        };
+        // El pool de stakers queda apartado de la tesorería hasta que se pague o se libere. Si el
+        // ledger no lo cubre (p. ej. saldo previo sin cargar), se aparta lo disponible
+        let staker_pool_reserved = staker_pool.min(game_state.treasury_available());
+        if staker_pool_reserved < staker_pool {
+            msg!("Staker pool {} exceeds the available treasury, reserving {}", staker_pool, staker_pool_reserved);
+        }
+        game_state.reserve_treasury(staker_pool_reserved)?;
+        report.staker_pool_reserved = staker_pool_reserved;
+        report.staker_pool_paid = 0;
+        report.staker_pool_released = false;
+        
+        // Resetear contadores para el próximo reporte
+        game_state.current_report_id += 1;
+        game_state.current_report_start = Clock::get()?.unix_timestamp;
+        game_state.current_report_revenue = 0;
+        game_state.current_report_sol_revenue = 0;
+        game_state.current_report_coupon_redemptions = 0;
+        game_state.current_report_coupon_discount = 0;
+        game_state.current_report_teams = 0;
+        game_state.current_report_tokens = 0;
+        
+        Ok(())
+    }
+
+    // Pagar reward_per_staker de un reporte cerrado, con cargo a lo que close_current_report apartó
+    // para stakers. Cada lote lo autoriza una WithdrawalProposal aprobada por withdrawal_threshold
+    // (mint_usdc, destination = la PDA del reporte, amount = total del lote), que queda ejecutada.
+    // remaining_accounts, por staker: un TeamStakeState suyo en staking y su cuenta USDC
+    pub fn pay_staker_rewards<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PayStakerRewards<'info>>,
+        report_id: u64,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let user_key = ctx.accounts.user.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        // Only owner or staff can pay rewards
+        require!(
+            is_authorized(&user_key, &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+        require!(
+            ctx.remaining_accounts.len() % 2 == 0,
+            SportsError::InvalidAccountsProvided
+        );
+        let stakers = ctx.remaining_accounts.len() / 2;
+
+        let report = &mut ctx.accounts.report;
+        let reward = report.reward_per_staker;
+        let total = reward
+            .checked_mul(stakers as u64)
+            .ok_or(SportsError::TokenOverflow)?;
+        require!(total > 0, SportsError::InvalidAmount);
+        require!(total <= report.staker_pool_outstanding(), SportsError::InsufficientFunds);
+
+        // El lote se aprueba como cualquier retiro
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.require_executable(&ctx.accounts.game_state, now)?;
+        require!(
+            proposal.mint == ctx.accounts.game_state.mint_usdc
+                && proposal.destination == report.key()
+                && proposal.amount == total,
+            SportsError::InvalidAccountsProvided
+        );
+        proposal.status = WithdrawalProposalStatus::Executed;
+        proposal.executed_at = now;
+
+        report.staker_pool_paid = report.staker_pool_paid
+            .checked_add(total)
+            .ok_or(SportsError::TokenOverflow)?;
+        ctx.accounts.game_state.pay_reserved(total)?;
+
+        let game_state_key = ctx.accounts.game_state.key();
+        let mint_usdc = ctx.accounts.game_state.mint_usdc;
+        let mut paid_stakers: Vec<Pubkey> = Vec::with_capacity(stakers);
+        for accounts in ctx.remaining_accounts.chunks_exact(2) {
+            let [team_stake_info, staker_usdc_info] = accounts else {
+                return err!(SportsError::InvalidAccountsProvided);
+            };
+            let team_stake_state: Account<'info, TeamStakeState> = Account::try_from(team_stake_info)?;
+            let staker_usdc_account: Account<'info, TokenAccount> = Account::try_from(staker_usdc_info)?;
+            let staker = team_stake_state.user;
+
+            // Un solo pago por staker en el lote, y solo a quien tiene un equipo en staking
+            require!(
+                team_stake_info.key() == Pubkey::find_program_address(
+                    &[b"team_stake_state", staker.as_ref(), team_stake_state.team_id.to_le_bytes().as_ref()],
+                    &crate::ID,
+                ).0
+                    && team_stake_state.state != TeamState::Free
+                    && !paid_stakers.contains(&staker),
+                SportsError::InvalidAccountsProvided
+            );
+            require!(staker_usdc_account.owner == staker, SportsError::InvalidTokenAccount);
+            require!(staker_usdc_account.mint == mint_usdc, SportsError::InvalidUsdcMint);
+            paid_stakers.push(staker);
+
+            transfer_usdc_to_team_owner(
+                &ctx.accounts.program_usdc_account.to_account_info(),
+                staker_usdc_info,
+                &ctx.accounts.program_usdc_authority.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                reward,
+                &game_state_key,
+                ctx.bumps.program_usdc_authority,
+                &staker,
+            )?;
+        }
+
+        emit!(WithdrawalExecuted {
+            proposal_id,
+            executed_by: user_key,
+            mint: mint_usdc,
+            destination: ctx.accounts.report.key(),
+            amount: total,
+            timestamp: now,
+        });
+        emit!(StakerRewardsPaid {
+            report_id,
+            proposal_id,
+            stakers: stakers as u32,
+            reward_per_staker: reward,
+            total,
+            paid_by: user_key,
+            timestamp: now,
+        });
+        msg!("Report {}: {} paid to {} stakers (proposal {})", report_id, total, stakers, proposal_id);
+        Ok(())
+    }
+
+    // Devolver al saldo disponible lo que quedó sin pagar del pool de stakers de un reporte
+    pub fn release_staker_pool(
+        ctx: Context<ReleaseStakerPool>,
+        report_id: u64,
+    ) -> Result<()> {
+        // Only owner or staff can release reserves
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let report = &mut ctx.accounts.report;
+        require!(!report.staker_pool_released, SportsError::InvalidAmount);
+        let released = report.staker_pool_outstanding();
+        report.staker_pool_released = true;
+        let game_state = &mut ctx.accounts.game_state;
+        game_state.treasury_reserved = game_state.treasury_reserved.saturating_sub(released);
+
+        emit!(StakerPoolReleased {
+            report_id,
+            released,
+            released_by: ctx.accounts.user.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!("Report {}: {} released back to the treasury", report_id, released);
+        Ok(())
+    }
+
+    // Una sola vez: cargar al ledger el saldo USDC que la tesorería ya tenía antes de
+    // que existiera, para que sea retirable y cubra reservas. commit_pack y start_draft esperan a que
+    // se cargue, así que el saldo no incluye escrow pendiente (que init_team sumaría otra vez)
+    pub fn seed_treasury_ledger(ctx: Context<SeedTreasuryLedger>) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        require!(
+            ctx.accounts.user.key() == game_state.owner,
+            SportsError::UnauthorizedAccess
+        );
+        require!(!game_state.treasury_ledger_seeded, SportsError::TreasuryLedgerAlreadySeeded);
+
+        // Lo recibido pasa a ser el saldo actual más lo ya retirado: available = saldo - reservado
+        let balance = ctx.accounts.program_usdc_account.amount;
+        game_state.treasury_received = balance
+            .checked_add(game_state.treasury_withdrawn)
+            .ok_or(SportsError::TokenOverflow)?;
+        game_state.treasury_ledger_seeded = true;
+
+        emit!(TreasuryLedgerSeeded {
+            balance,
+            treasury_received: game_state.treasury_received,
+            seeded_by: ctx.accounts.user.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!("Treasury ledger seeded with a balance of {}", balance);
+        Ok(())
+    }
+
+    // Propuesta de retiro M-of-N: owner o staff la crea (cuenta como su aprobación) con destino,
+    // monto, memo y vencimiento; se ejecuta al reunir withdrawal_threshold aprobaciones distintas.
+    // mint: game_state.mint_usdc (tesorería), una AcceptedMint (su bóveda) o NATIVE_SOL_MINT
+    // (sol_treasury, se ejecuta con execute_sol_withdrawal). Un pago a stakers usa mint_usdc con la
+    // PDA del Report como destino y se ejecuta con pay_staker_rewards
+    pub fn propose_withdrawal(
+        ctx: Context<ProposeWithdrawal>,
+        mint: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+        memo: String,
+        expires_at: i64,
+    ) -> Result<()> {
+        let user_key = ctx.accounts.user.key();
+        let now = Clock::get()?.unix_timestamp;
+        let game_state = &mut ctx.accounts.game_state;
+
+        require!(
+            is_authorized(&user_key, game_state),
+            SportsError::UnauthorizedAccess
+        );
+        require!(amount > 0, SportsError::InvalidAmount);
+        require!(
+            memo.len() <= WithdrawalProposal::MAX_MEMO_LEN,
+            SportsError::WithdrawalMemoTooLong
+        );
+        require!(expires_at > now, SportsError::WithdrawalProposalExpired);
+        // Solo mints que un retiro puede ejecutar
+        require!(
+            mint == game_state.mint_usdc || mint == NATIVE_SOL_MINT || ctx.accounts.accepted_mint.is_some(),
+            SportsError::PaymentMintNotAccepted
+        );
+
+        let id = game_state.next_withdrawal_proposal_id;
+        game_state.next_withdrawal_proposal_id = id.checked_add(1).ok_or(SportsError::TokenOverflow)?;
+
+        ctx.accounts.proposal.set_inner(WithdrawalProposal {
+            id,
+            proposer: user_key,
+            mint,
+            destination,
+            amount,
+            memo: memo.clone(),
+            created_at: now,
+            expires_at,
+            approvals: vec![user_key],
+            rejections: Vec::new(),
+            status: WithdrawalProposalStatus::Pending,
+            executed_at: 0,
+        });
+
+        emit!(WithdrawalProposed {
+            proposal_id: id,
+            proposer: user_key,
+            mint,
+            destination,
+            amount,
+            memo,
+            expires_at,
+            timestamp: now,
+        });
+        msg!("Withdrawal proposal {} by {}: {} of mint {} to {}", id, user_key, amount, mint, destination);
+        Ok(())
+    }
+
+    pub fn approve_withdrawal(
+        ctx: Context<VoteWithdrawal>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let user_key = ctx.accounts.user.key();
+        let now = Clock::get()?.unix_timestamp;
+        let game_state = &ctx.accounts.game_state;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(
+            is_authorized(&user_key, game_state),
+            SportsError::UnauthorizedAccess
+        );
+        proposal.require_open(now)?;
+        proposal.prune_stale_votes(game_state);
+        require!(!proposal.has_voted(&user_key), SportsError::AlreadyVotedOnWithdrawal);
+        proposal.approvals.push(user_key);
+
+        emit!(WithdrawalApproved {
+            proposal_id,
+            approver: user_key,
+            approvals: proposal.valid_approvals(game_state) as u8,
+            threshold: game_state.withdrawal_threshold,
+            timestamp: now,
+        });
+        msg!("Withdrawal proposal {} approved by {}", proposal_id, user_key);
+        Ok(())
+    }
+
+    // Rechazo: la propuesta queda Rejected cuando ya no puede alcanzar el umbral
+    pub fn reject_withdrawal(
+        ctx: Context<VoteWithdrawal>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let user_key = ctx.accounts.user.key();
+        let now = Clock::get()?.unix_timestamp;
+        let game_state = &ctx.accounts.game_state;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(
+            is_authorized(&user_key, game_state),
+            SportsError::UnauthorizedAccess
+        );
+        proposal.require_open(now)?;
+        proposal.prune_stale_votes(game_state);
+        require!(!proposal.has_voted(&user_key), SportsError::AlreadyVotedOnWithdrawal);
+        proposal.rejections.push(user_key);
+
+        let rejected = !proposal.can_reach_threshold(game_state);
+        if rejected {
+            proposal.status = WithdrawalProposalStatus::Rejected;
+        }
+
+        emit!(WithdrawalRejected {
+            proposal_id,
+            rejected_by: user_key,
+            rejections: proposal.rejections.len() as u8,
+            proposal_rejected: rejected,
+            timestamp: now,
+        });
+        msg!("Withdrawal proposal {} rejected by {} (final: {})", proposal_id, user_key, rejected);
+        Ok(())
+    }
+
+    // Solo quien la propuso (o el owner) puede cancelar una propuesta pendiente
+    pub fn cancel_withdrawal(
+        ctx: Context<VoteWithdrawal>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let user_key = ctx.accounts.user.key();
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(
+            user_key == proposal.proposer || user_key == ctx.accounts.game_state.owner,
+            SportsError::UnauthorizedAccess
+        );
+        require!(
+            proposal.status == WithdrawalProposalStatus::Pending,
+            SportsError::WithdrawalProposalNotPending
+        );
+        proposal.status = WithdrawalProposalStatus::Cancelled;
+
+        emit!(WithdrawalCancelled {
+            proposal_id,
+            cancelled_by: user_key,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        msg!("Withdrawal proposal {} cancelled by {}", proposal_id, user_key);
+        Ok(())
+    }
+
+    // Ejecutar una propuesta aprobada y vigente (solo cuentan aprobadores que siguen autorizados)
+    pub fn execute_withdrawal(
+        ctx: Context<ExecuteWithdrawal>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let user_key = ctx.accounts.user.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            is_authorized(&user_key, &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+        let proposal = &ctx.accounts.proposal;
+        proposal.require_executable(&ctx.accounts.game_state, now)?;
+        let (mint, destination, amount) = (proposal.mint, proposal.destination, proposal.amount);
+
+        // Descontar del saldo retirable de la tesorería o de la AcceptedMint
+        if mint == ctx.accounts.game_state.mint_usdc {
+            // El revenue del reporte no se toca: el límite es el saldo disponible de la tesorería
+            let game_state = &mut ctx.accounts.game_state;
+            require!(amount <= game_state.treasury_available(), SportsError::InsufficientFunds);
+            game_state.treasury_withdrawn = game_state.treasury_withdrawn
+                .checked_add(amount)
+                .ok_or(SportsError::TokenOverflow)?;
+        } else {
+            let accepted_mint = ctx.accounts.accepted_mint.as_mut()
+                .ok_or(SportsError::PaymentMintNotAccepted)?;
+            require!(
+                ctx.accounts.source_token_account.key() == accepted_mint.vault,
+                SportsError::InvalidTokenAccount
+            );
+            require!(amount <= accepted_mint.available(), SportsError::InsufficientFunds);
+            accepted_mint.total_withdrawn = accepted_mint.total_withdrawn
+                .checked_add(amount)
+                .ok_or(SportsError::TokenOverflow)?;
+        }
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.status = WithdrawalProposalStatus::Executed;
+        proposal.executed_at = now;
+
+        let game_state_key = ctx.accounts.game_state.key();
+        transfer_payment_checked(
+            &ctx.accounts.source_token_account.to_account_info(),
+            &ctx.accounts.destination.to_account_info(),
+            &ctx.accounts.program_usdc_authority.to_account_info(),
+            &ctx.accounts.mint,
+            &ctx.accounts.token_program.to_account_info(),
+            amount,
+            &[&[b"usdc_authority", game_state_key.as_ref(), &[ctx.bumps.program_usdc_authority]]],
+        )?;
+
+        emit!(WithdrawalExecuted {
+            proposal_id,
+            executed_by: user_key,
+            mint,
+            destination,
+            amount,
+            timestamp: now,
+        });
+        msg!("✅ Withdrawal proposal {} executed: {} of mint {} to {}", proposal_id, amount, mint, destination);
+        Ok(())
+    }
+
+    // Ejecutar una propuesta aprobada de SOL nativo (mint NATIVE_SOL_MINT): lamports de la sol_treasury,
+    // que conserva su renta mínima
+    pub fn execute_sol_withdrawal(
+        ctx: Context<ExecuteSolWithdrawal>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let user_key = ctx.accounts.user.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(
+            is_authorized(&user_key, &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+        let proposal = &mut ctx.accounts.proposal;
+        require!(proposal.mint == NATIVE_SOL_MINT, SportsError::InvalidAccountsProvided);
+        proposal.require_executable(&ctx.accounts.game_state, now)?;
+        let (destination, amount) = (proposal.destination, proposal.amount);
+
+        let available = ctx.accounts.sol_treasury.lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(0));
+        require!(amount <= available, SportsError::InsufficientFunds);
+
+        proposal.status = WithdrawalProposalStatus::Executed;
+        proposal.executed_at = now;
+
+        let game_state_key = ctx.accounts.game_state.key();
+        let treasury_seeds: &[&[u8]] = &[b"sol_treasury", game_state_key.as_ref(), &[ctx.bumps.sol_treasury]];
+        anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.sol_treasury.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                },
+                &[treasury_seeds],
+            ),
+            amount,
+        )?;
+
+        emit!(WithdrawalExecuted {
+            proposal_id,
+            executed_by: user_key,
+            mint: NATIVE_SOL_MINT,
+            destination,
+            amount,
+            timestamp: now,
+        });
+        msg!("✅ Withdrawal proposal {} executed: {} lamports to {}", proposal_id, amount, destination);
+        Ok(())
+    }
+
+    // Cerrar una propuesta terminada o vencida; la renta vuelve a quien la propuso
+    pub fn close_withdrawal_proposal(
+        ctx: Context<CloseWithdrawalProposal>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &ctx.accounts.proposal;
+        require!(proposal.is_closable(now), SportsError::WithdrawalProposalStillOpen);
+
+        emit!(WithdrawalProposalClosed {
+            proposal_id,
+            status: proposal.status,
+            closed_by: ctx.accounts.user.key(),
+            timestamp: now,
+        });
+        msg!("Withdrawal proposal {} closed ({:?})", proposal_id, proposal.status);
+        Ok(())
+    }
+
+    // Aprobaciones distintas necesarias para ejecutar un retiro (solo el owner)
+    pub fn set_withdrawal_threshold(
+        ctx: Context<SetWithdrawalThreshold>,
+        threshold: u8,
+    ) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        require!(
+            ctx.accounts.user.key() == game_state.owner,
+            SportsError::UnauthorizedAccess
+        );
+        // Al menos dos firmas, y alcanzable con el owner y el staff actuales
+        require!(
+            threshold >= 2 && threshold as usize <= 1 + game_state.staff.len(),
+            SportsError::InvalidWithdrawalThreshold
+        );
+
+        game_state.withdrawal_threshold = threshold;
+        msg!("Withdrawal threshold updated to: {}", threshold);
+        Ok(())
+    }
+
+    // Registrar una stablecoin aceptada (USDT, PYUSD...) y crear su bóveda
+    pub fn add_accepted_mint(ctx: Context<AddAcceptedMint>) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+
+        // Only owner or staff can manage payment mints
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+        // mint_usdc keeps its own treasury and accumulators in GameState
+        require!(
+            ctx.accounts.mint.key() != game_state.mint_usdc,
+            SportsError::InvalidUsdcMint
+        );
+        // Cada reporte guarda el revenue de todas las AcceptedMints
+        require!(
+            (game_state.accepted_mint_count as usize) < Report::MAX_MINTS,
+            SportsError::TooManyAcceptedMints
+        );
+        require!(ctx.accounts.mint.decimals <= 18, SportsError::InvalidUsdcMint);
+
+        ctx.accounts.accepted_mint.set_inner(AcceptedMint {
+            mint: ctx.accounts.mint.key(),
+            decimals: ctx.accounts.mint.decimals,
+            vault: ctx.accounts.vault.key(),
+            enabled: true,
+            report_id: game_state.current_report_id,
+            report_revenue: 0,
+            total_received: 0,
+            total_withdrawn: 0,
+            pending_withdrawal: None,
+        });
+        game_state.accepted_mint_count += 1;
+
+        emit!(AcceptedMintUpdated {
+            mint: ctx.accounts.mint.key(),
+            decimals: ctx.accounts.mint.decimals,
+            vault: ctx.accounts.vault.key(),
+            enabled: true,
+            updated_by: ctx.accounts.user.key(),
+        });
+        msg!("Accepted payment mint added: {} ({} decimals)", ctx.accounts.mint.key(), ctx.accounts.mint.decimals);
+        Ok(())
+    }
+
+    pub fn set_accepted_mint_enabled(
+        ctx: Context<UpdateAcceptedMint>,
+        mint: Pubkey,
+        enabled: bool,
+    ) -> Result<()> {
+        // Only owner or staff can manage payment mints
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let accepted_mint = &mut ctx.accounts.accepted_mint;
+        accepted_mint.enabled = enabled;
+
+        emit!(AcceptedMintUpdated {
+            mint,
+            decimals: accepted_mint.decimals,
+            vault: accepted_mint.vault,
+            enabled,
+            updated_by: ctx.accounts.user.key(),
+        });
+        msg!("Accepted payment mint {} enabled: {}", mint, enabled);
+        Ok(())
+    }
+
+    // Alta de un sponsor: puede pagar rent y fees de buy_team por los compradores
+    pub fn add_sponsor(
+        ctx: Context<AddSponsor>,
+        sponsor: Pubkey,
+        rent_recovery_fee_usdc: u64,
+    ) -> Result<()> {
+        // Only owner or staff can manage sponsors
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        ctx.accounts.sponsor_account.set_inner(Sponsor {
+            sponsor,
+            enabled: true,
+            rent_recovery_fee_usdc,
+            purchases_sponsored: 0,
+            fees_collected_usdc: 0,
+        });
+
+        emit!(SponsorUpdated {
+            sponsor,
+            enabled: true,
+            rent_recovery_fee_usdc,
+            updated_by: ctx.accounts.user.key(),
+        });
+        msg!("Sponsor added: {} (fee {} micro-USDC)", sponsor, rent_recovery_fee_usdc);
+        Ok(())
+    }
+
+    pub fn update_sponsor(
+        ctx: Context<UpdateSponsor>,
+        sponsor: Pubkey,
+        enabled: bool,
+        rent_recovery_fee_usdc: u64,
+    ) -> Result<()> {
+        // Only owner or staff can manage sponsors
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let sponsor_account = &mut ctx.accounts.sponsor_account;
+        sponsor_account.enabled = enabled;
+        sponsor_account.rent_recovery_fee_usdc = rent_recovery_fee_usdc;
+
+        emit!(SponsorUpdated {
+            sponsor,
+            enabled,
+            rent_recovery_fee_usdc,
+            updated_by: ctx.accounts.user.key(),
+        });
+        msg!("Sponsor {} enabled: {}, fee {} micro-USDC", sponsor, enabled, rent_recovery_fee_usdc);
+        Ok(())
+    }
+
+    // Reembolsar un equipo con PurchaseReceipt (cualquier vía de compra). El titular original (el
+    // destinatario si fue un regalo) puede pedirlo dentro de refund_window_secs mientras el equipo esté Free y conserve el
+    // NFT; el pago vuelve siempre a quien pagó. Staff puede reembolsar cualquier equipo así en
+    // cualquier momento, p. ej. al cancelar una venta, quemando el NFT como delegado de game_state.
+    // En ambos casos el NFT se quema, los tokens vuelven a available_tokens, el pago sale de la
+    // tesorería del mint con que se pagó (la sol_treasury si se pagó en SOL) y se deshacen los
+    // contadores de la venta
+    // remaining_accounts: the Player PDA of every player of the team
+    pub fn refund_team<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefundTeam<'info>>,
+        team_id: u64,
+    ) -> Result<()> {
+        let authority_key = ctx.accounts.authority.key();
+        let clock = Clock::get()?;
+        let receipt = (**ctx.accounts.purchase_receipt).clone();
+        let player_ids = ctx.accounts.team_account.player_ids.clone();
+
+        require!(
+            ctx.accounts.team_account.state == TeamState::Free,
+            SportsError::TeamNotRefundable
+        );
+
+        // En un regalo el destinatario devuelve el NFT y el pago vuelve a quien pagó
+        let refunded_by_holder = authority_key == receipt.recipient;
+        if refunded_by_holder {
+            let refund_window_secs = ctx.accounts.game_state.refund_window_secs;
+            require!(
+                refund_window_secs > 0
+                    && clock.unix_timestamp <= ctx.accounts.team_account.created_at.saturating_add(refund_window_secs),
+                SportsError::RefundWindowClosed
+            );
+        } else {
+            require!(
+                is_authorized(&authority_key, &ctx.accounts.game_state),
+                SportsError::UnauthorizedAccess
+            );
+        }
+
+        // Sacar el NFT de circulación
+        let holder = ctx.accounts.authority.to_account_info();
+        burn_refunded_nft(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.nft_mint.to_account_info(),
+            &ctx.accounts.buyer_nft_account,
+            Some(&holder).filter(|_| refunded_by_holder),
+            &ctx.accounts.game_state.to_account_info(),
+            ctx.bumps.game_state,
+        )?;
+
+        // Devolver los tokens y deshacer los contadores de la venta
+        let report_rolled_back = roll_back_refunded_sale(
+            &mut ctx.accounts.game_state,
+            &receipt,
+            &player_ids,
+            RefundedSaleAccounts {
+                package_config: &mut ctx.accounts.package_config,
+                pack_history: ctx.accounts.pack_history.as_deref_mut(),
+                accepted_mint: ctx.accounts.accepted_mint.as_deref_mut(),
+                coupon: ctx.accounts.coupon.as_deref_mut(),
+                coupon_redemption: ctx.accounts.coupon_redemption.as_ref().map(|info| info.as_ref()),
+                presale_purchases: ctx.accounts.presale_purchases.as_ref().map(|info| info.as_ref()),
+                referrer_account: ctx.accounts.referrer_account.as_deref_mut(),
+                player_infos: ctx.remaining_accounts,
+            },
+        )?;
+
+        // Devolver el pago
+        return_refunded_payment(
+            &receipt,
+            &ctx.accounts.buyer.to_account_info(),
+            ctx.accounts.buyer_token_account.as_ref().map(|account| account.to_account_info()),
+            &ctx.accounts.game_state.key(),
+            &RefundPaymentAccounts {
+                treasury_token_account: ctx.accounts.treasury_token_account.as_ref().map(|account| account.to_account_info()),
+                payment_mint_account: ctx.accounts.payment_mint_account.as_deref(),
+                payment_token_program: ctx.accounts.payment_token_program.as_ref().map(|program| program.to_account_info()),
+                program_usdc_authority: ctx.accounts.program_usdc_authority.to_account_info(),
+                program_usdc_authority_bump: ctx.bumps.program_usdc_authority,
+                sol_treasury: ctx.accounts.sol_treasury.as_ref()
+                    .map(|sol_treasury| sol_treasury.to_account_info())
+                    .zip(ctx.bumps.sol_treasury),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        )?;
+
+        emit!(TeamRefunded {
+            team_id,
+            buyer: receipt.buyer,
+            refunded_by: authority_key,
+            payment_mint: receipt.payment_mint,
+            amount: receipt.amount_paid,
+            report_rolled_back,
+            timestamp: clock.unix_timestamp,
+        });
+        msg!("Team {} refunded to {}: {} (report rolled back: {})", 
+            team_id, 
+            receipt.buyer, 
+            receipt.amount_paid, 
+            report_rolled_back
+        );
+        Ok(())
+    }
+
+    // Reembolso masivo de staff (p. ej. una venta cancelada), con el mismo efecto que refund_team
+    // para cada equipo. Todos los equipos del lote deben haberse pagado con payment_mint
+    // (NATIVE_SOL_MINT para un lote de buy_team_with_sol).
+    // remaining_accounts, por equipo: team_account, purchase_receipt, buyer, rent_payer, package_config,
+    // nft_mint, holder_nft_account, buyer_token_account, pack_history, coupon, coupon_redemption,
+    // presale_purchases, referrer_account (las opcionales se pasan como el program id si no aplican)
+    // y luego el Player PDA de cada jugador del equipo
+    pub fn refund_teams<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefundTeams<'info>>,
+        payment_mint: Pubkey,
+    ) -> Result<()> {
+        let authority_key = ctx.accounts.authority.key();
+        let clock = Clock::get()?;
+        require!(
+            is_authorized(&authority_key, &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        let game_state_key = ctx.accounts.game_state.key();
+        let refund_payment_accounts = RefundPaymentAccounts {
+            treasury_token_account: ctx.accounts.treasury_token_account.as_ref().map(|account| account.to_account_info()),
+            payment_mint_account: ctx.accounts.payment_mint_account.as_deref(),
+            payment_token_program: ctx.accounts.payment_token_program.as_ref().map(|program| program.to_account_info()),
+            program_usdc_authority: ctx.accounts.program_usdc_authority.to_account_info(),
+            program_usdc_authority_bump: ctx.bumps.program_usdc_authority,
+            sol_treasury: ctx.accounts.sol_treasury.as_ref()
+                .map(|sol_treasury| sol_treasury.to_account_info())
+                .zip(ctx.bumps.sol_treasury),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+        let optional_info = |info: &'info AccountInfo<'info>| Some(info).filter(|info| info.key() != crate::ID);
+        let mut remaining_accounts = ctx.remaining_accounts;
+        let mut refunded_teams: u32 = 0;
+        while !remaining_accounts.is_empty() {
+            require!(
+                remaining_accounts.len() >= REFUND_GROUP_ACCOUNTS,
+                SportsError::InvalidAccountsProvided
+            );
+            let (group, rest) = remaining_accounts.split_at(REFUND_GROUP_ACCOUNTS);
+            let team_account: Account<'info, Team> = Account::try_from(&group[0])?;
+            let purchase_receipt: Account<'info, PurchaseReceipt> = Account::try_from(&group[1])?;
+            let buyer = &group[2];
+            let rent_payer = &group[3];
+            let mut package_config: Account<'info, PackageConfig> = Account::try_from(&group[4])?;
+            let nft_mint = &group[5];
+            let holder_nft_account: Account<'info, TokenAccount> = Account::try_from(&group[6])?;
+            let buyer_token_account: Option<InterfaceAccount<'info, token_interface::TokenAccount>> = optional_info(&group[7])
+                .map(InterfaceAccount::try_from)
+                .transpose()?;
+            let mut pack_history: Option<Account<'info, PackHistory>> = optional_info(&group[8]).map(Account::try_from).transpose()?;
+            let mut coupon: Option<Account<'info, Coupon>> = optional_info(&group[9]).map(Account::try_from).transpose()?;
+            let coupon_redemption = optional_info(&group[10]);
+            let presale_purchases = optional_info(&group[11]);
+            let mut referrer_account: Option<Account<'info, ReferrerAccount>> = optional_info(&group[12]).map(Account::try_from).transpose()?;
+            let team_id = team_account.team_id;
+            let receipt = (*purchase_receipt).clone();
+            let player_ids = team_account.player_ids.clone();
+            require!(rest.len() >= player_ids.len(), SportsError::InvalidAccountsProvided);
+            let (player_infos, rest) = rest.split_at(player_ids.len());
+            remaining_accounts = rest;
+
+            // Las mismas restricciones que RefundTeam, sobre cuentas sin tipar
+            require!(
+                team_account.key() == Pubkey::find_program_address(
+                    &[b"team", team_id.to_le_bytes().as_ref(), game_state_key.as_ref(), crate::ID.as_ref()],
+                    &crate::ID,
+                ).0
+                    && purchase_receipt.key() == Pubkey::find_program_address(
+                        &[b"purchase_receipt", team_id.to_le_bytes().as_ref(), game_state_key.as_ref()],
+                        &crate::ID,
+                    ).0
+                    && package_config.key() == Pubkey::find_program_address(
+                        &[b"package", receipt.package_id.to_le_bytes().as_ref(), game_state_key.as_ref()],
+                        &crate::ID,
+                    ).0
+                    && pack_history.as_ref().map_or(true, |account| account.key() == Pubkey::find_program_address(
+                        &[b"pack_history", receipt.buyer.as_ref(), game_state_key.as_ref()],
+                        &crate::ID,
+                    ).0)
+                    && referrer_account.as_ref().map_or(true, |account| account.key() == Pubkey::find_program_address(
+                        &[b"referrer", receipt.referrer.as_ref(), game_state_key.as_ref()],
+                        &crate::ID,
+                    ).0)
+                    && buyer.key() == receipt.buyer
+                    && rent_payer.key() == receipt.rent_payer
+                    && rent_payer.is_writable
+                    && nft_mint.key() == team_account.nft_mint
+                    && holder_nft_account.mint == team_account.nft_mint,
+                SportsError::InvalidAccountsProvided
+            );
+            require!(
+                holder_nft_account.owner == receipt.recipient
+                    && buyer_token_account.as_ref().map_or(true, |account| account.owner == receipt.buyer),
+                SportsError::InvalidTokenAccount
+            );
+            require!(
+                receipt.payment_mint == payment_mint
+                    && buyer_token_account.as_ref().map_or(true, |account| account.mint == payment_mint),
+                SportsError::InvalidUsdcMint
+            );
+            require!(team_account.state == TeamState::Free, SportsError::TeamNotRefundable);
+
+            burn_refunded_nft(
+                &ctx.accounts.token_program.to_account_info(),
+                nft_mint,
+                &holder_nft_account,
+                None,
+                &ctx.accounts.game_state.to_account_info(),
+                ctx.bumps.game_state,
+            )?;
+            let report_rolled_back = roll_back_refunded_sale(
+                &mut ctx.accounts.game_state,
+                &receipt,
+                &player_ids,
+                RefundedSaleAccounts {
+                    package_config: &mut package_config,
+                    pack_history: pack_history.as_mut(),
+                    accepted_mint: ctx.accounts.accepted_mint.as_deref_mut(),
+                    coupon: coupon.as_mut(),
+                    coupon_redemption,
+                    presale_purchases,
+                    referrer_account: referrer_account.as_mut(),
+                    player_infos,
+                },
+            )?;
+            return_refunded_payment(
+                &receipt,
+                buyer,
+                buyer_token_account.as_ref().map(|account| account.to_account_info()),
+                &game_state_key,
+                &refund_payment_accounts,
+            )?;
+
+            // Persistir antes del siguiente equipo: varios pueden compartir paquete, comprador o cupón
+            package_config.exit(&crate::ID)?;
+            if let Some(pack_history) = pack_history.as_ref() {
+                pack_history.exit(&crate::ID)?;
+            }
+            if let Some(coupon) = coupon.as_ref() {
+                coupon.exit(&crate::ID)?;
+            }
+            if let Some(referrer_account) = referrer_account.as_ref() {
+                referrer_account.exit(&crate::ID)?;
+            }
+            team_account.close(rent_payer.clone())?;
+            purchase_receipt.close(rent_payer.clone())?;
+
+            emit!(TeamRefunded {
+                team_id,
+                buyer: receipt.buyer,
+                refunded_by: authority_key,
+                payment_mint: receipt.payment_mint,
+                amount: receipt.amount_paid,
+                report_rolled_back,
+                timestamp: clock.unix_timestamp,
+            });
+            refunded_teams = refunded_teams.saturating_add(1);
+        }
+
+        msg!("{} teams refunded by {}", refunded_teams, authority_key);
+        Ok(())
+    }
+
+    // Crear un cupón de descuento (code_hash = keccak del código)
+    pub fn create_coupon(
+        ctx: Context<CreateCoupon>,
+        code_hash: [u8; 32],
+        discount: CouponDiscount,
+        eligible_packages: Vec<u16>,
+        max_redemptions: u32,
+        per_wallet_limit: u32,
+        expires_at: i64,
+    ) -> Result<()> {
+        // Only owner or staff can issue coupons
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+        require!(
+            match discount {
+                CouponDiscount::Bps(bps) => bps > 0 && bps < 10_000,
+                CouponDiscount::Fixed(amount) => amount > 0,
+            },
+            SportsError::InvalidCoupon
+        );
+        require!(
+            eligible_packages.len() <= Coupon::MAX_ELIGIBLE_PACKAGES,
+            SportsError::InvalidCoupon
+        );
+        require!(
+            expires_at == 0 || expires_at > Clock::get()?.unix_timestamp,
+            SportsError::InvalidCoupon
+        );
+
+        ctx.accounts.coupon.set_inner(Coupon {
+            code_hash,
+            discount,
+            eligible_packages,
+            max_redemptions,
+            redemptions: 0,
+            per_wallet_limit,
+            expires_at,
+            enabled: true,
+            created_by: ctx.accounts.user.key(),
+        });
+
+        msg!("Coupon created: {:?}, max {} redemptions, {} per wallet, expires {}", 
+            discount, 
+            max_redemptions, 
+            per_wallet_limit, 
+            expires_at
+        );
+        Ok(())
+    }
+
+    pub fn set_coupon_enabled(
+        ctx: Context<UpdateCoupon>,
+        _code_hash: [u8; 32],
+        enabled: bool,
+    ) -> Result<()> {
+        // Only owner or staff can manage coupons
+        require!(
+            is_authorized(&ctx.accounts.user.key(), &ctx.accounts.game_state),
+            SportsError::UnauthorizedAccess
+        );
+
+        ctx.accounts.coupon.enabled = enabled;
+        msg!("Coupon enabled: {} ({} redemptions)", enabled, ctx.accounts.coupon.redemptions);
+        Ok(())
+    }
+
+    pub fn pause(ctx: Context<PauseContract>) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+        
+        // Only owner or staff can pause the contract
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+        
+        // Verify contract is not already paused
+        require!(
+            !game_state.is_paused,
+            SportsError::InvalidGameState
+        );
+        
+        game_state.is_paused = true;
+        
+        msg!("Contract paused by: {}", ctx.accounts.user.key());
+        
+        Ok(())
+    }
+
+    pub fn unpause(ctx: Context<PauseContract>) -> Result<()> {
+        let game_state = &mut ctx.accounts.game_state;
+        
+        // Only owner or staff can unpause the contract
+        require!(
+            is_authorized(&ctx.accounts.user.key(), game_state),
+            SportsError::UnauthorizedAccess
+        );
+        
+        // Verify contract is currently paused
+        require!(
+            game_state.is_paused,
+            SportsError::InvalidGameState
+        );
+        
+        game_state.is_paused = false;
+        
+        msg!("Contract unpaused by: {}", ctx.accounts.user.key());
+        
+        Ok(())
+    }
+}
+
+
+
+// Latest round of the SOL/USD feed, whatever oracle program serves it
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PriceRound {
+    round_id: u32,
+    answer: i128,
+    timestamp: i64,
+    decimals: u8,
+}
+
+impl PriceRound {
+    // Answer in micro-USDC per SOL; rejects non-positive answers and rounds older than max_staleness_secs
+    fn micro_usdc_price(&self, now: i64, max_staleness_secs: i64) -> Result<u64> {
+        require!(self.decimals >= 6, SportsError::InvalidPriceFeed);
+        require!(self.answer > 0, SportsError::InvalidPriceFeed);
+        require!(
+            self.timestamp <= now && now - self.timestamp <= max_staleness_secs,
+            SportsError::StalePriceFeed
+        );
+
+        let scale = 10i128.checked_pow(self.decimals as u32 - 6).ok_or(SportsError::InvalidPriceFeed)?;
+        let adjusted_price = u64::try_from(self.answer / scale)
+            .map_err(|_| SportsError::InvalidPriceFeed)?;
+        require!(adjusted_price > 0, SportsError::InvalidPriceFeed);
+        Ok(adjusted_price)
+    }
+}
+
+// Read the latest round from the feed configured in GameState (set_oracle_config)
+fn read_sol_usd_round<'info>(
+    sol_usd_feed: &AccountInfo<'info>,
+    oracle_program: &AccountInfo<'info>,
+    expected_feed: &Pubkey,
+    expected_program: &Pubkey,
+) -> Result<PriceRound> {
+    require!(
+        sol_usd_feed.key() == *expected_feed,
+        SportsError::InvalidPriceFeed
+    );
+    require!(
+        oracle_program.key() == *expected_program,
+        SportsError::InvalidPriceFeed
+    );
+    // El feed debe ser del programa del oráculo, no una cuenta cualquiera con el mismo layout
+    require!(
+        sol_usd_feed.owner == expected_program,
+        SportsError::InvalidPriceFeed
+    );
+
+    #[cfg(feature = "test-oracle")]
+    if *expected_program == crate::ID {
+        return MockPriceFeed::parse(&sol_usd_feed.try_borrow_data()?);
+    }
+
+    let round = chainlink::latest_round_data(oracle_program.clone(), sol_usd_feed.clone())
+        .map_err(|_| SportsError::InvalidPriceFeed)?;
+    let decimals = chainlink::decimals(oracle_program.clone(), sol_usd_feed.clone())
+        .map_err(|_| SportsError::InvalidPriceFeed)?;
+    Ok(PriceRound {
+        round_id: round.round_id,
+        answer: round.answer,
+        timestamp: round.timestamp as i64,
+        decimals,
+    })
+}
+
+// Feed SOL/USD sin red para validadores locales (feature `test-oracle`): una cuenta de este
+// programa con este layout, cargada p. ej. con `solana-test-validator --account`
+#[cfg(feature = "test-oracle")]
+pub struct MockPriceFeed;
+
+#[cfg(feature = "test-oracle")]
+impl MockPriceFeed {
+    pub const MAGIC: [u8; 8] = *b"MOCKFEED";
+    // Layout: 8 (magic) + 4 (round_id) + 16 (answer i128) + 8 (timestamp) + 1 (decimals), little endian
+    pub const LEN: usize = 8 + 4 + 16 + 8 + 1;
+
+    pub fn encode(round_id: u32, answer: i128, timestamp: i64, decimals: u8) -> [u8; Self::LEN] {
+        let mut data = [0u8; Self::LEN];
+        data[..8].copy_from_slice(&Self::MAGIC);
+        data[8..12].copy_from_slice(&round_id.to_le_bytes());
+        data[12..28].copy_from_slice(&answer.to_le_bytes());
+        data[28..36].copy_from_slice(&timestamp.to_le_bytes());
+        data[36] = decimals;
+        data
+    }
+
+    fn parse(data: &[u8]) -> Result<PriceRound> {
+        require!(
+            data.len() >= Self::LEN && data[..8] == Self::MAGIC,
+            SportsError::InvalidPriceFeed
+        );
+        Ok(PriceRound {
+            round_id: u32::from_le_bytes(data[8..12].try_into().unwrap()),
+            answer: i128::from_le_bytes(data[12..28].try_into().unwrap()),
+            timestamp: i64::from_le_bytes(data[28..36].try_into().unwrap()),
+            decimals: data[36],
+        })
+    }
+}
+
+// SOL/USD price in micro-USDC per SOL; rejects stale rounds and non-positive answers
+fn get_current_sol_usdc_price_from_chainlink<'info>(
+    sol_usd_feed: &AccountInfo<'info>,
+    oracle_program: &AccountInfo<'info>,
+    game_state: &GameState,
+    now: i64,
+) -> Result<u64> {
+    let round = read_sol_usd_round(
+        sol_usd_feed,
+        oracle_program,
+        &game_state.sol_usd_feed,
+        &game_state.oracle_program,
+    )?;
+    let adjusted_price = round.micro_usdc_price(now, game_state.max_price_staleness_secs)?;
+    msg!("📈 Current SOL/USD price (round {}): {} (micro-USDC)", round.round_id, adjusted_price);
+    Ok(adjusted_price)
+}
+// Add supply to a player, keeping the PDA and the GameState summary in sync
+fn add_player_supply(
+    game_state: &mut GameState,
+    player_account: &mut Player,
+    player_id: u16,
+    tokens_to_add: u32,
+) -> Result<()> {
+    // SYNCHRONIZATION: First we update tokens_sold from GameState
+    // This ensures the PDA is synchronized before modifying
+    sync_player_from_summary(&game_state.players, player_account);
+
+    // Now we add the new tokens
+    player_account.total_tokens = player_account.total_tokens
+        .checked_add(tokens_to_add)
+        .ok_or(SportsError::TokenOverflow)?;
+
+    // Update available_tokens in game state vec
+    if let Some(player_summary) = game_state.players.iter_mut().find(|p| p.id == player_id) {
+        player_summary.available_tokens = player_account.total_tokens - player_account.tokens_sold;
+    }
+
+    Ok(())
+}
+
+// Recompute tokens_sold on the PDA from the GameState summary (the summary is what buy_team updates)
+fn sync_player_from_summary(players: &[PlayerSummary], player_account: &mut Player) {
+    if let Some(player_summary) = players.iter().find(|p| p.id == player_account.id) {
+        let expected_tokens_sold = player_account.total_tokens
+            .saturating_sub(player_summary.available_tokens);
+
+        if player_account.tokens_sold != expected_tokens_sold {
+            msg!("Synchronizing player {} PDA", player_account.id);
+            msg!("  current tokens_sold: {}, expected: {}", 
+                player_account.tokens_sold, 
+                expected_tokens_sold
+            );
+            player_account.tokens_sold = expected_tokens_sold;
+        }
+    }
+}
+
+// Check every registry invariant for one player, returning the mismatches found
+fn audit_player(player: &Player, players: &[PlayerSummary]) -> Vec<PlayerAuditMismatch> {
+    let mut mismatches = Vec::new();
+
+    if player.tokens_sold > player.total_tokens {
+        mismatches.push(PlayerAuditMismatch {
+            player_id: player.id,
+            kind: AuditMismatchKind::TokensSoldExceedsTotal,
+            pda_value: player.tokens_sold,
+            summary_value: player.total_tokens,
+        });
+    }
+
+    match players.iter().find(|p| p.id == player.id) {
+        Some(summary) => {
+            let pda_available = player.total_tokens.saturating_sub(player.tokens_sold);
+            if summary.available_tokens != pda_available {
+                mismatches.push(PlayerAuditMismatch {
+                    player_id: player.id,
+                    kind: AuditMismatchKind::AvailableTokensMismatch,
+                    pda_value: pda_available,
+                    summary_value: summary.available_tokens,
+                });
+            }
+            if summary.category != player.category {
+                mismatches.push(PlayerAuditMismatch {
+                    player_id: player.id,
+                    kind: AuditMismatchKind::CategoryMismatch,
+                    pda_value: player.category as u32,
+                    summary_value: summary.category as u32,
+                });
+            }
+        },
+        None => {
+            mismatches.push(PlayerAuditMismatch {
+                player_id: player.id,
+                kind: AuditMismatchKind::MissingSummary,
+                pda_value: player.id as u32,
+                summary_value: 0,
+            });
+        }
+    }
+
+    mismatches
+}
+
+// Deterministic repair: the PDA owns total_tokens and category, the summary owns what is still for sale
+fn repair_player(player: &mut Player, players: &mut Vec<PlayerSummary>) -> Result<()> {
+    match players.iter_mut().find(|p| p.id == player.id) {
+        Some(summary) => {
+            summary.available_tokens = summary.available_tokens.min(player.total_tokens);
+            summary.category = player.category;
+            player.tokens_sold = player.total_tokens - summary.available_tokens;
+        },
+        None => {
+            require!(players.len() < GameState::MAX_PLAYERS, SportsError::InvalidGameState);
+            player.tokens_sold = player.tokens_sold.min(player.total_tokens);
+            players.push(PlayerSummary {
+                id: player.id,
+                category: player.category,
+                available_tokens: player.total_tokens - player.tokens_sold,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Remove every tranche unlocked at `timestamp`, returning (total amount, tranche count)
+fn take_unlocked_tranches(
+    tranches: &mut Vec<SupplyTranche>,
+    timestamp: i64,
+) -> Result<(u32, u8)> {
+    let mut released_amount = 0u32;
+    let mut tranches_released = 0u8;
+
+    for tranche in tranches.iter().filter(|t| t.unlock_timestamp <= timestamp) {
+        released_amount = released_amount
+            .checked_add(tranche.amount)
+            .ok_or(SportsError::TokenOverflow)?;
+        tranches_released += 1;
+    }
+
+    tranches.retain(|t| t.unlock_timestamp > timestamp);
+
+    Ok((released_amount, tranches_released))
+}
+
+// Helper function to check if user is owner or staff
+fn is_authorized(user_key: &Pubkey, game_state: &GameState) -> bool {
+    user_key == &game_state.owner || game_state.staff.contains(user_key)
+}
+
+// Helper function to check if contract is paused
+fn require_not_paused(game_state: &GameState) -> Result<()> {
+    require!(!game_state.is_paused, SportsError::ContractPaused);
+    Ok(())
+}
+
+// Pure business logic for creating player data
+fn create_player_data(
+    player_id: u16,
+    provider_id: u16,
+    category: PlayerCategory,
+    total_tokens: u32,
+    metadata_uri: Option<String>,
+    name: String,
+    discipline: String,
+    country: String,
+) -> (PlayerData, PlayerSummary) {
+    let player_data = PlayerData {
+        id: player_id,
+        provider_id,
+        category: category.clone(),
+        total_tokens,
+        tokens_sold: 0,
+        metadata_uri,
+        name,
+        discipline,
+        country,
+    };
+    
+    let player_summary = PlayerSummary {
+        id: player_id,
+        category,
+        available_tokens: total_tokens,
+    };
+    
+    (player_data, player_summary)
+}
+
+// Pure function to apply player data to account
+fn apply_player_data(player_account: &mut Player, player_data: &PlayerData) {
+    player_account.id = player_data.id;
+    player_account.provider_id = player_data.provider_id;
+    player_account.category = player_data.category;  // Ya es PlayerCategory
+    player_account.total_tokens = player_data.total_tokens;
+    player_account.tokens_sold = player_data.tokens_sold;
+    player_account.metadata_uri = player_data.metadata_uri.clone();  // Ya es Option<String>
+    player_account.name = player_data.name.clone();
+    player_account.discipline = player_data.discipline.clone();
+    player_account.country = player_data.country.clone();
+    player_account.version = 0;
+}
+
+// Pure business logic for updating player data
+fn update_player_data(
+    current_player: &Player,
+    provider_id: Option<u16>,
+    category: Option<PlayerCategory>,
+    total_tokens: Option<u32>,
+    metadata_uri: Option<Option<String>>,
+    name: Option<String>,
+    discipline: Option<String>,
+    country: Option<String>,
+) -> Result<PlayerUpdateData> {
+    // Validate that new total_tokens is not less than tokens_sold
+    if let Some(new_total) = total_tokens {
+        if new_total < current_player.tokens_sold {
+            return Err(SportsError::InvalidTokenUpdate.into());
+        }
+    }
+
+    let update_data = PlayerUpdateData {
+        provider_id,
+        category,
+        total_tokens,
+        metadata_uri,
+        name,
+        discipline,
+        country,
+    };
+
+    Ok(update_data)
+}
+
+// Pure function listing the fields an update would actually change (old -> new)
+fn diff_player_updates(current_player: &Player, update_data: &PlayerUpdateData) -> Vec<PlayerFieldChange> {
+    let mut changes = Vec::new();
+
+    if let Some(provider_id) = update_data.provider_id {
+        if provider_id != current_player.provider_id {
+            changes.push(PlayerFieldChange::ProviderId { old: current_player.provider_id, new: provider_id });
+        }
+    }
+    if let Some(category) = update_data.category {
+        if category != current_player.category {
+            changes.push(PlayerFieldChange::Category { old: current_player.category, new: category });
+        }
+    }
+    if let Some(total_tokens) = update_data.total_tokens {
+        if total_tokens != current_player.total_tokens {
+            changes.push(PlayerFieldChange::TotalTokens { old: current_player.total_tokens, new: total_tokens });
+        }
+    }
+    if let Some(metadata_uri) = &update_data.metadata_uri {
+        if metadata_uri != &current_player.metadata_uri {
+            changes.push(PlayerFieldChange::MetadataUri {
+                old: current_player.metadata_uri.clone(),
+                new: metadata_uri.clone(),
+            });
+        }
+    }
+    if let Some(name) = &update_data.name {
+        if name != &current_player.name {
+            changes.push(PlayerFieldChange::Name { old: current_player.name.clone(), new: name.clone() });
+        }
+    }
+    if let Some(discipline) = &update_data.discipline {
+        if discipline != &current_player.discipline {
+            changes.push(PlayerFieldChange::Discipline {
+                old: current_player.discipline.clone(),
+                new: discipline.clone(),
+            });
+        }
+    }
+    if let Some(country) = &update_data.country {
+        if country != &current_player.country {
+            changes.push(PlayerFieldChange::Country { old: current_player.country.clone(), new: country.clone() });
+        }
+    }
+
+    changes
+}
+
+// Pure function to apply updates to player account
+fn apply_player_updates(player_account: &mut Player, update_data: &PlayerUpdateData) {
+    if let Some(provider_id) = update_data.provider_id {
+        player_account.provider_id = provider_id;
+    }
+    if let Some(category) = update_data.category {
+        player_account.category = category;  // Ya es PlayerCategory
+    }
+    if let Some(total_tokens) = update_data.total_tokens {
+        player_account.total_tokens = total_tokens;
+    }
+    if let Some(metadata_uri) = &update_data.metadata_uri {
+        player_account.metadata_uri = metadata_uri.clone();  // Ya es Option<String>
+    }
+    if let Some(name) = &update_data.name {
+        player_account.name = name.clone();
+    }
+    if let Some(discipline) = &update_data.discipline {
+        player_account.discipline = discipline.clone();
+    }
+    if let Some(country) = &update_data.country {
+        player_account.country = country.clone();
+    }
+}
+
+// Struct for pure player data (not tied to Anchor)
+#[derive(Clone, Debug, PartialEq)]
+struct PlayerData {
     id: u16,
     provider_id: u16,
     category: PlayerCategory,
@@ -1279,1397 +4310,6595 @@ struct PlayerData {
     discipline: String,
     country: String,
 }
- impl PlayerData {
-    pub fn new(
-        id: u16,
-        provider_id: u16,
-        category: PlayerCategory,
-        total_tokens: u32,
-        metadata_uri: Option<String>,
-        name: String,
-        discipline: String,
-        country: String,    
-    ) -> Self {
-        Self {
-            id,
-            provider_id,
-            category,
-            total_tokens,
-            tokens_sold: 0, // Inicialmente no se han vendido tokens
-            metadata_uri,
-            name,
-            discipline,
-            country,
+ impl PlayerData {
+    pub fn new(
+        id: u16,
+        provider_id: u16,
+        category: PlayerCategory,
+        total_tokens: u32,
+        metadata_uri: Option<String>,
+        name: String,
+        discipline: String,
+        country: String,    
+    ) -> Self {
+        Self {
+            id,
+            provider_id,
+            category,
+            total_tokens,
+            tokens_sold: 0, // Inicialmente no se han vendido tokens
+            metadata_uri,
+            name,
+            discipline,
+            country,
+        }
+    }
+ }
+// Struct for player update data
+#[derive(Clone, Debug, PartialEq)]
+struct PlayerUpdateData {
+    provider_id: Option<u16>,
+    category: Option<PlayerCategory>,
+    total_tokens: Option<u32>,
+    metadata_uri: Option<Option<String>>,
+    name: Option<String>,
+    discipline: Option<String>,
+    country: Option<String>,    
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = GameState::SPACE,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(provider_id: u16, category: PlayerCategory, total_tokens: u32, metadata_uri: Option<String>)]
+pub struct CreatePlayer<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = Player::SPACE,
+        seeds = [b"player", game_state.next_player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub player_account: Account<'info, Player>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_id: u16)]
+pub struct AddTokens<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        mut,
+        seeds = [b"player", player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub player_account: Account<'info, Player>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageStaff<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_id: u16)]
+pub struct UpdatePlayer<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        mut,
+        seeds = [b"player", player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub player_account: Account<'info, Player>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_id: u16)]
+pub struct ProposeCategoryChange<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        seeds = [b"player", player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub player_account: Account<'info, Player>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = CategoryChangeProposal::SPACE,
+        seeds = [b"category_change", player_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub category_change: Account<'info, CategoryChangeProposal>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_id: u16)]
+pub struct ApproveCategoryChange<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        mut,
+        seeds = [b"player", player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub player_account: Account<'info, Player>,
+    
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"category_change", player_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub category_change: Account<'info, CategoryChangeProposal>,
+    
+    /// CHECK: Receives the proposal rent, must match the proposer
+    #[account(
+        mut,
+        constraint = proposer.key() == category_change.proposer @ SportsError::InvalidAccountsProvided
+    )]
+    pub proposer: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_id: u16)]
+pub struct CancelCategoryChange<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"category_change", player_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub category_change: Account<'info, CategoryChangeProposal>,
+    
+    /// CHECK: Receives the proposal rent, must match the proposer
+    #[account(
+        mut,
+        constraint = proposer.key() == category_change.proposer @ SportsError::InvalidAccountsProvided
+    )]
+    pub proposer: UncheckedAccount<'info>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AuditPlayers<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_id: u16)]
+pub struct ScheduleSupplyDrop<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        seeds = [b"player", player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub player_account: Account<'info, Player>,
+    
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = SupplySchedule::SPACE,
+        seeds = [b"supply_schedule", player_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub supply_schedule: Account<'info, SupplySchedule>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_id: u16)]
+pub struct CancelSupplyDrop<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        mut,
+        seeds = [b"supply_schedule", player_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub supply_schedule: Account<'info, SupplySchedule>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_id: u16)]
+pub struct ReleaseScheduledSupply<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        mut,
+        seeds = [b"player", player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub player_account: Account<'info, Player>,
+    
+    #[account(
+        mut,
+        seeds = [b"supply_schedule", player_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub supply_schedule: Account<'info, SupplySchedule>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    package_id: u16,
+    terms_accepted: bool,
+    presale_proof: Vec<[u8; 32]>,
+    payment_mint: Pubkey,
+    max_price: u64,
+    gift_message_hash: Option<[u8; 32]>,
+    coupon_code: Option<String>,
+)]
+pub struct BuyTeam<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        init,
+        payer = fee_payer,
+        space = Team::SPACE,
+        seeds = [b"team", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub team_account: Account<'info, Team>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// Pays rent and transaction fees: user itself, or an allowlisted sponsor (gasless purchase)
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    /// Allowlist entry of fee_payer, required when fee_payer != user
+    #[account(
+        mut,
+        seeds = [b"sponsor", fee_payer.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub sponsor: Option<Box<Account<'info, Sponsor>>>,
+
+    /// Sponsor's token account in payment_mint, receives the rent-recovery fee
+    #[account(
+        mut,
+        constraint = sponsor_fee_account.mint == payment_mint @ SportsError::InvalidUsdcMint,
+        constraint = sponsor_fee_account.owner == fee_payer.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub sponsor_fee_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
+    
+    /// User's token account in payment_mint (source)
+    #[account(
+        mut,
+        constraint = user_usdc_account.mint == payment_mint @ SportsError::InvalidUsdcMint,
+        constraint = user_usdc_account.owner == user.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub user_usdc_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+    
+    /// Program's token account in payment_mint (destination/treasury)
+    #[account(
+        mut,
+        constraint = program_usdc_account.mint == payment_mint @ SportsError::InvalidUsdcMint,
+        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_usdc_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
+
+    /// payment_mint itself: transfer_checked needs it (Token-2022 mints like PYUSD reject plain transfers)
+    #[account(address = payment_mint @ SportsError::InvalidUsdcMint)]
+    pub payment_mint_account: Box<InterfaceAccount<'info, token_interface::Mint>>,
+
+    /// Program that owns payment_mint (SPL Token or Token-2022)
+    pub payment_token_program: Interface<'info, TokenInterface>,
+
+    /// Registry entry for payment_mint (None when paying with game_state.mint_usdc)
+    #[account(
+        mut,
+        seeds = [b"accepted_mint", payment_mint.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub accepted_mint: Option<Box<Account<'info, AcceptedMint>>>,
+
+    /// Optional discount coupon, the PDA of keccak(coupon_code)
+    #[account(
+        mut,
+        seeds = [b"coupon", coupon_code_hash(coupon_code.as_deref().unwrap_or_default()).as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub coupon: Option<Box<Account<'info, Coupon>>>,
+
+    /// CHECK: Per-wallet redemption PDA [b"coupon_redemption", coupon, user], created on first use
+    #[account(mut)]
+    pub coupon_redemption: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: Buyer's Referral PDA, created on the first purchase when it comes with a referrer
+    #[account(
+        mut,
+        seeds = [b"referral", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub referral: UncheckedAccount<'info>,
+
+    /// Referrer credited with the commission (must match the bound referrer)
+    #[account(mut)]
+    pub referrer_account: Option<Box<Account<'info, ReferrerAccount>>>,
+
+    /// CHECK: Referrer's own Referral PDA, must be empty (referrers can't have a referrer: no loops)
+    pub referrer_referral: Option<UncheckedAccount<'info>>,
+
+    /// What was paid for this team, kept for refund_team
+    #[account(
+        init,
+        payer = fee_payer,
+        space = PurchaseReceipt::SPACE,
+        seeds = [b"purchase_receipt", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub purchase_receipt: Box<Account<'info, PurchaseReceipt>>,
+    
+    /// PDA authority for program's USDC account
+    /// CHECK: This is validated through constraint and used as authority
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+    
+    pub clock: Sysvar<'info, Clock>,
+    
+    pub system_program: Program<'info, System>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    // NFT Minting accounts
+    #[account(
+        init,
+        payer = fee_payer,
+        mint::decimals = 0,
+        mint::authority = game_state,
+        mint::freeze_authority = game_state,
+        seeds = [b"nft_mint", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub nft_mint: Account<'info, Mint>,
+    
+    /// CHECK: Metadata account PDA derivado correctamente
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            mpl_token_metadata::ID.as_ref(),
+            nft_mint.key().as_ref(),
+        ],
+        bump,
+        seeds::program = mpl_token_metadata::ID,
+    )]
+    pub metadata_account: UncheckedAccount<'info>,
+    
+    /// CHECK: NFT ATA of recipient (or user when not a gift), created in the handler with user as payer
+    #[account(mut)]
+    pub user_nft_account: UncheckedAccount<'info>,
+
+    /// CHECK: Optional gift recipient: holds the NFT and is recorded as first_buyer
+    pub recipient: Option<UncheckedAccount<'info>>,
+    
+    /// CHECK: Metaplex Token Metadata Program
+    #[account(
+        constraint = metadata_program.key() == mpl_token_metadata::ID @ SportsError::InvalidAccountsProvided
+    )]
+    pub metadata_program: UncheckedAccount<'info>,
+    
+    /// CHECK: Update authority account for NFT metadata
+    #[account(
+        constraint = update_authority.key() == game_state.nft_update_authority @ SportsError::InvalidAccountsProvided
+    )]
+    pub update_authority: UncheckedAccount<'info>,
+    
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+
+    /// CHECK: Chainlink SOL/USD feed (validado en runtime, solo con EntropySourceKind::Chainlink)
+    pub sol_usd_feed: AccountInfo<'info>,
+    /// CHECK: Chainlink program (validado en runtime, solo con EntropySourceKind::Chainlink)
+    pub chainlink_program: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+
+    /// Needed once a scheduled price change is due (buy_team applies and logs it)
+    #[account(
+        mut,
+        seeds = [b"price_history", game_state.key().as_ref()],
+        bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    /// Per-user pity counter (packs opened without a Gold athlete)
+    #[account(
+        init_if_needed,
+        payer = fee_payer,
+        space = PackHistory::SPACE,
+        seeds = [b"pack_history", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub pack_history: Box<Account<'info, PackHistory>>,
+
+    /// CHECK: Per-wallet presale counter PDA [b"presale_purchases", presale_merkle_root, user, game_state],
+    /// only during the presale; created on the wallet's first presale purchase
+    #[account(mut)]
+    pub presale_purchases: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(package_id: u16)]
+pub struct BuyTeamWithSol<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = Team::SPACE,
+        seeds = [b"team", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub team_account: Account<'info, Team>,
+    
+    /// What was paid for this team, kept for refund_team
+    #[account(
+        init,
+        payer = user,
+        space = PurchaseReceipt::SPACE,
+        seeds = [b"purchase_receipt", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub purchase_receipt: Box<Account<'info, PurchaseReceipt>>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// Tesorería SOL del programa (recibe los pagos en lamports)
+    #[account(
+        mut,
+        seeds = [b"sol_treasury", game_state.key().as_ref()],
+        bump
+    )]
+    pub sol_treasury: SystemAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+    
+    pub token_program: Program<'info, Token>,
+    
+    // NFT Minting accounts
+    #[account(
+        init,
+        payer = user,
+        mint::decimals = 0,
+        mint::authority = game_state,
+        mint::freeze_authority = game_state,
+        seeds = [b"nft_mint", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub nft_mint: Account<'info, Mint>,
+    
+    /// CHECK: Metadata account PDA derivado correctamente
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            mpl_token_metadata::ID.as_ref(),
+            nft_mint.key().as_ref(),
+        ],
+        bump,
+        seeds::program = mpl_token_metadata::ID,
+    )]
+    pub metadata_account: UncheckedAccount<'info>,
+    
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = nft_mint,
+        associated_token::authority = user,
+    )]
+    pub user_nft_account: Account<'info, TokenAccount>,
+    
+    /// CHECK: Metaplex Token Metadata Program
+    #[account(
+        constraint = metadata_program.key() == mpl_token_metadata::ID @ SportsError::InvalidAccountsProvided
+    )]
+    pub metadata_program: UncheckedAccount<'info>,
+    
+    /// CHECK: Update authority account for NFT metadata
+    #[account(
+        constraint = update_authority.key() == game_state.nft_update_authority @ SportsError::InvalidAccountsProvided
+    )]
+    pub update_authority: UncheckedAccount<'info>,
+    
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+
+    /// CHECK: Chainlink SOL/USD feed (validado en runtime, solo con EntropySourceKind::Chainlink)
+    pub sol_usd_feed: AccountInfo<'info>,
+    /// CHECK: Chainlink program (validado en runtime, solo con EntropySourceKind::Chainlink)
+    pub chainlink_program: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+
+    /// Needed once a scheduled price change is due (applied and logged before pricing the purchase)
+    #[account(
+        mut,
+        seeds = [b"price_history", game_state.key().as_ref()],
+        bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    /// Per-user pity counter (packs opened without a Gold athlete)
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PackHistory::SPACE,
+        seeds = [b"pack_history", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub pack_history: Box<Account<'info, PackHistory>>,
+}
+
+#[derive(Accounts)]
+#[instruction(package_id: u16)]
+pub struct BuyTeams<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// User's USDC token account (source)
+    #[account(
+        mut,
+        constraint = user_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = user_usdc_account.owner == user.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub user_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// Program's USDC token account (destination/treasury)
+    #[account(
+        mut,
+        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// PDA authority for program's USDC account
+    /// CHECK: This is validated through constraint and used as authority
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Metaplex Token Metadata Program
+    #[account(
+        constraint = metadata_program.key() == mpl_token_metadata::ID @ SportsError::InvalidAccountsProvided
+    )]
+    pub metadata_program: UncheckedAccount<'info>,
+
+    /// CHECK: Update authority account for NFT metadata
+    #[account(
+        constraint = update_authority.key() == game_state.nft_update_authority @ SportsError::InvalidAccountsProvided
+    )]
+    pub update_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Chainlink SOL/USD feed (validado en runtime, solo con EntropySourceKind::Chainlink)
+    pub sol_usd_feed: AccountInfo<'info>,
+    /// CHECK: Chainlink program (validado en runtime, solo con EntropySourceKind::Chainlink)
+    pub chainlink_program: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+
+    /// Needed once a scheduled price change is due (applied and logged before pricing the purchase)
+    #[account(
+        mut,
+        seeds = [b"price_history", game_state.key().as_ref()],
+        bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    /// Per-user pity counter (packs opened without a Gold athlete)
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PackHistory::SPACE,
+        seeds = [b"pack_history", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub pack_history: Box<Account<'info, PackHistory>>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl BuyTeams<'_> {
+    // Bounded by compute: each team creates 5 accounts and 3 CPIs to token programs
+    pub const MAX_TEAMS: usize = 4;
+    pub const ACCOUNTS_PER_TEAM: usize = 5;
+}
+
+#[derive(Accounts)]
+pub struct UpdateTeamPrices<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"package", PackageConfig::LEGACY_A.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_a: Account<'info, PackageConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"package", PackageConfig::LEGACY_B.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_b: Account<'info, PackageConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"package", PackageConfig::LEGACY_C.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_c: Account<'info, PackageConfig>,
+
+    /// Created here so the purchase paths can log the change once it is due
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PriceHistory::SPACE,
+        seeds = [b"price_history", game_state.key().as_ref()],
+        bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(package_id: u16)]
+pub struct SchedulePackagePrice<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Account<'info, PackageConfig>,
+
+    /// Created here so the purchase paths can log the change once it is due
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = PriceHistory::SPACE,
+        seeds = [b"price_history", game_state.key().as_ref()],
+        bump
+    )]
+    pub price_history: Box<Account<'info, PriceHistory>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateGameState<'info> {
+    /// CHECK: Decoded with the legacy layout in migrate_game_state (still not a valid GameState)
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(player_id: u16)]
+pub struct MigratePlayer<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    /// CHECK: Decoded with the legacy layout in migrate_player
+    #[account(
+        mut,
+        seeds = [b"player", player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub player: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(report_id: u64)]
+pub struct MigrateReport<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    /// CHECK: Decoded with the legacy layout in migrate_report
+    #[account(
+        mut,
+        seeds = [b"report", report_id.to_le_bytes().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub report: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateLegacyPackages<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        init,
+        payer = user,
+        space = PackageConfig::SPACE,
+        seeds = [b"package", PackageConfig::LEGACY_A.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_a: Account<'info, PackageConfig>,
+
+    #[account(
+        init,
+        payer = user,
+        space = PackageConfig::SPACE,
+        seeds = [b"package", PackageConfig::LEGACY_B.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_b: Account<'info, PackageConfig>,
+
+    #[account(
+        init,
+        payer = user,
+        space = PackageConfig::SPACE,
+        seeds = [b"package", PackageConfig::LEGACY_C.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_c: Account<'info, PackageConfig>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(package_id: u16)]
+pub struct CreatePackageConfig<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        init,
+        payer = user,
+        space = PackageConfig::SPACE,
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Account<'info, PackageConfig>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(package_id: u16)]
+pub struct UpdatePackageConfig<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Account<'info, PackageConfig>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(package_id: u16)]
+pub struct QuotePackOdds<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+}
+
+#[derive(Accounts)]
+#[instruction(package_id: u16)]
+pub struct GetPackageSaleStatus<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Account<'info, PackageConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(package_id: u16)]
+pub struct StartDraft<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(
+        mut,
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+
+    /// Needed once a scheduled price change is due (applied and logged before pricing the purchase)
+    #[account(
+        mut,
+        seeds = [b"price_history", game_state.key().as_ref()],
+        bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    /// One open draft per user
+    #[account(
+        init,
+        payer = user,
+        space = Draft::SPACE,
+        seeds = [b"draft", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub draft: Box<Account<'info, Draft>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// User's USDC token account (source)
+    #[account(
+        mut,
+        constraint = user_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = user_usdc_account.owner == user.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub user_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// Program's USDC token account (destination/treasury)
+    #[account(
+        mut,
+        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// PDA authority for program's USDC account
+    /// CHECK: This is validated through constraint and used as authority
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    /// CHECK: Chainlink SOL/USD feed (validado en runtime, solo con EntropySourceKind::Chainlink)
+    pub sol_usd_feed: AccountInfo<'info>,
+    /// CHECK: Chainlink program (validado en runtime, solo con EntropySourceKind::Chainlink)
+    pub chainlink_program: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinishDraft<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(
+        mut,
+        close = buyer,
+        seeds = [b"draft", buyer.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub draft: Box<Account<'info, Draft>>,
+
+    /// CHECK: Buyer recorded in the draft (bound by the draft seeds), receives the NFT and the draft rent
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// Buyer (finish_draft) or anyone after the deadline (auto_finish_draft); pays the new accounts
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = Team::SPACE,
+        seeds = [b"team", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub team_account: Box<Account<'info, Team>>,
+
+    /// What was paid for this team, kept for refund_team
+    #[account(
+        init,
+        payer = payer,
+        space = PurchaseReceipt::SPACE,
+        seeds = [b"purchase_receipt", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub purchase_receipt: Box<Account<'info, PurchaseReceipt>>,
+
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = game_state,
+        mint::freeze_authority = game_state,
+        seeds = [b"nft_mint", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub nft_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: Metadata account PDA derivado correctamente
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            mpl_token_metadata::ID.as_ref(),
+            nft_mint.key().as_ref(),
+        ],
+        bump,
+        seeds::program = mpl_token_metadata::ID,
+    )]
+    pub metadata_account: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = buyer,
+    )]
+    pub user_nft_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex Token Metadata Program
+    #[account(
+        constraint = metadata_program.key() == mpl_token_metadata::ID @ SportsError::InvalidAccountsProvided
+    )]
+    pub metadata_program: UncheckedAccount<'info>,
+
+    /// CHECK: Update authority account for NFT metadata
+    #[account(
+        constraint = update_authority.key() == game_state.nft_update_authority @ SportsError::InvalidAccountsProvided
+    )]
+    pub update_authority: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"package", draft.package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RefundDraft<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(
+        mut,
+        close = buyer,
+        seeds = [b"draft", buyer.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub draft: Box<Account<'info, Draft>>,
+
+    /// CHECK: Buyer recorded in the draft (bound by the draft seeds), receives the draft rent
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// Buyer, or anyone after the deadline
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"package", draft.package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+
+    /// Buyer's USDC token account (refund destination)
+    #[account(
+        mut,
+        constraint = buyer_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = buyer_usdc_account.owner == buyer.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub buyer_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// Program's USDC token account (treasury)
+    #[account(
+        mut,
+        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// PDA authority for program's USDC account
+    /// CHECK: This is validated through constraint and used as authority
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(package_id: u16)]
+pub struct CommitPack<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [b"package", package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+
+    /// Needed once a scheduled price change is due (applied and logged before pricing the purchase)
+    #[account(
+        mut,
+        seeds = [b"price_history", game_state.key().as_ref()],
+        bump
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    /// One pending commit per user
+    #[account(
+        init,
+        payer = user,
+        space = PackCommit::SPACE,
+        seeds = [b"pack_commit", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub pack_commit: Box<Account<'info, PackCommit>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// User's USDC token account (source)
+    #[account(
+        mut,
+        constraint = user_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = user_usdc_account.owner == user.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub user_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// Program's USDC token account (destination/treasury)
+    #[account(
+        mut,
+        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// PDA authority for program's USDC account
+    /// CHECK: This is validated through constraint and used as authority
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealPack<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
+
+    #[account(
+        mut,
+        close = buyer,
+        seeds = [b"pack_commit", buyer.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub pack_commit: Box<Account<'info, PackCommit>>,
+
+    /// CHECK: Buyer recorded in the commit (bound by the pack_commit seeds), receives the NFT and the commit rent
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+
+    /// Whoever reveals (buyer or crank) pays the rent of the new accounts
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = Team::SPACE,
+        seeds = [b"team", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub team_account: Box<Account<'info, Team>>,
+
+    /// What was paid for this team, kept for refund_team
+    #[account(
+        init,
+        payer = payer,
+        space = PurchaseReceipt::SPACE,
+        seeds = [b"purchase_receipt", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub purchase_receipt: Box<Account<'info, PurchaseReceipt>>,
+
+    #[account(
+        init,
+        payer = payer,
+        mint::decimals = 0,
+        mint::authority = game_state,
+        mint::freeze_authority = game_state,
+        seeds = [b"nft_mint", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub nft_mint: Box<Account<'info, Mint>>,
+
+    /// CHECK: Metadata account PDA derivado correctamente
+    #[account(
+        mut,
+        seeds = [
+            b"metadata",
+            mpl_token_metadata::ID.as_ref(),
+            nft_mint.key().as_ref(),
+        ],
+        bump,
+        seeds::program = mpl_token_metadata::ID,
+    )]
+    pub metadata_account: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = nft_mint,
+        associated_token::authority = buyer,
+    )]
+    pub user_nft_account: Box<Account<'info, TokenAccount>>,
+
+    /// CHECK: Metaplex Token Metadata Program
+    #[account(
+        constraint = metadata_program.key() == mpl_token_metadata::ID @ SportsError::InvalidAccountsProvided
+    )]
+    pub metadata_program: UncheckedAccount<'info>,
+
+    /// CHECK: Update authority account for NFT metadata
+    #[account(
+        constraint = update_authority.key() == game_state.nft_update_authority @ SportsError::InvalidAccountsProvided
+    )]
+    pub update_authority: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"package", pack_commit.package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+
+    /// Buyer's pity counter
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PackHistory::SPACE,
+        seeds = [b"pack_history", buyer.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub pack_history: Box<Account<'info, PackHistory>>,
+
+    /// CHECK: SlotHashes sysvar, read raw (too large to deserialize)
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ExpirePackCommit<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        close = buyer,
+        seeds = [b"pack_commit", buyer.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub pack_commit: Box<Account<'info, PackCommit>>,
+
+    /// CHECK: Buyer recorded in the commit (bound by the pack_commit seeds), receives the commit rent
+    #[account(mut)]
+    pub buyer: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"package", pack_commit.package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
+
+    /// Buyer's USDC token account (refund destination)
+    #[account(
+        mut,
+        constraint = buyer_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = buyer_usdc_account.owner == buyer.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub buyer_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// Program's USDC token account (treasury)
+    #[account(
+        mut,
+        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_usdc_account: Box<Account<'info, TokenAccount>>,
+
+    /// PDA authority for program's USDC account
+    /// CHECK: This is validated through constraint and used as authority
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    /// CHECK: SlotHashes sysvar, to tell whether an AutoReveal commit can still be revealed
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetCommitRevealConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetEntropySource<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetOracleConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetReferralCommission<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        init,
+        payer = user,
+        space = ReferrerAccount::SPACE,
+        seeds = [b"referrer", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub referrer_account: Account<'info, ReferrerAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralCommission<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [b"referrer", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub referrer_account: Account<'info, ReferrerAccount>,
+
+    /// Program's USDC token account (treasury)
+    #[account(
+        mut,
+        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_usdc_account: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA authority for program's USDC account
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    /// Referrer's USDC token account (destination)
+    #[account(
+        mut,
+        constraint = user_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = user_usdc_account.owner == user.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub user_usdc_account: Account<'info, TokenAccount>,
+
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetPresaleConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPityThreshold<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetNftImageUrl<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(staker_pool: u64, stakers_count: u32)]
+pub struct CloseReport<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        init,
+        payer = user,
+        space = Report::SPACE,
+        seeds = [b"report", game_state.current_report_id.to_le_bytes().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub report: Account<'info, Report>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(report_id: u64, proposal_id: u64)]
+pub struct PayStakerRewards<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [b"report", report_id.to_le_bytes().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub report: Account<'info, Report>,
+
+    /// Approved proposal for this batch (destination = report, amount = total)
+    #[account(
+        mut,
+        seeds = [b"withdrawal_proposal", proposal_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub proposal: Box<Account<'info, WithdrawalProposal>>,
+
+    /// Program's USDC token account (treasury)
+    #[account(
+        mut,
+        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_usdc_account: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA authority for program's USDC account
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(report_id: u64)]
+pub struct ReleaseStakerPool<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [b"report", report_id.to_le_bytes().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub report: Account<'info, Report>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SeedTreasuryLedger<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    /// Program's USDC token account (treasury)
+    #[account(
+        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
+        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_usdc_account: Account<'info, TokenAccount>,
+
+    /// CHECK: PDA authority for program's USDC account
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    pub user: Signer<'info>,
+}
+
+
+// Context for initializing user stake state
+#[derive(Accounts)]
+pub struct InitializeUserStakeState<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init,
+        payer = user,
+        space = UserStakeState::SPACE,
+        seeds = [b"user_stake_state", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub user_stake_state: Account<'info, UserStakeState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Context for staking a team
+#[derive(Accounts)]
+#[instruction(team_id: u64)]
+pub struct StakeTeam<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        mut,
+        seeds = [b"team", team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub team_account: Account<'info, Team>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// User stake state tracking account (list of team IDs)
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = UserStakeState::SPACE,
+        seeds = [b"user_stake_state", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub user_stake_state: Account<'info, UserStakeState>,
+    
+    /// Individual team stake state account (detailed state)
+    #[account(
+        init,
+        payer = user,
+        space = TeamStakeState::SPACE,
+        seeds = [b"team_stake_state", user.key().as_ref(), team_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub team_stake_state: Account<'info, TeamStakeState>,
+    
+    /// User's NFT account (source)
+    #[account(
+        mut,
+        constraint = user_nft_account.mint == team_account.nft_mint @ SportsError::InvalidNftMint,
+        constraint = user_nft_account.owner == user.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub user_nft_account: Account<'info, TokenAccount>,
+    
+    /// Program's NFT account (destination)
+    #[account(
+        mut,
+        constraint = program_nft_account.mint == team_account.nft_mint @ SportsError::InvalidNftMint,
+        constraint = program_nft_account.owner == program_nft_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_nft_account: Account<'info, TokenAccount>,
+    
+    /// PDA authority for program's NFT account
+    /// CHECK: This is validated through constraint and used as authority
+    #[account(
+        seeds = [b"nft_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_nft_authority: UncheckedAccount<'info>,
+    
+    /// Clock for timestamp
+    pub clock: Sysvar<'info, Clock>,
+    
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+// Context for withdrawing a team
+#[derive(Accounts)]
+#[instruction(team_id: u64)]
+pub struct WithdrawTeam<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(
+        mut,
+        seeds = [b"team", team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub team_account: Account<'info, Team>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// User stake state tracking account (list of team IDs)
+    #[account(
+        mut,
+        seeds = [b"user_stake_state", user.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub user_stake_state: Account<'info, UserStakeState>,
+    
+    /// Individual team stake state account (detailed state)
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = TeamStakeState::SPACE,
+        seeds = [b"team_stake_state", user.key().as_ref(), team_id.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub team_stake_state: Account<'info, TeamStakeState>,
+    
+    /// User's NFT token account (destination)
+    #[account(
+        mut,
+        constraint = user_nft_account.mint == team_account.nft_mint @ SportsError::InvalidNftMint,
+        constraint = user_nft_account.owner == user.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub user_nft_account: Account<'info, TokenAccount>,
+    
+    /// Program's NFT token account (source)
+    #[account(
+        mut,
+        constraint = program_nft_account.mint == team_account.nft_mint @ SportsError::InvalidNftMint,
+        constraint = program_nft_account.owner == program_nft_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub program_nft_account: Account<'info, TokenAccount>,
+    
+    /// PDA authority for program's NFT account
+    /// CHECK: This is validated through constraint and used as authority
+    #[account(
+        seeds = [b"nft_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_nft_authority: UncheckedAccount<'info>,
+    
+    /// Clock for timestamp
+    pub clock: Sysvar<'info, Clock>,
+    
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Context for refreshing team status
+#[derive(Accounts)]
+#[instruction(team_id: u64)]
+pub struct RefreshTeamStatus<'info> {
+    #[account(
+        mut,
+        seeds = [b"team", team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub team_account: Account<'info, Team>,
+    
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+    
+    #[account(mut)]
+    pub user: Signer<'info>,
+    
+    /// Clock for timestamp
+    pub clock: Sysvar<'info, Clock>,
+}
+
+// Main game account containing vec with minimal data
+#[account]
+pub struct GameState {
+    pub owner: Pubkey,
+    pub staff: Vec<Pubkey>,
+    pub players: Vec<PlayerSummary>,
+    pub next_player_id: u16,
+    pub mint_usdc: Pubkey,               // USDC token mint address
+    pub team_price_a: u64,
+    pub team_price_b: u64,
+    pub team_price_c: u64,
+    pub next_team_id: u64,
+    pub next_reward_id: u64,
+    pub nft_update_authority: Pubkey,    // update authority para NFTs
+    pub nft_image_url: String,           // NUEVO: url de imagen para NFTs
+    // Tracking del reporte actual
+    pub current_report_id: u64,        // ID del reporte que se está acumulando
+    pub current_report_start: i64,     // Timestamp de inicio del reporte actual
+    pub is_report_open: bool,          // Si el reporte actual está abierto para acumular
+    // Acumulados del reporte actual
+    pub current_report_revenue: u64,    // Revenue total acumulado en el reporte actual
+    pub current_report_teams: u32,      // Cantidad de equipos vendidos en el reporte actual
+    pub current_report_tokens: u32,     // Cantidad de tokens vendidos en el reporte actual
+    // Pausable functionality
+    pub is_paused: bool,                // Si el contrato está pausado
+    // Withdrawal security system
+    pub pending_withdrawal: Option<WithdrawalRequest>,  // Sin uso desde WithdrawalProposal (se mantiene por el layout)
+    // Time lock
+    pub time_lock: i64,                 // Tiempo de bloqueo
+    // Pity guarantee
+    pub pity_threshold: u32,            // Packs sin Gold antes de garantizar uno (0 = desactivado)
+    // Commit-reveal
+    pub reveal_window_slots: u64,       // Slots after target_slot before a commit expires
+    pub expired_commit_policy: ExpiredCommitPolicy,
+    // Entropía para buy_team
+    pub entropy_source: EntropySourceKind,
+    pub entropy_oracle: Pubkey,         // Randomness account read by EntropySourceKind::Oracle
+    // Presale (allowlist)
+    pub presale_merkle_root: [u8; 32],  // Raíz del allowlist ([0; 32] = sin presale)
+    pub presale_end: i64,               // Fase pública a partir de este timestamp
+    pub presale_wallet_cap: u32,        // Packs por wallet durante la presale (0 = sin límite)
+    // Pagos en SOL
+    pub current_report_sol_revenue: u64, // Lamports cobrados en el reporte actual (aparte del USDC)
+    // Cupones
+    pub current_report_coupon_redemptions: u32, // Cupones canjeados en el reporte actual
+    pub current_report_coupon_discount: u64,    // Descuento total (USDC) en el reporte actual
+    // Referidos
+    pub referral_commission_bps: u16,   // Comisión del referidor sobre cada venta en USDC (0 = desactivado)
+    pub referral_commissions_owed: u64, // Comisiones acumuladas sin reclamar (incluidas en treasury_reserved)
+    // Reembolsos
+    pub refund_window_secs: i64,        // Ventana de arrepentimiento desde created_at (0 = sin reembolsos del comprador)
+    // Oráculo SOL/USD (por cluster)
+    pub sol_usd_feed: Pubkey,           // Feed leído por los pagos en SOL y la entropía Chainlink
+    pub oracle_program: Pubkey,         // Programa dueño del feed (Chainlink, o este programa con test-oracle)
+    pub max_price_staleness_secs: i64,  // Antigüedad máxima aceptada de la ronda
+    // Retiros M-of-N
+    pub withdrawal_threshold: u8,       // Aprobaciones distintas (owner/staff) para ejecutar una WithdrawalProposal
+    pub next_withdrawal_proposal_id: u64,
+    // Tesorería (mint_usdc): el revenue de los reportes es histórico, los retiros salen de acá
+    pub treasury_received: u64,         // USDC cobrado por ventas, neto de reembolsos
+    pub treasury_withdrawn: u64,        // USDC retirado por propuestas o pagado como recompensas
+    pub treasury_reserved: u64,         // USDC apartado para recompensas (pools de stakers y comisiones de referidos)
+    pub accepted_mint_count: u8,        // AcceptedMints registradas (close_current_report las recibe todas)
+    pub treasury_ledger_seeded: bool,   // seed_treasury_ledger ya cargó el saldo previo al ledger
+}
+
+impl GameState {
+    pub const MAX_PLAYERS: usize = 1300;
+    // Space estimation: 8 (discriminator) + 32 (owner) + 4 (staff vec len) + (3 staff * 32) + 4 (players vec len) + (1300 players * PlayerSummary::SIZE) + 2 (next_player_id) + 32 (mint_usdc) + 24 (3 team prices u64) + 8 (next_team_id) + 8 (next_reward_id) + 8 (current_report_id) + 8 (current_report_start) + 1 (is_report_open) + 8 (current_report_revenue) + 4 (current_report_teams) + 4 (current_report_tokens) + 1 (is_paused) + 1 (option) + WithdrawalRequest::SIZE + 32 (nft_update_authority) + 1 (nft_image_url) + 1 (string) + 8 (time_lock) + 4 (pity_threshold) + 8 (reveal_window_slots) + 1 (expired_commit_policy) + 1 (entropy_source) + 32 (entropy_oracle) + 32 (presale_merkle_root) + 8 (presale_end) + 4 (presale_wallet_cap) + 8 (current_report_sol_revenue) + 4 (current_report_coupon_redemptions) + 8 (current_report_coupon_discount) + 2 (referral_commission_bps) + 8 (referral_commissions_owed) + 8 (refund_window_secs) + 32 (sol_usd_feed) + 32 (oracle_program) + 8 (max_price_staleness_secs) + 1 (withdrawal_threshold) + 8 (next_withdrawal_proposal_id) + 8 (treasury_received) + 8 (treasury_withdrawn) + 8 (treasury_reserved) + 1 (accepted_mint_count) + 1 (treasury_ledger_seeded)
+    // Total: 8 + 32 + 4 + 96 + 4 + (1300 * 7) + 2 + 32 + 24 + 8 + 8 + 8 + 8 + 1 + 8 + 4 + 4 + 1 + 1 + (32 + 8 + 8) + 32 + 1 + 1 + 100 + 8 + 4 + 8 + 1 + 1 + 32 + 32 + 8 + 4 + 8 + 4 + 8 + 2 + 8 + 8 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + 1 + 1 = 9,778 bytes
+    pub const SPACE: usize = 8 + 32 + 4 + (3 * 32) + 4 + (Self::MAX_PLAYERS * PlayerSummary::SIZE) + 2 + 32 + 24 + 8 + 8 + 8 + 8 + 1 + 8 + 4 + 4 + 1 + 1 + WithdrawalRequest::SIZE + 32 + 1 + 1 + 100 + 8 + 4 + 8 + 1 + 1 + 32 + 32 + 8 + 4 + 8 + 4 + 8 + 2 + 8 + 8 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + 1 + 1;
+
+    // Presale while an allowlist root is set and presale_end has not been reached
+    pub fn sale_phase(&self, now: i64) -> SalePhase {
+        if self.presale_merkle_root != [0; 32] && now < self.presale_end {
+            SalePhase::Presale
+        } else {
+            SalePhase::Public
+        }
+    }
+
+    // Treasury balance that is neither withdrawn nor reserved for rewards
+    pub fn treasury_available(&self) -> u64 {
+        self.treasury_received
+            .saturating_sub(self.treasury_withdrawn)
+            .saturating_sub(self.treasury_reserved)
+    }
+
+    // Set aside part of the available balance for rewards
+    pub fn reserve_treasury(&mut self, amount: u64) -> Result<()> {
+        require!(amount <= self.treasury_available(), SportsError::InsufficientFunds);
+        self.treasury_reserved = self.treasury_reserved.checked_add(amount).ok_or(SportsError::TokenOverflow)?;
+        Ok(())
+    }
+
+    // Pay out a reserved reward: leaves the treasury as withdrawn
+    pub fn pay_reserved(&mut self, amount: u64) -> Result<()> {
+        self.treasury_reserved = self.treasury_reserved.saturating_sub(amount);
+        self.treasury_withdrawn = self.treasury_withdrawn.checked_add(amount).ok_or(SportsError::TokenOverflow)?;
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum SalePhase {
+    Presale,       // Solo buy_team, con prueba Merkle y tope por wallet
+    Public,
+}
+
+// Estructura para solicitudes de retiro pendientes
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct WithdrawalRequest {
+    pub requester: Pubkey,              // Quien hizo la primera solicitud
+    pub amount: u64,                    // Cantidad a retirar
+    pub timestamp: i64,                 // Cuándo se hizo la solicitud
+}
+
+impl WithdrawalRequest {
+    // Space: 32 (requester) + 8 (amount) + 8 (timestamp)
+    pub const SIZE: usize = 32 + 8 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum WithdrawalProposalStatus {
+    Pending,
+    Executed,
+    Rejected,      // Too many rejections to reach the threshold
+    Cancelled,
+}
+
+// Propuesta de retiro de la tesorería (o de la bóveda de una AcceptedMint), aprobada por M de N
+#[account]
+pub struct WithdrawalProposal {
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub mint: Pubkey,                            // mint_usdc, an AcceptedMint or NATIVE_SOL_MINT
+    pub destination: Pubkey,                     // Token account in `mint` (a wallet for NATIVE_SOL_MINT, the Report for staker payouts)
+    pub amount: u64,
+    pub memo: String,                            // Max MAX_MEMO_LEN bytes
+    pub created_at: i64,
+    pub expires_at: i64,                         // Cannot be approved or executed from then on
+    pub approvals: Vec<Pubkey>,                  // Distinct approvers, proposer included
+    pub rejections: Vec<Pubkey>,
+    pub status: WithdrawalProposalStatus,
+    pub executed_at: i64,
+}
+
+impl WithdrawalProposal {
+    pub const MAX_MEMO_LEN: usize = 64;
+    pub const MAX_VOTERS: usize = 4;             // Owner + 3 staff
+    // Space: 8 (discriminator) + 8 (id) + 32 (proposer) + 32 (mint) + 32 (destination) + 8 (amount)
+    //        + 4 + 64 (memo) + 8 (created_at) + 8 (expires_at) + 4 + (4 * 32) (approvals)
+    //        + 4 + (4 * 32) (rejections) + 1 (status) + 8 (executed_at)
+    pub const SPACE: usize = 8 + 8 + 32 + 32 + 32 + 8 + 4 + Self::MAX_MEMO_LEN + 8 + 8
+        + 4 + (Self::MAX_VOTERS * 32) + 4 + (Self::MAX_VOTERS * 32) + 1 + 8;
+
+    pub fn has_voted(&self, voter: &Pubkey) -> bool {
+        self.approvals.contains(voter) || self.rejections.contains(voter)
+    }
+
+    // Drop votes of accounts that are no longer owner or staff (they don't count anyway), so a
+    // staff removed and re-added can't push the vote lists past MAX_VOTERS
+    pub fn prune_stale_votes(&mut self, game_state: &GameState) {
+        self.approvals.retain(|voter| is_authorized(voter, game_state));
+        self.rejections.retain(|voter| is_authorized(voter, game_state));
+    }
+
+    // Finished (executed, rejected or cancelled) or expired: its rent can be reclaimed
+    pub fn is_closable(&self, now: i64) -> bool {
+        self.status != WithdrawalProposalStatus::Pending || now >= self.expires_at
+    }
+
+    // Approvals from accounts that are still owner or staff
+    pub fn valid_approvals(&self, game_state: &GameState) -> usize {
+        self.approvals.iter().filter(|approver| is_authorized(approver, game_state)).count()
+    }
+
+    // Whether the voters that have not rejected can still reach withdrawal_threshold
+    pub fn can_reach_threshold(&self, game_state: &GameState) -> bool {
+        let voters = 1 + game_state.staff.len();
+        let rejections = self.rejections.iter().filter(|voter| is_authorized(voter, game_state)).count();
+        voters.saturating_sub(rejections) >= game_state.withdrawal_threshold as usize
+    }
+
+    // Open and approved by withdrawal_threshold current owner/staff
+    pub fn require_executable(&self, game_state: &GameState, now: i64) -> Result<()> {
+        self.require_open(now)?;
+        require!(
+            self.valid_approvals(game_state) >= game_state.withdrawal_threshold as usize,
+            SportsError::WithdrawalThresholdNotMet
+        );
+        Ok(())
+    }
+
+    // Pending and not expired
+    pub fn require_open(&self, now: i64) -> Result<()> {
+        require!(
+            self.status == WithdrawalProposalStatus::Pending,
+            SportsError::WithdrawalProposalNotPending
+        );
+        require!(now < self.expires_at, SportsError::WithdrawalProposalExpired);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_game_state_space_calculation() {
+    // Verify the space calculation is correct
+    let expected = 8 + 32 + 4 + (3 * 32) + 4 + (1300 * 7) + 2 + 32 + 24 + 8 + 8 + 8 + 8 + 1 + 8 + 4 + 4 + 1 + 1 + WithdrawalRequest::SIZE + 32 + 1 + 1 + 100 + 8 + 4 + 8 + 1 + 1 + 32 + 32 + 8 + 4 + 8 + 4 + 8 + 2 + 8 + 8 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + 1 + 1;
+    assert_eq!(GameState::SPACE, expected);
+    assert_eq!(GameState::SPACE, 9778);
+}
+
+// Individual PDA account for each player with complete information
+#[account]
+pub struct Player {
+    pub id: u16,
+    pub provider_id: u16,
+    pub category: PlayerCategory,  // Cambiado de u8 a PlayerCategory
+    pub total_tokens: u32,
+    pub tokens_sold: u32,
+    pub metadata_uri: Option<String>,  // Cambiado de String a Option<String>
+    pub name: String,
+    pub discipline: String,
+    pub country: String,
+    pub version: u32,                  // Incremented on every effective update
+}
+
+impl Player {
+    // Space: 8 (discriminator) + 2 (id u16) + 2 (provider_id) + 1 (category) + 4 (total_tokens) + 4 (tokens_sold) + 4 (option) + 100 (string max) + 100 (name) + 100 (discipline) + 100 (country) + 4 (version)
+    pub const SPACE: usize = 8 + 2 + 2 + 1 + 4 + 4 + 4 + 100 + 100 + 100 + 100 + 4;
+}
+
+// Pending category change for a player with tokens in circulation
+#[account]
+pub struct CategoryChangeProposal {
+    pub player_id: u16,
+    pub proposer: Pubkey,
+    pub new_category: PlayerCategory,
+    pub player_version: u32,           // Player version the proposal was made against
+    pub timestamp: i64,
+}
+
+impl CategoryChangeProposal {
+    // Space: 8 (discriminator) + 2 (player_id) + 32 (proposer) + 1 (new_category) + 4 (player_version) + 8 (timestamp)
+    pub const SPACE: usize = 8 + 2 + 32 + 1 + 4 + 8;
+}
+
+// Paid draft: offered athletes with their tokens in escrow until finish_draft
+#[account]
+pub struct Draft {
+    pub buyer: Pubkey,                               // 32 bytes
+    pub package_id: u16,                             // 2 bytes
+    pub offered_player_ids: Vec<u16>,                // 4 + (8 * 2) bytes
+    pub category_minimums: Vec<CategoryMinimum>,     // 4 + (3 * 2) bytes - snapshot of the package
+    pub price_paid_usdc: u64,                        // 8 bytes
+    pub terms_accepted: bool,                        // 1 byte
+    pub created_at: i64,                             // 8 bytes
+    pub deadline: i64,                               // 8 bytes - after it, auto_finish_draft
+}
+
+impl Draft {
+    pub const OFFERED: usize = 8;
+    pub const PICKS: usize = 5;
+    pub const DURATION_SECS: i64 = 24 * 60 * 60;
+    pub const SPACE: usize = 8 + 32 + 2 + 4 + (Self::OFFERED * 2) + 4 + (PackageConfig::MAX_MINIMUMS * CategoryMinimum::SIZE) + 8 + 1 + 8 + 8;
+}
+
+// Paid pack waiting for its target slot (commit-reveal)
+#[account]
+pub struct PackCommit {
+    pub buyer: Pubkey,                         // 32 bytes
+    pub package_id: u16,                       // 2 bytes
+    pub price_paid_usdc: u64,                  // 8 bytes
+    pub commitment: [u8; 32],                  // 32 bytes - client randomness
+    pub commit_slot: u64,                      // 8 bytes
+    pub target_slot: u64,                      // 8 bytes - its slot hash seeds the draw
+    pub terms_accepted: bool,                  // 1 byte
+    pub created_at: i64,                       // 8 bytes
+}
+
+impl PackCommit {
+    pub const SPACE: usize = 8 + 32 + 2 + 8 + 32 + 8 + 8 + 1 + 8;
+    pub const REVEAL_DELAY_SLOTS: u64 = 2;
+    // SlotHashes keeps the last 512 slots
+    pub const MAX_REVEAL_WINDOW_SLOTS: u64 = 512 - Self::REVEAL_DELAY_SLOTS;
+
+    pub fn is_expired(&self, current_slot: u64, reveal_window_slots: u64) -> bool {
+        current_slot > self.target_slot.saturating_add(reveal_window_slots)
+    }
+}
+
+// What happens to a commit nobody revealed within the window.
+// Refund lets a buyer who dislikes the (already computable) draw walk away, AutoReveal does not
+// while the target slot hash is still in SlotHashes (keep a reveal crank running).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ExpiredCommitPolicy {
+    AutoReveal, // Anyone can still reveal it; refunded once the target slot hash leaves SlotHashes
+    Refund,     // Anyone can close it and refund the buyer
+}
+
+// Entropy source used by buy_team, chosen in GameState
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum EntropySourceKind {
+    Chainlink,     // Latest Chainlink SOL/USD round (sol_usd_feed + chainlink_program)
+    SlotHashes,    // Test only: most recent slot hash, known before the transaction lands
+    Oracle,        // Test only: public randomness account, reused for up to MAX_STALENESS_SLOTS
+    Deterministic, // Local tests only
+}
+
+impl EntropySourceKind {
+    // Whether this build of the program can use the source. SlotHashes and Oracle are predictable
+    // (not VRF-like), so like Deterministic they require the `test-entropy` feature
+    pub fn is_available(&self) -> bool {
+        match self {
+            EntropySourceKind::Chainlink => true,
+            EntropySourceKind::SlotHashes
+            | EntropySourceKind::Oracle
+            | EntropySourceKind::Deterministic => cfg!(feature = "test-entropy"),
+        }
+    }
+
+    // Whether the source reads remaining_accounts[0]
+    pub fn needs_account(&self) -> bool {
+        matches!(self, EntropySourceKind::SlotHashes | EntropySourceKind::Oracle)
+    }
+}
+
+// Pity counter PDA per user
+#[account]
+pub struct PackHistory {
+    pub user: Pubkey,                          // 32 bytes
+    pub packs_without_gold: u32,               // 4 bytes - consecutive packs without a Gold pick
+    pub total_packs: u32,                      // 4 bytes
+    pub gold_guarantees: u32,                  // 4 bytes - times the guarantee kicked in
+}
+
+impl PackHistory {
+    pub const SPACE: usize = 8 + 32 + 4 + 4 + 4; // discriminator + user + counters
+
+    pub fn is_pity_due(&self, pity_threshold: u32) -> bool {
+        pity_threshold > 0 && self.packs_without_gold >= pity_threshold
+    }
+
+    pub fn record_pack(&mut self, got_gold: bool, gold_guaranteed: bool) -> Result<()> {
+        self.total_packs = self.total_packs.checked_add(1).ok_or(SportsError::TokenOverflow)?;
+        if gold_guaranteed {
+            self.gold_guarantees = self.gold_guarantees.checked_add(1).ok_or(SportsError::TokenOverflow)?;
+        }
+        if got_gold {
+            self.packs_without_gold = 0;
+        } else {
+            self.packs_without_gold = self.packs_without_gold
+                .checked_add(1)
+                .ok_or(SportsError::TokenOverflow)?;
+        }
+        Ok(())
+    }
+
+    // Deshacer un pack reembolsado. El contador de pity solo se restaura si es el último pack
+    // del usuario; si compró más después, ese contador ya refleja los packs posteriores
+    pub fn unrecord_pack(&mut self, pack_number: u32, packs_without_gold_before: u32, gold_guaranteed: bool) {
+        if self.total_packs == pack_number {
+            self.packs_without_gold = packs_without_gold_before;
+        }
+        self.total_packs = self.total_packs.saturating_sub(1);
+        if gold_guaranteed {
+            self.gold_guarantees = self.gold_guarantees.saturating_sub(1);
+        }
+    }
+}
+
+// Presale purchases PDA per wallet - capped by game_state.presale_wallet_cap.
+// Las seeds incluyen la raíz del allowlist: cada presale nueva empieza con contadores en cero
+#[account]
+pub struct PresalePurchases {
+    pub buyer: Pubkey,                         // 32 bytes
+    pub purchased: u32,                        // 4 bytes - packs bought during the presale
+}
+
+impl PresalePurchases {
+    pub const SPACE: usize = 8 + 32 + 4; // discriminator + buyer + purchased
+
+    pub fn record_purchase(&mut self, wallet_cap: u32) -> Result<()> {
+        require!(
+            wallet_cap == 0 || self.purchased < wallet_cap,
+            SportsError::PresaleWalletCapReached
+        );
+        self.purchased = self.purchased.checked_add(1).ok_or(SportsError::TokenOverflow)?;
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum CouponDiscount {
+    Bps(u16),      // Porcentaje en basis points (< 10_000)
+    Fixed(u64),    // Monto fijo en micro-USDC
+}
+
+// Hash under which a coupon code is stored and its PDA derived
+pub fn coupon_code_hash(code: &str) -> [u8; 32] {
+    anchor_lang::solana_program::keccak::hash(code.as_bytes()).0
+}
+
+// Cupón de descuento, PDA por hash del código
+#[account]
+pub struct Coupon {
+    pub code_hash: [u8; 32],                     // coupon_code_hash of the code (the code is only revealed when redeemed)
+    pub discount: CouponDiscount,
+    pub eligible_packages: Vec<u16>,             // Empty = every package
+    pub max_redemptions: u32,                    // 0 = unlimited
+    pub redemptions: u32,
+    pub per_wallet_limit: u32,                   // 0 = unlimited
+    pub expires_at: i64,                         // 0 = never
+    pub enabled: bool,
+    pub created_by: Pubkey,
+}
+
+impl Coupon {
+    pub const MAX_ELIGIBLE_PACKAGES: usize = 8;
+    // Space: 8 (discriminator) + 32 (code_hash) + 1 + 8 (discount) + 4 + (8 * 2) (eligible_packages)
+    //        + 4 (max_redemptions) + 4 (redemptions) + 4 (per_wallet_limit) + 8 (expires_at) + 1 (enabled) + 32 (created_by)
+    pub const SPACE: usize = 8 + 32 + 1 + 8 + 4 + (Self::MAX_ELIGIBLE_PACKAGES * 2) + 4 + 4 + 4 + 8 + 1 + 32;
+
+    // Discount (micro-USDC) this coupon gives on `price_usdc`, if it can be redeemed now
+    pub fn discount_for(&self, package_id: u16, price_usdc: u64, now: i64, wallet_redemptions: u32) -> Result<u64> {
+        require!(self.enabled, SportsError::CouponNotRedeemable);
+        require!(self.expires_at == 0 || now < self.expires_at, SportsError::CouponExpired);
+        require!(
+            self.eligible_packages.is_empty() || self.eligible_packages.contains(&package_id),
+            SportsError::CouponNotRedeemable
+        );
+        require!(
+            self.max_redemptions == 0 || self.redemptions < self.max_redemptions,
+            SportsError::CouponRedemptionLimit
+        );
+        require!(
+            self.per_wallet_limit == 0 || wallet_redemptions < self.per_wallet_limit,
+            SportsError::CouponRedemptionLimit
+        );
+
+        let discount = match self.discount {
+            CouponDiscount::Bps(bps) => (price_usdc as u128 * bps as u128 / 10_000) as u64,
+            CouponDiscount::Fixed(amount) => amount,
+        };
+        // A coupon never makes a pack free
+        require!(discount < price_usdc, SportsError::InvalidPrice);
+        Ok(discount)
+    }
+}
+
+// Canjes de un cupón por wallet
+#[account]
+pub struct CouponRedemption {
+    pub coupon: Pubkey,                          // 32 bytes
+    pub buyer: Pubkey,                           // 32 bytes
+    pub redemptions: u32,                        // 4 bytes
+}
+
+impl CouponRedemption {
+    pub const SPACE: usize = 8 + 32 + 32 + 4; // discriminator + coupon + buyer + redemptions
+}
+
+// Recibo de compra de un equipo (buy_team, buy_teams, buy_team_with_sol, reveal_pack y drafts):
+// lo que se cobró y en qué reporte, para refund_team / refund_teams
+#[account]
+#[derive(Default)]
+pub struct PurchaseReceipt {
+    pub team_id: u64,
+    pub buyer: Pubkey,                           // Payer, refunded on refund_team
+    pub recipient: Pubkey,                       // NFT holder (== buyer unless gifted)
+    pub package_id: u16,
+    pub price_paid_usdc: u64,                    // After coupons
+    pub payment_mint: Pubkey,                    // NATIVE_SOL_MINT for buy_team_with_sol
+    pub amount_paid: u64,                        // In payment_mint base units (lamports for SOL)
+    pub report_id: u64,
+    pub purchased_at: i64,
+    pub referrer: Pubkey,                        // Pubkey::default() = no commission
+    pub referral_commission: u64,                // Clawed back from the referrer on refund
+    pub coupon: Pubkey,                          // Pubkey::default() = no coupon
+    pub coupon_discount_usdc: u64,
+    pub presale_root: [u8; 32],                  // Allowlist root of the presale counter, [0; 32] = public sale
+    pub pack_number: u32,                        // pack_history.total_packs after this purchase, 0 = not recorded (drafts)
+    pub packs_without_gold_before: u32,          // Pity counter before this purchase
+    pub gold_guaranteed: bool,
+    pub rent_payer: Pubkey,                      // fee_payer of buy_team (a sponsor, or the buyer); gets the rent back on refund
+}
+
+impl PurchaseReceipt {
+    // Space: 8 (discriminator) + 8 (team_id) + 32 (buyer) + 32 (recipient) + 2 (package_id) + 8 (price_paid_usdc)
+    //        + 32 (payment_mint) + 8 (amount_paid) + 8 (report_id) + 8 (purchased_at) + 32 (referrer) + 8 (referral_commission)
+    //        + 32 (coupon) + 8 (coupon_discount_usdc) + 32 (presale_root) + 4 (pack_number)
+    //        + 4 (packs_without_gold_before) + 1 (gold_guaranteed) + 32 (rent_payer)
+    pub const SPACE: usize = 8 + 8 + 32 + 32 + 2 + 8 + 32 + 8 + 8 + 8 + 32 + 8 + 32 + 8 + 32 + 4 + 4 + 1 + 32;
+}
+
+// Referidor de un comprador, fijado en su primera compra referida
+#[account]
+pub struct Referral {
+    pub buyer: Pubkey,                           // 32 bytes
+    pub referrer: Pubkey,                        // 32 bytes
+    pub bound_at: i64,                           // 8 bytes
+}
+
+impl Referral {
+    pub const SPACE: usize = 8 + 32 + 32 + 8; // discriminator + buyer + referrer + bound_at
+}
+
+// Cuenta del referidor: comisiones acumuladas y reclamadas (micro-USDC)
+#[account]
+pub struct ReferrerAccount {
+    pub referrer: Pubkey,                        // 32 bytes
+    pub accrued: u64,                            // 8 bytes - total earned
+    pub claimed: u64,                            // 8 bytes - total withdrawn
+    pub referred_buyers: u32,                    // 4 bytes
+}
+
+impl ReferrerAccount {
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 4; // discriminator + referrer + accrued + claimed + referred_buyers
+
+    pub fn claimable(&self) -> u64 {
+        self.accrued.saturating_sub(self.claimed)
+    }
+
+    // Clawback de una comisión reembolsada: sale de lo pendiente y, si ya se reclamó, de futuras
+    // comisiones. Devuelve lo que dejó de estar pendiente
+    pub fn claw_back(&mut self, commission: u64) -> u64 {
+        let claimable_before = self.claimable();
+        self.accrued = self.accrued.saturating_sub(commission);
+        claimable_before - self.claimable()
+    }
+}
+
+// Revenue of the open report in each AcceptedMint. All of them must be passed, once each
+fn accepted_mint_revenues<'info>(accepted_mint_infos: &'info [AccountInfo<'info>], game_state: &Account<'info, GameState>) -> Result<Vec<MintRevenue>> {
+    require!(
+        accepted_mint_infos.len() == game_state.accepted_mint_count as usize,
+        SportsError::InvalidAccountsProvided
+    );
+    let mut mint_revenues: Vec<MintRevenue> = Vec::with_capacity(accepted_mint_infos.len());
+    for accepted_mint_info in accepted_mint_infos.iter() {
+        let accepted_mint: Account<'info, AcceptedMint> = Account::try_from(accepted_mint_info)?;
+        require!(
+            accepted_mint_info.key() == Pubkey::find_program_address(
+                &[b"accepted_mint", accepted_mint.mint.as_ref(), game_state.key().as_ref()],
+                &crate::ID,
+            ).0
+                && !mint_revenues.iter().any(|r| r.mint == accepted_mint.mint),
+            SportsError::InvalidAccountsProvided
+        );
+        mint_revenues.push(MintRevenue {
+            mint: accepted_mint.mint,
+            revenue: accepted_mint.revenue_for(game_state.current_report_id),
+        });
+    }
+    Ok(mint_revenues)
+}
+
+// Stablecoin aceptada como pago además de game_state.mint_usdc, con su propia bóveda y acumulados
+#[account]
+pub struct AcceptedMint {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub vault: Pubkey,                           // ATA of usdc_authority for this mint
+    pub enabled: bool,
+    pub report_id: u64,                          // Report report_revenue belongs to
+    pub report_revenue: u64,                     // Revenue in this mint for report_id
+    pub total_received: u64,
+    pub total_withdrawn: u64,
+    pub pending_withdrawal: Option<WithdrawalRequest>, // Unused since WithdrawalProposal (kept for the layout)
+}
+
+impl AcceptedMint {
+    // Space: 8 (discriminator) + 32 (mint) + 1 (decimals) + 32 (vault) + 1 (enabled) + 8 (report_id)
+    //        + 8 (report_revenue) + 8 (total_received) + 8 (total_withdrawn) + 1 (option) + WithdrawalRequest::SIZE
+    pub const SPACE: usize = 8 + 32 + 1 + 32 + 1 + 8 + 8 + 8 + 8 + 1 + WithdrawalRequest::SIZE;
+
+    // Balance received in this mint and not yet withdrawn
+    pub fn available(&self) -> u64 {
+        self.total_received.saturating_sub(self.total_withdrawn)
+    }
+
+    // Revenue accumulated for `report_id` (0 if no sale since it opened)
+    pub fn revenue_for(&self, report_id: u64) -> u64 {
+        if self.report_id == report_id { self.report_revenue } else { 0 }
+    }
+
+    // Undo a sale refunded from this mint's vault
+    pub fn record_refund(&mut self, report_id: u64, amount: u64) {
+        if self.report_id == report_id {
+            self.report_revenue = self.report_revenue.saturating_sub(amount);
+        }
+        self.total_received = self.total_received.saturating_sub(amount);
+    }
+
+    // Add a sale, starting a fresh accumulator when the open report changed
+    pub fn record_revenue(&mut self, report_id: u64, amount: u64) -> Result<()> {
+        if self.report_id != report_id {
+            self.report_id = report_id;
+            self.report_revenue = 0;
+        }
+        self.report_revenue = self.report_revenue.checked_add(amount).ok_or(SportsError::TokenOverflow)?;
+        self.total_received = self.total_received.checked_add(amount).ok_or(SportsError::TokenOverflow)?;
+        Ok(())
+    }
+}
+
+// Sponsor allowlisted para compras sin SOL: paga rent y fees de buy_team (fee_payer)
+#[account]
+pub struct Sponsor {
+    pub sponsor: Pubkey,                         // 32 bytes
+    pub enabled: bool,                           // 1 byte
+    pub rent_recovery_fee_usdc: u64,             // 8 bytes - cobrado al comprador por compra (0 = gratis)
+    pub purchases_sponsored: u64,                // 8 bytes
+    pub fees_collected_usdc: u64,                // 8 bytes
+}
+
+impl Sponsor {
+    pub const SPACE: usize = 8 + 32 + 1 + 8 + 8 + 8; // discriminator + sponsor + enabled + fee + counters
+}
+
+// Supply schedule PDA per player - pending tranches released by the crank
+#[account]
+pub struct SupplySchedule {
+    pub player_id: u16,                         // 2 bytes
+    pub tranches: Vec<SupplyTranche>,           // 4 + (MAX_TRANCHES * SupplyTranche::SIZE)
+    pub total_released: u64,                    // 8 bytes - tokens released so far
+    pub initialized: bool,                      // 1 byte - set by the first scheduled tranche
+}
+
+impl SupplySchedule {
+    pub const MAX_TRANCHES: usize = 24;
+    // Space: 8 (discriminator) + 2 (player_id) + 4 (vec len) + (24 * 12) + 8 (total_released) + 1 (initialized)
+    pub const SPACE: usize = 8 + 2 + 4 + (Self::MAX_TRANCHES * SupplyTranche::SIZE) + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct SupplyTranche {
+    pub unlock_timestamp: i64,
+    pub amount: u32,
+}
+
+impl SupplyTranche {
+    pub const SIZE: usize = 8 + 4; // unlock_timestamp + amount
+}
+
+// Team account representing a purchased team
+#[account]
+pub struct Team {
+    pub first_buyer: Pubkey,                    // 32 bytes
+    pub player_ids: Vec<u16>,             // 4 + (MAX_PLAYERS * 2) bytes
+    pub category: TeamPackage,            // 1 byte (enum) + 2 bytes for Custom package ids
+    pub created_at: i64,                  // 8 bytes
+    pub transition_timestamp: i64,        // 8 bytes - timestamp cuando cambió de estado
+    pub nft_mint: Pubkey,                 // 32 bytes
+    pub state: TeamState,                 // 1 byte (enum)
+    pub team_id: u64,                     // 8 bytes - unique identifier
+    pub terms_accepted: bool,             // 1 byte - si acepta términos y condiciones
+}
+
+impl Team {
+    pub const MAX_PLAYERS: usize = PackageConfig::MAX_SLOTS;
+    // Space: 8 (discriminator) + 32 (owner) + 4 + 20 (player_ids vec) + 3 (category) + 8 (created_at) + 8 (transition_timestamp) + 32 (nft_mint) + 1 (state) + 8 (team_id) + 1 (terms_accepted)
+    pub const SPACE: usize = 8 + 32 + 4 + (Self::MAX_PLAYERS * 2) + 3 + 8 + 8 + 32 + 1 + 8 + 1;
+}
+
+// Data-driven package definition - PDA per package id
+#[account]
+pub struct PackageConfig {
+    pub package_id: u16,
+    pub name: String,                                // Display name (max 32 bytes)
+    pub slot_count: u8,                              // Players per team
+    pub category_minimums: Vec<CategoryMinimum>,     // Guaranteed slots, filled after the random ones
+    pub price_usdc: u64,
+    pub is_active: bool,
+    // Edición limitada y ventana de venta
+    pub max_supply: u32,                             // Max packs sold (0 = unlimited)
+    pub sold: u32,                                   // Packs sold (commit/draft count when paid)
+    pub sale_start: i64,                             // Unix timestamp (0 = on sale immediately)
+    pub sale_end: i64,                               // Unix timestamp (0 = no end)
+    pub presale_price_usdc: u64,                     // Price during the presale (0 = price_usdc)
+    pub pricing_curve: Option<PricingCurve>,         // Inventory-driven price (None = fixed price)
+    pub scheduled_price: Option<ScheduledPrice>,     // Queued pricing change (applied by the purchase paths once due)
+}
+
+impl PackageConfig {
+    pub const LEGACY_A: u16 = 0;
+    pub const LEGACY_B: u16 = 1;
+    pub const LEGACY_C: u16 = 2;
+    pub const MAX_SLOTS: usize = 10;
+    pub const MAX_MINIMUMS: usize = 3;
+    pub const MAX_NAME_LEN: usize = 32;
+    // Space: 8 (discriminator) + 2 (package_id) + 4 + 32 (name) + 1 (slot_count) + 4 + (3 * 2) (minimums) + 8 (price_usdc) + 1 (is_active)
+    //        + 4 (max_supply) + 4 (sold) + 8 (sale_start) + 8 (sale_end) + 8 (presale_price_usdc) + 1 + PricingCurve::SIZE (pricing_curve)
+    //        + 1 + ScheduledPrice::SIZE (scheduled_price)
+    pub const SPACE: usize = 8 + 2 + 4 + Self::MAX_NAME_LEN + 1 + 4 + (Self::MAX_MINIMUMS * CategoryMinimum::SIZE) + 8 + 1 + 4 + 4 + 8 + 8 + 8 + 1 + PricingCurve::SIZE + 1 + ScheduledPrice::SIZE;
+
+    // Slots filled by the general weighted draw
+    pub fn general_slots(&self) -> usize {
+        self.slot_count as usize - self.reserved_slots()
+    }
+
+    // Slots reserved for per-category minimums
+    pub fn reserved_slots(&self) -> usize {
+        self.category_minimums.iter().map(|m| m.count as usize).sum()
+    }
+
+    // Price to charge in the given sale phase, after the pricing curve (if any)
+    pub fn price_for(&self, phase: SalePhase, players: &[PlayerSummary], now: i64) -> u64 {
+        let pricing = self.pricing_at(now);
+        let base_price = match phase {
+            SalePhase::Presale if pricing.presale_price_usdc > 0 => pricing.presale_price_usdc,
+            _ => pricing.price_usdc,
+        };
+        match pricing.pricing_curve.as_ref() {
+            Some(curve) => curve.price(base_price, premium_stock(players)),
+            None => base_price,
+        }
+    }
+
+    // Pricing in force at `now`: the scheduled one once due (even before it is persisted)
+    pub fn pricing_at(&self, now: i64) -> ScheduledPrice {
+        self.due_price(now).unwrap_or(ScheduledPrice {
+            price_usdc: self.price_usdc,
+            presale_price_usdc: self.presale_price_usdc,
+            pricing_curve: self.pricing_curve,
+            effective_at: 0,
+        })
+    }
+
+    // Scheduled price change whose effective_at has been reached
+    pub fn due_price(&self, now: i64) -> Option<ScheduledPrice> {
+        self.scheduled_price.filter(|scheduled| scheduled.effective_at <= now)
+    }
+
+    // price_usdc, or the scheduled price once due (even before it is persisted)
+    pub fn effective_price_usdc(&self, now: i64) -> u64 {
+        self.pricing_at(now).price_usdc
+    }
+
+    // Packs left under max_supply (None = unlimited)
+    pub fn remaining_supply(&self) -> Option<u32> {
+        (self.max_supply > 0).then(|| self.max_supply.saturating_sub(self.sold))
+    }
+
+    pub fn sale_status(&self, now: i64) -> PackageSaleStatus {
+        if !self.is_active {
+            PackageSaleStatus::Inactive
+        } else if self.sale_start > 0 && now < self.sale_start {
+            PackageSaleStatus::NotStarted
+        } else if self.sale_end > 0 && now >= self.sale_end {
+            PackageSaleStatus::Ended
+        } else if self.remaining_supply() == Some(0) {
+            PackageSaleStatus::SoldOut
+        } else {
+            PackageSaleStatus::Active
+        }
+    }
+}
+
+// Cambio de precio programado de un paquete
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ScheduledPrice {
+    pub price_usdc: u64,
+    pub presale_price_usdc: u64,                 // 0 = price_usdc
+    pub pricing_curve: Option<PricingCurve>,
+    pub effective_at: i64,
+}
+
+impl ScheduledPrice {
+    pub const SIZE: usize = 8 + 8 + 1 + PricingCurve::SIZE + 8;
+}
+
+// Historial de precios aplicados (ring buffer) para que el frontend pueda graficarlos
+#[account]
+pub struct PriceHistory {
+    pub next_index: u16,                         // Slot overwritten by the next entry once full
+    pub entries: Vec<PriceHistoryEntry>,         // At most CAPACITY, oldest at next_index when full
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PriceHistoryEntry {
+    pub package_id: u16,
+    pub old_price_usdc: u64,
+    pub new_price_usdc: u64,
+    pub effective_at: i64,
+}
+
+impl PriceHistoryEntry {
+    pub const SIZE: usize = 2 + 8 + 8 + 8;
+}
+
+impl PriceHistory {
+    pub const CAPACITY: usize = 64;
+    pub const SPACE: usize = 8 + 2 + 4 + Self::CAPACITY * PriceHistoryEntry::SIZE; // discriminator + next_index + entries
+
+    pub fn record(&mut self, entry: PriceHistoryEntry) {
+        if self.entries.len() < Self::CAPACITY {
+            self.entries.push(entry);
+        } else {
+            self.entries[self.next_index as usize] = entry;
+        }
+        self.next_index = ((self.next_index as usize + 1) % Self::CAPACITY) as u16;
+    }
+
+    // Entries from oldest to newest
+    pub fn chronological(&self) -> Vec<PriceHistoryEntry> {
+        if self.entries.len() < Self::CAPACITY {
+            return self.entries.clone();
+        }
+        let (newest, oldest) = self.entries.split_at(self.next_index as usize);
+        oldest.iter().chain(newest).copied().collect()
+    }
+}
+
+// Precio dinámico: recargo de hasta max_markup_bps a medida que baja el stock Silver/Gold
+// por debajo de reference_premium_stock, acotado a [min_price_usdc, max_price_usdc]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PricingCurve {
+    pub reference_premium_stock: u32,  // Silver/Gold tokens at which the markup starts
+    pub max_markup_bps: u16,           // Markup when the premium stock reaches 0
+    pub min_price_usdc: u64,
+    pub max_price_usdc: u64,
+}
+
+impl PricingCurve {
+    pub const SIZE: usize = 4 + 2 + 8 + 8;
+
+    pub fn price(&self, base_price: u64, premium_stock: u64) -> u64 {
+        let reference = self.reference_premium_stock as u128;
+        let scarcity_bps = reference.saturating_sub(premium_stock as u128) * 10_000 / reference.max(1);
+        let markup_bps = self.max_markup_bps as u128 * scarcity_bps / 10_000;
+        let price = base_price as u128 * (10_000 + markup_bps) / 10_000;
+        (price.min(u64::MAX as u128) as u64).clamp(self.min_price_usdc, self.max_price_usdc)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PackageSaleStatus {
+    Active,
+    Inactive,      // is_active = false
+    NotStarted,    // Before sale_start
+    Ended,         // sale_end reached
+    SoldOut,       // sold == max_supply
+}
+
+// Returned by get_package_sale_status
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct PackageSaleInfo {
+    pub package_id: u16,
+    pub status: PackageSaleStatus,
+    pub price_usdc: u64,           // Price in the current sale phase
+    pub sold: u32,
+    pub max_supply: u32,
+    pub remaining_supply: Option<u32>,
+    pub sale_start: i64,
+    pub sale_end: i64,
+    pub now: i64,
+}
+
+// At least `count` slots drawn from the categories in `category_mask`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CategoryMinimum {
+    pub category_mask: u8,   // Bit per PlayerCategory (Bronze = 1, Silver = 2, Gold = 4)
+    pub count: u8,
+}
+
+impl CategoryMinimum {
+    pub const SIZE: usize = 1 + 1;
+    pub const PREMIUM_MASK: u8 = (1 << PlayerCategory::Silver as u8) | (1 << PlayerCategory::Gold as u8);
+
+    pub fn matches(&self, category: PlayerCategory) -> bool {
+        self.category_mask & (1 << category as u8) != 0
+    }
+}
+
+// Report tracking structure
+#[account]
+pub struct Report {
+    pub report_id: u64,
+    pub epoch: u64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+    pub revenue: u64,
+    pub teams_sold: u32,
+    pub tokens_sold: u32,
+    pub staker_pool: u64,
+    pub stakers_count: u32,
+    pub reward_per_staker: u64,
+    pub sol_revenue_lamports: u64,     // Ventas cobradas en SOL (revenue es solo USDC)
+    pub coupon_redemptions: u32,
+    pub coupon_discount_usdc: u64,     // Descuento total concedido con cupones
+    pub mint_revenues: Vec<MintRevenue>, // Ventas en cada AcceptedMint (en su propia moneda)
+    pub staker_pool_reserved: u64,     // Parte de staker_pool apartada de la tesorería al cerrar (acotada al disponible)
+    pub staker_pool_paid: u64,         // Pagado a stakers con pay_staker_rewards
+    pub staker_pool_released: bool,    // Lo no pagado volvió al saldo disponible (release_staker_pool)
+}
+
+impl Report {
+    // Máximo de AcceptedMints registrables (una entrada de mint_revenues cada una)
+    pub const MAX_MINTS: usize = 4;
+    // Space: 8 (discriminator) + 8 (report_id) + 8 (epoch) + 8 (start) + 8 (end) + 8 (revenue) + 4 (teams_sold)
+    //        + 4 (tokens_sold) + 8 (staker_pool) + 4 (stakers_count) + 8 (reward_per_staker)
+    //        + 8 (sol_revenue_lamports) + 4 (coupon_redemptions) + 8 (coupon_discount_usdc)
+    //        + 4 + (MAX_MINTS * MintRevenue::SIZE) (mint_revenues)
+    //        + 8 (staker_pool_reserved) + 8 (staker_pool_paid) + 1 (staker_pool_released)
+    pub const SPACE: usize = 8 + 8 + 8 + 8 + 8 + 8 + 4 + 4 + 8 + 4 + 8 + 8 + 4 + 8
+        + 4 + (Self::MAX_MINTS * MintRevenue::SIZE) + 8 + 8 + 1;
+
+    // Reserved staker rewards not yet paid or released
+    pub fn staker_pool_outstanding(&self) -> u64 {
+        if self.staker_pool_released {
+            0
+        } else {
+            self.staker_pool_reserved.saturating_sub(self.staker_pool_paid)
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct MintRevenue {
+    pub mint: Pubkey,
+    pub revenue: u64,                  // In the mint's own units
+}
+
+impl MintRevenue {
+    pub const SIZE: usize = 32 + 8;
+}
+
+// Layouts del deploy anterior, solo para los migrate_*. Team no cambió de layout
+// (TeamPackage::Custom es una variante nueva al final) y PackageConfig no existía.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyGameState {
+    pub owner: Pubkey,
+    pub staff: Vec<Pubkey>,
+    pub players: Vec<PlayerSummary>,
+    pub next_player_id: u16,
+    pub mint_usdc: Pubkey,
+    pub team_price_a: u64,
+    pub team_price_b: u64,
+    pub team_price_c: u64,
+    pub next_team_id: u64,
+    pub next_reward_id: u64,
+    pub nft_update_authority: Pubkey,
+    pub nft_image_url: String,
+    pub current_report_id: u64,
+    pub current_report_start: i64,
+    pub is_report_open: bool,
+    pub current_report_revenue: u64,
+    pub current_report_teams: u32,
+    pub current_report_tokens: u32,
+    pub is_paused: bool,
+    pub pending_withdrawal: Option<WithdrawalRequest>,
+    pub time_lock: i64,
+}
+
+impl LegacyGameState {
+    // Campos nuevos con los mismos valores por defecto que initialize
+    pub fn into_current(self) -> GameState {
+        GameState {
+            owner: self.owner,
+            staff: self.staff,
+            players: self.players,
+            next_player_id: self.next_player_id,
+            mint_usdc: self.mint_usdc,
+            team_price_a: self.team_price_a,
+            team_price_b: self.team_price_b,
+            team_price_c: self.team_price_c,
+            next_team_id: self.next_team_id,
+            next_reward_id: self.next_reward_id,
+            nft_update_authority: self.nft_update_authority,
+            nft_image_url: self.nft_image_url,
+            current_report_id: self.current_report_id,
+            current_report_start: self.current_report_start,
+            is_report_open: self.is_report_open,
+            current_report_revenue: self.current_report_revenue,
+            current_report_teams: self.current_report_teams,
+            current_report_tokens: self.current_report_tokens,
+            is_paused: self.is_paused,
+            pending_withdrawal: None, // Reemplazado por WithdrawalProposal
+            time_lock: self.time_lock,
+            pity_threshold: 0,
+            reveal_window_slots: 150,
+            expired_commit_policy: ExpiredCommitPolicy::AutoReveal,
+            entropy_source: EntropySourceKind::Chainlink,
+            entropy_oracle: Pubkey::default(),
+            presale_merkle_root: [0; 32],
+            presale_end: 0,
+            presale_wallet_cap: 0,
+            current_report_sol_revenue: 0,
+            current_report_coupon_redemptions: 0,
+            current_report_coupon_discount: 0,
+            referral_commission_bps: 0,
+            referral_commissions_owed: 0,
+            refund_window_secs: 0,
+            sol_usd_feed: CHAINLINK_SOL_USD_FEED_DEVNET,
+            oracle_program: CHAINLINK_PROGRAM_ID,
+            max_price_staleness_secs: DEFAULT_MAX_PRICE_STALENESS_SECS,
+            withdrawal_threshold: 2,
+            next_withdrawal_proposal_id: 1,
+            treasury_received: 0,
+            treasury_withdrawn: 0,
+            treasury_reserved: 0,
+            accepted_mint_count: 0,
+            treasury_ledger_seeded: false,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyPlayer {
+    pub id: u16,
+    pub provider_id: u16,
+    pub category: PlayerCategory,
+    pub total_tokens: u32,
+    pub tokens_sold: u32,
+    pub metadata_uri: Option<String>,
+    pub name: String,
+    pub discipline: String,
+    pub country: String,
+}
+
+impl LegacyPlayer {
+    pub fn into_current(self) -> Player {
+        Player {
+            id: self.id,
+            provider_id: self.provider_id,
+            category: self.category,
+            total_tokens: self.total_tokens,
+            tokens_sold: self.tokens_sold,
+            metadata_uri: self.metadata_uri,
+            name: self.name,
+            discipline: self.discipline,
+            country: self.country,
+            version: 0,
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyReport {
+    pub report_id: u64,
+    pub epoch: u64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+    pub revenue: u64,
+    pub teams_sold: u32,
+    pub tokens_sold: u32,
+    pub staker_pool: u64,
+    pub stakers_count: u32,
+    pub reward_per_staker: u64,
+}
+
+impl LegacyReport {
+    pub fn into_current(self) -> Report {
+        Report {
+            report_id: self.report_id,
+            epoch: self.epoch,
+            start_timestamp: self.start_timestamp,
+            end_timestamp: self.end_timestamp,
+            revenue: self.revenue,
+            teams_sold: self.teams_sold,
+            tokens_sold: self.tokens_sold,
+            staker_pool: self.staker_pool,
+            stakers_count: self.stakers_count,
+            reward_per_staker: self.reward_per_staker,
+            sol_revenue_lamports: 0,
+            coupon_redemptions: 0,
+            coupon_discount_usdc: 0,
+            mint_revenues: Vec::new(),
+            // El deploy anterior no apartaba el pool: no hay nada reservado que pagar o liberar
+            staker_pool_reserved: 0,
+            staker_pool_paid: 0,
+            staker_pool_released: true,
+        }
+    }
+}
+
+// User stake state tracking - PDA per user for efficient stake queries
+// Only stores team IDs, detailed state is in individual TeamStakeState PDAs
+#[account]
+pub struct UserStakeState {
+    pub user: Pubkey,                           // 32 bytes - owner of this stake state
+    pub staked_team_ids: Vec<u64>,              // 4 + (n * 8) bytes - only team IDs
+}
+
+impl UserStakeState {
+    // Space: 8 (discriminator) + 32 (user) + 4 (vec len) + (1000 * 8)
+    // Assuming max 1000 staked teams per user (8 bytes per team ID)
+    pub const SPACE: usize = 8 + 32 + 4 + (1000 * 8);
+}
+
+// Individual team stake state - PDA per user+team for detailed tracking
+#[account]
+pub struct TeamStakeState {
+    pub user: Pubkey,                          // 32 bytes - owner of this stake
+    pub team_id: u64,                          // 8 bytes - team identifier
+    pub state: TeamState,                      // 1 byte - current team state
+    pub transition_timestamp: i64,             // 8 bytes - when state changed
+    pub rewards_earned: u64,                   // 8 bytes - accumulated rewards
+}
+
+impl TeamStakeState {
+    pub const SPACE: usize = 8 + 32 + 8 + 1 + 8 + 8; // discriminator + user + team_id + state + timestamp + rewards
+}
+
+// Minimal structure for the vec in GameState
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PlayerSummary {
+    pub id: u16,
+    pub category: PlayerCategory,
+    pub available_tokens: u32,
+    
+}
+
+impl PlayerSummary {
+    pub const SIZE: usize = 2 + 1 + 4; // id (u16) + category + available_tokens
+}
+
+// Enum for player categories
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Copy)]
+pub enum PlayerCategory {
+    Bronze = 0,
+    Silver = 1,
+    Gold = 2,
+}
+
+// Enum for team packages (A/B/C kept for teams bought before PackageConfig)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum TeamPackage {
+    A, // 5 random players
+    B, // 4 random + 1 Silver/Gold
+    C, // 3 random + 2 Silver/Gold
+    Custom(u16), // Any other PackageConfig id
+}
+
+impl TeamPackage {
+    pub fn from_package_id(package_id: u16) -> Self {
+        match package_id {
+            PackageConfig::LEGACY_A => TeamPackage::A,
+            PackageConfig::LEGACY_B => TeamPackage::B,
+            PackageConfig::LEGACY_C => TeamPackage::C,
+            id => TeamPackage::Custom(id),
+        }
+    }
+
+    pub fn package_id(&self) -> u16 {
+        match self {
+            TeamPackage::A => PackageConfig::LEGACY_A,
+            TeamPackage::B => PackageConfig::LEGACY_B,
+            TeamPackage::C => PackageConfig::LEGACY_C,
+            TeamPackage::Custom(id) => *id,
+        }
+    }
+}
+
+// Enum for team states
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum TeamState {
+    Free,        // Free/Available (was Libre)
+    WarmingUp,   // Warming up (was Calentando)
+    OnField,     // On field/Playing (was EnCancha)
+    ToWithdraw,  // To be retired/withdrawn (was ARetirar)
+}
+
+// Struct to represent selected players for a team
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct TeamPurchase {
+    pub buyer: Pubkey,
+    pub package: TeamPackage,
+    pub player_ids: Vec<u16>,
+    pub purchase_timestamp: i64,
+    pub purchase_slot: u64,
+    pub price_paid_usdc: u64,
+    pub team_id: u64,
+    pub gold_guaranteed: bool,
+}
+
+// Events
+#[event]
+pub struct TeamStartedWarmup {
+    pub team_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TeamEnteredField {
+    pub team_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TeamStartedWithdrawal {
+    pub team_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TeamWithdrawn {
+    pub team_id: u64,
+    pub timestamp: i64,
+}
+
+// Eventos
+#[event]
+pub struct TokenSold {
+    pub player_id: u16,               // ID del jugador vendido
+    pub team_id: u64,                 // ID del equipo donde se vendió
+    pub timestamp: i64,               // Cuándo se vendió
+    pub buyer: Pubkey,                // Quién compró el equipo
+    pub report_id: u64,               // ID del reporte al que pertenece esta venta
+}
+
+// Typed old/new pair for one changed Player field
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum PlayerFieldChange {
+    ProviderId { old: u16, new: u16 },
+    Category { old: PlayerCategory, new: PlayerCategory },
+    TotalTokens { old: u32, new: u32 },
+    MetadataUri { old: Option<String>, new: Option<String> },
+    Name { old: String, new: String },
+    Discipline { old: String, new: String },
+    Country { old: String, new: String },
+}
+
+#[event]
+pub struct PlayerUpdated {
+    pub player_id: u16,
+    pub version: u32,                 // Player version after the update
+    pub updated_by: Pubkey,
+    pub approved_by: Option<Pubkey>,  // Second approver for category changes
+    pub changes: Vec<PlayerFieldChange>,
+    pub timestamp: i64,
+}
+
+// Single invariant violation found by audit_players
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct PlayerAuditMismatch {
+    pub player_id: u16,
+    pub kind: AuditMismatchKind,
+    pub pda_value: u32,               // Value read from the Player PDA
+    pub summary_value: u32,           // Value read from GameState.players (or the bound it violates)
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum AuditMismatchKind {
+    TokensSoldExceedsTotal,  // tokens_sold > total_tokens on the PDA
+    AvailableTokensMismatch, // summary.available_tokens != total_tokens - tokens_sold
+    CategoryMismatch,        // summary.category != PDA category
+    MissingSummary,          // PDA has no entry in GameState.players
+    IdMismatch,              // PDA address is not derived from its stored id
+}
+
+#[event]
+pub struct PlayerAuditReport {
+    pub auditor: Pubkey,
+    pub players_checked: u16,
+    pub mismatches: Vec<PlayerAuditMismatch>,
+    pub repaired: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SupplyReleased {
+    pub player_id: u16,
+    pub amount: u32,                  // Tokens added to circulation
+    pub tranches_released: u8,
+    pub new_total_tokens: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TeamPurchased {
+    pub team_id: u64,
+    pub buyer: Pubkey,
+    pub package_id: u16,
+    pub player_ids: Vec<u16>,
+    pub price_paid_usdc: u64,
+    pub payment_mint: Pubkey,         // Stablecoin used to pay
+    pub amount_paid: u64,             // In payment_mint base units
+    pub report_id: u64,
+    pub gold_guaranteed: bool,        // Pity guarantee forced a Gold slot
+    pub recipient: Pubkey,            // NFT holder (== buyer unless gifted)
+    pub gift_message_hash: Option<[u8; 32]>,
+    pub nft_delegate: Option<Pubkey>, // game_state when the holder delegated the NFT for staff refunds
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TeamPurchasedWithSol {
+    pub team_id: u64,
+    pub buyer: Pubkey,
+    pub package_id: u16,
+    pub player_ids: Vec<u16>,
+    pub price_usdc: u64,              // Package price the lamports were derived from
+    pub sol_price_usdc: u64,          // SOL/USD used (micro-USDC per SOL)
+    pub price_paid_lamports: u64,
+    pub report_id: u64,
+    pub gold_guaranteed: bool,
+    pub nft_delegate: Option<Pubkey>, // game_state (staff refunds burn the NFT as delegate)
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TeamsPurchased {
+    pub buyer: Pubkey,
+    pub package_id: u16,
+    pub team_ids: Vec<u64>,
+    pub player_ids: Vec<Vec<u16>>,    // Per team, same order as team_ids
+    pub prices_paid_usdc: Vec<u64>,   // Per team (the pricing curve moves with each pack)
+    pub total_paid_usdc: u64,
+    pub report_id: u64,
+    pub gold_guarantees: u8,          // Teams with a pity-forced Gold slot
+    pub nft_delegate: Option<Pubkey>, // game_state, delegate of every NFT of the batch (staff refunds)
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DraftStarted {
+    pub buyer: Pubkey,
+    pub package_id: u16,
+    pub offered_player_ids: Vec<u16>,
+    pub price_paid_usdc: u64,
+    pub deadline: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DraftFinished {
+    pub buyer: Pubkey,
+    pub team_id: u64,
+    pub picked_player_ids: Vec<u16>,
+    pub released_player_ids: Vec<u16>, // Returned to available_tokens
+    pub auto_completed: bool,          // Picked by auto_draft_picks after the deadline
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DraftRefunded {
+    pub buyer: Pubkey,
+    pub package_id: u16,
+    pub released_player_ids: Vec<u16>, // Returned to available_tokens
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PackCommitted {
+    pub buyer: Pubkey,
+    pub package_id: u16,
+    pub price_paid_usdc: u64,
+    pub commitment: [u8; 32],
+    pub target_slot: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PackRevealed {
+    pub buyer: Pubkey,
+    pub team_id: u64,
+    pub target_slot: u64,
+    pub revealed_by: Pubkey,
+    pub auto_revealed: bool,          // Revealed after the window under AutoReveal
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PackCommitRefunded {
+    pub buyer: Pubkey,
+    pub package_id: u16,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TeamRefunded {
+    pub team_id: u64,
+    pub buyer: Pubkey,
+    pub refunded_by: Pubkey,
+    pub payment_mint: Pubkey,
+    pub amount: u64,                  // In payment_mint base units
+    pub report_rolled_back: bool,     // Sale belonged to the open report
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralBound {
+    pub buyer: Pubkey,
+    pub referrer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralCommissionAccrued {
+    pub referrer: Pubkey,
+    pub buyer: Pubkey,
+    pub team_id: u64,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReferralCommissionClaimed {
+    pub referrer: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CouponRedeemed {
+    pub code_hash: [u8; 32],
+    pub buyer: Pubkey,
+    pub package_id: u16,
+    pub discount_usdc: u64,
+    pub price_paid_usdc: u64,         // After the discount
+    pub report_id: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalProposed {
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub memo: String,
+    pub expires_at: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalApproved {
+    pub proposal_id: u64,
+    pub approver: Pubkey,
+    pub approvals: u8,                // Valid approvals so far
+    pub threshold: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalRejected {
+    pub proposal_id: u64,
+    pub rejected_by: Pubkey,
+    pub rejections: u8,
+    pub proposal_rejected: bool,      // The threshold can no longer be reached
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub proposal_id: u64,
+    pub cancelled_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakerRewardsPaid {
+    pub report_id: u64,
+    pub proposal_id: u64,             // WithdrawalProposal that approved the batch
+    pub stakers: u32,
+    pub reward_per_staker: u64,
+    pub total: u64,
+    pub paid_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StakerPoolReleased {
+    pub report_id: u64,
+    pub released: u64,                // Reserved but unpaid, back to the available balance
+    pub released_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryLedgerSeeded {
+    pub balance: u64,                 // Treasury token account balance when seeded
+    pub treasury_received: u64,
+    pub seeded_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalProposalClosed {
+    pub proposal_id: u64,
+    pub status: WithdrawalProposalStatus,        // Pending = closed after expiring
+    pub closed_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WithdrawalExecuted {
+    pub proposal_id: u64,
+    pub executed_by: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SponsorUpdated {
+    pub sponsor: Pubkey,
+    pub enabled: bool,
+    pub rent_recovery_fee_usdc: u64,
+    pub updated_by: Pubkey,
+}
+
+#[event]
+pub struct PurchaseSponsored {
+    pub sponsor: Pubkey,
+    pub buyer: Pubkey,
+    pub team_id: u64,
+    pub fee_usdc: u64,                // Rent-recovery fee in micro-USDC
+    pub payment_mint: Pubkey,
+    pub fee_amount: u64,              // In payment_mint base units
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AcceptedMintUpdated {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub vault: Pubkey,
+    pub enabled: bool,
+    pub updated_by: Pubkey,
+}
+
+#[event]
+pub struct OracleConfigured {
+    pub sol_usd_feed: Pubkey,
+    pub oracle_program: Pubkey,
+    pub max_price_staleness_secs: i64,
+    pub updated_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PresaleConfigured {
+    pub merkle_root: [u8; 32],
+    pub presale_end: i64,
+    pub wallet_cap: u32,
+    pub updated_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PriceChangeScheduled {
+    pub package_id: u16,
+    pub old_price_usdc: u64,
+    pub price_usdc: u64,
+    pub presale_price_usdc: u64,
+    pub pricing_curve: Option<PricingCurve>,
+    pub effective_at: i64,
+    pub scheduled_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PriceChangeCancelled {
+    pub package_id: u16,
+    pub price_usdc: u64,
+    pub effective_at: i64,
+    pub cancelled_by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PricesUpdated {
+    pub package_id: u16,
+    pub old_price_usdc: u64,
+    pub new_price_usdc: u64,
+    pub presale_price_usdc: u64,
+    pub pricing_curve: Option<PricingCurve>,
+    pub effective_at: i64,
+    pub applied_at: i64,
+}
+
+#[event]
+pub struct PackageSaleUpdated {
+    pub package_id: u16,
+    pub sale_start: i64,
+    pub sale_end: i64,
+    pub max_supply: u32,
+    pub sold: u32,
+    pub updated_by: Pubkey,
+    pub timestamp: i64,
+}
+
+// Custom errors
+#[error_code]
+pub enum SportsError {
+    #[msg("Insufficient tokens available")]
+    InsufficientTokens,
+    #[msg("Invalid player ID")]
+    InvalidPlayerId,
+    #[msg("Token overflow")]
+    TokenOverflow,
+    #[msg("Unauthorized access")]
+    UnauthorizedAccess,
+    #[msg("Staff limit exceeded")]
+    StaffLimitExceeded,
+    #[msg("Staff already exists")]
+    StaffAlreadyExists,
+    #[msg("Staff not found")]
+    StaffNotFound,
+    #[msg("Invalid token update")]
+    InvalidTokenUpdate,
+    #[msg("Insufficient players available")]
+    InsufficientPlayersAvailable,
+    #[msg("Insufficient premium players")]
+    InsufficientPremiumPlayers,
+    #[msg("Random selection failed")]
+    RandomSelectionFailed,
+    #[msg("Invalid price")]
+    InvalidPrice,
+    #[msg("Invalid state transition")]
+    InvalidStateTransition,
+    #[msg("Invalid amount")]
+    InvalidAmount,
+    #[msg("Reward already distributed")]
+    RewardAlreadyDistributed,
+    #[msg("No eligible teams")]
+    NoEligibleTeams,
+    #[msg("Invalid accounts provided")]
+    InvalidAccountsProvided,
+    #[msg("Invalid team ID")]
+    InvalidTeamId,
+    #[msg("Team not in correct state for this operation")]
+    InvalidTeamState,
+    #[msg("Waiting period not complete")]
+    WaitingPeriodNotComplete,
+    #[msg("NFT transfer failed")]
+    NftTransferFailed,
+    #[msg("No hay un reporte abierto para acumular ventas")]
+    NoOpenReport,
+    #[msg("Invalid report ID")]
+    InvalidReportId,
+    #[msg("No rewards available to claim")]
+    NoRewardsAvailable,
+    #[msg("Report expired")]
+    ReportExpired,
+    #[msg("Invalid game state")]
+    InvalidGameState,
+    #[msg("Insufficient funds")]
+    InsufficientFunds,
+    #[msg("Contract is paused")]
+    ContractPaused,
+    #[msg("Terms and conditions must be accepted")]
+    TermsNotAccepted,
+    #[msg("Invalid package selected")]
+    InvalidPackage,
+    #[msg("Team ID overflow protection")]
+    TeamIdOverflow,
+    #[msg("Invalid USDC mint address")]
+    InvalidUsdcMint,
+    #[msg("Invalid token account")]
+    InvalidTokenAccount,
+    #[msg("Withdrawal request already exists")]
+    WithdrawalRequestExists,
+    #[msg("No withdrawal request pending")]
+    NoWithdrawalRequestPending,
+    #[msg("Cannot approve own withdrawal request")]
+    CannotApproveSelfWithdrawal,
+    #[msg("Withdrawal amount mismatch")]
+    WithdrawalAmountMismatch,
+    #[msg("User does not own the NFT")]
+    UserDoesNotOwnNft,
+    #[msg("Invalid NFT mint")]
+    InvalidNftMint,
+    #[msg("Invalid price feed")]
+    InvalidPriceFeed,
+    #[msg("Invalid supply schedule")]
+    InvalidSupplySchedule,
+    #[msg("Supply schedule is full")]
+    SupplyScheduleFull,
+    #[msg("No scheduled supply has unlocked yet")]
+    NoSupplyUnlocked,
+    #[msg("Category change requires a second approver once tokens are sold")]
+    CategoryChangeRequiresApproval,
+    #[msg("Invalid player update")]
+    InvalidPlayerUpdate,
+    #[msg("Cannot approve own proposal")]
+    CannotApproveOwnProposal,
+    #[msg("Proposal is stale")]
+    StaleProposal,
+    #[msg("Package is not on sale")]
+    PackageInactive,
+    #[msg("Target slot has not passed yet")]
+    RevealTooEarly,
+    #[msg("Pack commit expired")]
+    PackCommitExpired,
+    #[msg("Pack commit has not expired")]
+    PackCommitNotExpired,
+    #[msg("Pack commit can still be revealed")]
+    PackCommitNotRefundable,
+    #[msg("Slot hash not available")]
+    SlotHashUnavailable,
+    #[msg("Entropy source not available in this build")]
+    EntropySourceUnavailable,
+    #[msg("Invalid entropy account")]
+    InvalidEntropyAccount,
+    #[msg("Oracle randomness is stale")]
+    StaleOracleRandomness,
+    #[msg("Pack odds could not be computed")]
+    OddsTooComplex,
+    #[msg("Invalid odds data")]
+    InvalidOddsData,
+    #[msg("Invalid draft picks")]
+    InvalidDraftPicks,
+    #[msg("Draft deadline has passed")]
+    DraftExpired,
+    #[msg("Draft deadline has not passed")]
+    DraftNotExpired,
+    #[msg("Package sale has not started")]
+    SaleNotStarted,
+    #[msg("Package sale has ended")]
+    SaleEnded,
+    #[msg("Package is sold out")]
+    PackageSoldOut,
+    #[msg("Invalid sale window")]
+    InvalidSaleWindow,
+    #[msg("Wallet is not on the presale allowlist")]
+    NotOnPresaleAllowlist,
+    #[msg("Presale purchase limit reached for this wallet")]
+    PresaleWalletCapReached,
+    #[msg("Only buy_team is available during the presale")]
+    PresaleBuyTeamOnly,
+    #[msg("Invalid presale configuration")]
+    InvalidPresaleConfig,
+    #[msg("Price feed round is stale")]
+    StalePriceFeed,
+    #[msg("SOL price exceeds max_lamports")]
+    SolPriceSlippage,
+    #[msg("Payment mint is not accepted")]
+    PaymentMintNotAccepted,
+    #[msg("Invalid coupon")]
+    InvalidCoupon,
+    #[msg("Coupon cannot be redeemed for this purchase")]
+    CouponNotRedeemable,
+    #[msg("Coupon has expired")]
+    CouponExpired,
+    #[msg("Coupon redemption limit reached")]
+    CouponRedemptionLimit,
+    #[msg("Invalid referrer")]
+    InvalidReferrer,
+    #[msg("Referral loop: the referrer has a referrer of its own")]
+    ReferralLoop,
+    #[msg("Price is above the buyer's max_price")]
+    PriceAboveMax,
+    #[msg("Team cannot be refunded")]
+    TeamNotRefundable,
+    #[msg("Refund window has closed")]
+    RefundWindowClosed,
+    #[msg("Invalid oracle configuration")]
+    InvalidOracleConfig,
+    #[msg("Fee payer is not an enabled sponsor")]
+    SponsorNotAllowed,
+    #[msg("Price change must be scheduled at least MIN_PRICE_CHANGE_DELAY_SECS ahead")]
+    InvalidPriceSchedule,
+    #[msg("No pending price change")]
+    NoScheduledPrice,
+    #[msg("Withdrawal proposal is not pending")]
+    WithdrawalProposalNotPending,
+    #[msg("Withdrawal proposal has expired")]
+    WithdrawalProposalExpired,
+    #[msg("Already voted on this withdrawal proposal")]
+    AlreadyVotedOnWithdrawal,
+    #[msg("Not enough approvals to execute the withdrawal")]
+    WithdrawalThresholdNotMet,
+    #[msg("Invalid withdrawal threshold")]
+    InvalidWithdrawalThreshold,
+    #[msg("Withdrawal memo is too long")]
+    WithdrawalMemoTooLong,
+    #[msg("Draft can still be completed")]
+    DraftNotRefundable,
+    #[msg("A referrer can only be set on the first purchase")]
+    ReferralAfterFirstPurchase,
+    #[msg("Maximum number of accepted payment mints reached")]
+    TooManyAcceptedMints,
+    #[msg("Withdrawal proposal is still open")]
+    WithdrawalProposalStillOpen,
+    #[msg("Treasury ledger already seeded")]
+    TreasuryLedgerAlreadySeeded,
+    #[msg("Treasury ledger must be seeded first")]
+    TreasuryLedgerNotSeeded,
+    #[msg("Account already uses the current layout")]
+    AccountAlreadyMigrated,
+}
+
+// Inputs every entropy source mixes in
+pub struct EntropySeed {
+    pub buyer: Pubkey,
+    pub slot: u64,
+    pub unix_timestamp: i64,
+    pub next_team_id: u64,
+}
+
+impl EntropySeed {
+    fn mix(&self, source_bytes: &[u8]) -> [u8; 32] {
+        use anchor_lang::solana_program::keccak;
+
+        let mut data = Vec::with_capacity(128);
+        data.extend_from_slice(&self.slot.to_le_bytes());
+        data.extend_from_slice(&self.unix_timestamp.to_le_bytes());
+        data.extend_from_slice(self.buyer.as_ref());
+        data.extend_from_slice(&self.next_team_id.to_le_bytes());
+        data.extend_from_slice(source_bytes);
+        keccak::hash(&data).0
+    }
+}
+
+// Source of randomness for player selection
+pub trait EntropySource {
+    fn entropy(&self, seed: &EntropySeed) -> Result<[u8; 32]>;
+}
+
+struct ChainlinkEntropy<'a, 'info> {
+    sol_usd_feed: &'a AccountInfo<'info>,
+    chainlink_program: &'a AccountInfo<'info>,
+    expected_feed: Pubkey,
+    expected_program: Pubkey,
+    max_staleness_secs: i64,
+}
+
+impl EntropySource for ChainlinkEntropy<'_, '_> {
+    fn entropy(&self, seed: &EntropySeed) -> Result<[u8; 32]> {
+        // Validación estricta de cuentas contra la configuración del oráculo
+        let round = read_sol_usd_round(
+            self.sol_usd_feed,
+            self.chainlink_program,
+            &self.expected_feed,
+            &self.expected_program,
+        )?;
+        // Una ronda vieja sería conocida de antemano: mismo chequeo de antigüedad que el precio en SOL
+        let price_6 = round.micro_usdc_price(seed.unix_timestamp, self.max_staleness_secs)?;
+
+        let mut source_bytes = Vec::with_capacity(12);
+        source_bytes.extend_from_slice(&round.round_id.to_le_bytes());
+        source_bytes.extend_from_slice(&price_6.to_le_bytes());
+        Ok(seed.mix(&source_bytes))
+    }
+}
+
+struct SlotHashesEntropy<'a, 'info> {
+    slot_hashes: &'a AccountInfo<'info>,
+}
+
+impl EntropySource for SlotHashesEntropy<'_, '_> {
+    fn entropy(&self, seed: &EntropySeed) -> Result<[u8; 32]> {
+        require!(
+            self.slot_hashes.key() == anchor_lang::solana_program::sysvar::slot_hashes::ID,
+            SportsError::InvalidEntropyAccount
+        );
+        let slot_hashes_data = self.slot_hashes.try_borrow_data()?;
+        let slot_hash = latest_slot_hash(&slot_hashes_data)
+            .ok_or(SportsError::SlotHashUnavailable)?;
+        Ok(seed.mix(&slot_hash))
+    }
+}
+
+// VRF-style randomness account filled by an off-chain oracle
+struct OracleEntropy<'a, 'info> {
+    randomness_account: &'a AccountInfo<'info>,
+    expected_account: Pubkey,
+}
+
+impl OracleEntropy<'_, '_> {
+    // Layout: 8-byte discriminator, 32-byte randomness, u64 slot it was fulfilled at
+    const RANDOMNESS_OFFSET: usize = 8;
+    const MAX_STALENESS_SLOTS: u64 = 150;
+}
+
+impl EntropySource for OracleEntropy<'_, '_> {
+    fn entropy(&self, seed: &EntropySeed) -> Result<[u8; 32]> {
+        require!(
+            self.expected_account != Pubkey::default()
+                && self.randomness_account.key() == self.expected_account,
+            SportsError::InvalidEntropyAccount
+        );
+        let data = self.randomness_account.try_borrow_data()?;
+        let randomness = parse_oracle_randomness(&data, seed.slot)?;
+        Ok(seed.mix(&randomness))
+    }
+}
+
+// Fixed per buyer and team id, so local integration tests get reproducible teams
+#[cfg(feature = "test-entropy")]
+struct DeterministicEntropy;
+
+#[cfg(feature = "test-entropy")]
+impl EntropySource for DeterministicEntropy {
+    fn entropy(&self, seed: &EntropySeed) -> Result<[u8; 32]> {
+        let fixed_seed = EntropySeed {
+            buyer: seed.buyer,
+            slot: 0,
+            unix_timestamp: 0,
+            next_team_id: seed.next_team_id,
+        };
+        Ok(fixed_seed.mix(b"deterministic"))
+    }
+}
+
+// Pick the configured entropy source; SlotHashes and Oracle (test builds) read remaining_accounts[0]
+fn resolve_entropy_source<'a, 'info>(
+    game_state: &GameState,
+    sol_usd_feed: &'a AccountInfo<'info>,
+    chainlink_program: &'a AccountInfo<'info>,
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> Result<Box<dyn EntropySource + 'a>> {
+    let entropy_account = || remaining_accounts.first().ok_or(SportsError::InvalidEntropyAccount);
+
+    // A source chosen before it was restricted to test builds is refused too
+    require!(
+        game_state.entropy_source.is_available(),
+        SportsError::EntropySourceUnavailable
+    );
+    match game_state.entropy_source {
+        EntropySourceKind::Chainlink => Ok(Box::new(ChainlinkEntropy {
+            sol_usd_feed,
+            chainlink_program,
+            expected_feed: game_state.sol_usd_feed,
+            expected_program: game_state.oracle_program,
+            max_staleness_secs: game_state.max_price_staleness_secs,
+        })),
+        EntropySourceKind::SlotHashes => Ok(Box::new(SlotHashesEntropy {
+            slot_hashes: entropy_account()?,
+        })),
+        EntropySourceKind::Oracle => Ok(Box::new(OracleEntropy {
+            randomness_account: entropy_account()?,
+            expected_account: game_state.entropy_oracle,
+        })),
+        #[cfg(feature = "test-entropy")]
+        EntropySourceKind::Deterministic => Ok(Box::new(DeterministicEntropy)),
+        #[cfg(not(feature = "test-entropy"))]
+        EntropySourceKind::Deterministic => err!(SportsError::EntropySourceUnavailable),
+    }
+}
+
+// Randomness from an oracle account, rejected if unfulfilled or older than MAX_STALENESS_SLOTS
+fn parse_oracle_randomness(data: &[u8], current_slot: u64) -> Result<[u8; 32]> {
+    let offset = OracleEntropy::RANDOMNESS_OFFSET;
+    let randomness: [u8; 32] = data
+        .get(offset..offset + 32)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(SportsError::InvalidEntropyAccount)?;
+    let fulfilled_slot = data
+        .get(offset + 32..offset + 40)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or(SportsError::InvalidEntropyAccount)?;
+
+    require!(randomness != [0u8; 32], SportsError::InvalidEntropyAccount);
+    require!(
+        fulfilled_slot <= current_slot
+            && current_slot - fulfilled_slot <= OracleEntropy::MAX_STALENESS_SLOTS,
+        SportsError::StaleOracleRandomness
+    );
+    Ok(randomness)
+}
+
+// Entropy for reveal_pack: unknown to everyone until the target slot is produced
+fn generate_entropy_with_slot_hash(
+    pack_commit: &PackCommit,
+    slot_hash: &[u8; 32],
+) -> [u8; 32] {
+    use anchor_lang::solana_program::keccak;
+
+    // Only values fixed at commit time (plus the target slot hash), so the reveal moment changes nothing
+    let mut data = Vec::with_capacity(104);
+    data.extend_from_slice(&pack_commit.commitment);
+    data.extend_from_slice(slot_hash);
+    data.extend_from_slice(pack_commit.buyer.as_ref());
+    data.extend_from_slice(&pack_commit.target_slot.to_le_bytes());
+    keccak::hash(&data).0
+}
+
+// Hash of the newest slot in raw SlotHashes data
+fn latest_slot_hash(slot_hashes_data: &[u8]) -> Option<[u8; 32]> {
+    let len = u64::from_le_bytes(slot_hashes_data.get(0..8)?.try_into().ok()?);
+    if len == 0 {
+        return None;
+    }
+    slot_hashes_data.get(16..48)?.try_into().ok()
+}
+
+// Hash of the first recorded slot >= `slot` (skipped slots have no entry) from raw SlotHashes data.
+// Layout: u64 entry count, then (u64 slot, [u8; 32] hash) entries, newest first.
+// None once `slot` has aged out: the oldest remaining entry is public and must not stand in for it,
+// so the result is only trusted when it is `slot` itself or an older entry proves nothing is missing.
+fn slot_hash_at_or_after(slot_hashes_data: &[u8], slot: u64) -> Option<[u8; 32]> {
+    const ENTRY_SIZE: usize = 8 + 32;
+
+    let len = u64::from_le_bytes(slot_hashes_data.get(0..8)?.try_into().ok()?) as usize;
+    let mut found: Option<(u64, [u8; 32])> = None;
+    for i in 0..len {
+        let offset = 8 + i * ENTRY_SIZE;
+        let entry = slot_hashes_data.get(offset..offset + ENTRY_SIZE)?;
+        let entry_slot = u64::from_le_bytes(entry[0..8].try_into().ok()?);
+        if entry_slot < slot {
+            return found.map(|(_, hash)| hash);
+        }
+        found = Some((entry_slot, entry[8..ENTRY_SIZE].try_into().ok()?));
+    }
+    found.filter(|(entry_slot, _)| *entry_slot == slot).map(|(_, hash)| hash)
+}
+
+// Domain tag for the per-slot selection streams
+const SLOT_STREAM_DOMAIN: &[u8] = b"sports:team-slot:v1";
+
+// Independent random stream for one team slot: seeded with keccak(domain || entropy || slot),
+// so no two slots (general or guaranteed) ever consume the same random bytes
+struct SlotStream {
+    state: [u8; 32],
+    counter: u32,
+}
+
+impl SlotStream {
+    fn new(entropy: &[u8; 32], slot_index: u16) -> Self {
+        use anchor_lang::solana_program::keccak;
+
+        let state = keccak::hashv(&[SLOT_STREAM_DOMAIN, entropy, &slot_index.to_le_bytes()]).0;
+        SlotStream { state, counter: 0 }
+    }
+
+    fn next_u128(&mut self) -> u128 {
+        use anchor_lang::solana_program::keccak;
+
+        self.state = keccak::hashv(&[&self.state, &self.counter.to_le_bytes()]).0;
+        self.counter = self.counter.wrapping_add(1);
+        u128::from_le_bytes(self.state[0..16].try_into().unwrap())
+    }
+
+    // Uniform in [0, bound): rejection sampling to avoid modulo bias
+    fn next_below(&mut self, bound: u128) -> u128 {
+        let limit = (u128::MAX / bound) * bound;
+        loop {
+            let r = self.next_u128();
+            if r < limit {
+                return r % bound;
+            }
+        }
+    }
+}
+
+// Weighted draw (by available_tokens) of `count` players among `candidates` not already in `selected`.
+// Each pick uses its own SlotStream, numbered from `first_slot`.
+fn select_random_players_weighted(
+    candidates: &[(usize, &PlayerSummary)],
+    count: usize,
+    entropy: &[u8; 32],
+    first_slot: u16,
+    selected: &mut Vec<usize>,
+) -> Result<()> {
+    for n in 0..count {
+        let slot_index = first_slot
+            .checked_add(n as u16)
+            .ok_or(SportsError::RandomSelectionFailed)?;
+        let mut stream = SlotStream::new(entropy, slot_index);
+
+        let total: u128 = candidates.iter()
+            .filter(|(i, _)| !selected.contains(i))
+            .map(|(_, p)| p.available_tokens as u128)
+            .sum();
+        require!(total > 0, SportsError::InsufficientPlayersAvailable);
+
+        let mut target = stream.next_below(total);
+        let mut chosen = None;
+        for (idx, p) in candidates.iter() {
+            if selected.contains(idx) { continue; }
+            let w = p.available_tokens as u128;
+            if w > target {
+                chosen = Some(*idx);
+                break;
+            }
+            target -= w;
+        }
+        let chosen = chosen.ok_or(SportsError::RandomSelectionFailed)?;
+        selected.push(chosen);
+    }
+    Ok(())
+}
+
+// Function to select players for a team driven by its PackageConfig.
+// Indices are returned in slot order: general slots first, then each per-category minimum.
+fn select_team_players(
+    available_players: &[(usize, &PlayerSummary)],
+    package_config: &PackageConfig,
+    entropy: &[u8; 32],
+) -> Result<Vec<usize>> {
+    let mut indices = Vec::with_capacity(package_config.slot_count as usize);
+
+    // General slots: weighted draw over every available player
+    select_random_players_weighted(
+        available_players,
+        package_config.general_slots(),
+        entropy,
+        0,
+        &mut indices,
+    )?;
+
+    // Guaranteed slots: weighted draw over the matching players NOT already selected
+    for minimum in package_config.category_minimums.iter() {
+        let eligible_players: Vec<(usize, &PlayerSummary)> = available_players
+            .iter()
+            .filter(|(_, p)| minimum.matches(p.category))
+            .copied()
+            .collect();
+
+        let eligible_remaining = eligible_players.iter()
+            .filter(|(idx, _)| !indices.contains(idx))
+            .count();
+        require!(
+            eligible_remaining >= minimum.count as usize,
+            SportsError::InsufficientPremiumPlayers
+        );
+
+        let first_slot = indices.len() as u16;
+        select_random_players_weighted(
+            &eligible_players,
+            minimum.count as usize,
+            entropy,
+            first_slot,
+            &mut indices,
+        )?;
+    }
+
+    Ok(indices)
+}
+
+// ============ ODDS DE PAQUETES ============
+
+// Probabilities are fixed point: ODDS_SCALE = 100%
+pub const ODDS_SCALE: u64 = 1_000_000_000;
+// Internal precision of the odds computation
+const ODDS_INTERNAL_SCALE: u128 = 1_000_000_000_000_000_000;
+// Partial draws tracked at once (bounded by the 32KB program heap)
+const MAX_ODDS_STATES: usize = 256;
+// Coarsest weight bucketing: floor(log2(available_tokens)) >> 5 is 0 for any u32, one class per category
+const MAX_ODDS_BUCKET_SHIFT: u32 = 5;
+
+// Probability (ODDS_SCALE) that each entry of `players` appears in a pack of `package_config`,
+// following select_team_players slot by slot. Players are grouped into classes and the draw is tracked
+// as a multiset of classes. Classes are exact (available_tokens, category) while the partial draws fit
+// in MAX_ODDS_STATES, which keeps the odds exact for small pools; otherwise players are grouped by
+// category and weight bucket, coarser until it fits, so any pool gets a close approximation.
+// Pity guarantees are not included.
+fn compute_pack_odds(players: &[PlayerSummary], package_config: &PackageConfig) -> Result<Vec<u64>> {
+    validate_package_availability(players, package_config)?;
+
+    // Slot filters in select_team_players order: general slots, then each minimum
+    let slot_filters: Vec<Option<&CategoryMinimum>> = std::iter::repeat(None).take(package_config.general_slots())
+        .chain(package_config.category_minimums.iter()
+            .flat_map(|m| std::iter::repeat(Some(m)).take(m.count as usize)))
+        .collect();
+
+    let exact = pack_odds_for_classes(players, &slot_filters, None)?;
+    if let Some(odds) = exact {
+        return Ok(odds);
+    }
+    for shift in 0..=MAX_ODDS_BUCKET_SHIFT {
+        if let Some(odds) = pack_odds_for_classes(players, &slot_filters, Some(shift))? {
+            return Ok(odds);
+        }
+    }
+    err!(SportsError::OddsTooComplex)
+}
+
+// One pass of compute_pack_odds. `bucket_shift`: None keys classes by exact available_tokens,
+// Some(shift) by floor(log2(available_tokens)) >> shift. Ok(None) when the draws don't fit.
+fn pack_odds_for_classes(
+    players: &[PlayerSummary],
+    slot_filters: &[Option<&CategoryMinimum>],
+    bucket_shift: Option<u32>,
+) -> Result<Option<Vec<u64>>> {
+    const NO_CLASS: u8 = u8::MAX;
+
+    // Clases de jugadores: (key, category, total weight, count); u8 class ids per player
+    let mut classes: Vec<(u32, PlayerCategory, u64, u32)> = Vec::new();
+    let mut player_class: Vec<u8> = Vec::with_capacity(players.len());
+    for player in players.iter() {
+        if player.available_tokens == 0 {
+            player_class.push(NO_CLASS);
+            continue;
+        }
+        let key = match bucket_shift {
+            None => player.available_tokens,
+            Some(shift) => player.available_tokens.ilog2() >> shift,
+        };
+        let class = match classes.iter().position(|(k, c, _, _)| *k == key && *c == player.category) {
+            Some(class) => class,
+            None if classes.len() < NO_CLASS as usize => {
+                classes.push((key, player.category, 0, 0));
+                classes.len() - 1
+            }
+            None => return Ok(None),
+        };
+        classes[class].2 += player.available_tokens as u64;
+        classes[class].3 += 1;
+        player_class.push(class as u8);
+    }
+
+    // Partial draws: sorted class ids picked so far + probability, merged by multiset
+    type OddsState = ([u8; PackageConfig::MAX_SLOTS], u128);
+    let mut states: Vec<OddsState> = Vec::with_capacity(MAX_ODDS_STATES);
+    let mut next_states: Vec<OddsState> = Vec::with_capacity(MAX_ODDS_STATES);
+    states.push(([0u8; PackageConfig::MAX_SLOTS], ODDS_INTERNAL_SCALE));
+    // Expected number of picks of each class
+    let mut expected = vec![0u128; classes.len()];
+
+    for (slot, filter) in slot_filters.iter().enumerate() {
+        let is_last_slot = slot + 1 == slot_filters.len();
+        next_states.clear();
+
+        for (picks, probability) in states.iter() {
+            let picks = &picks[..slot];
+            // Undrawn players of a class count with the class' average weight
+            let remaining_weight = |class: usize| -> u128 {
+                let (_, category, weight, count) = classes[class];
+                if !filter.map_or(true, |m| m.matches(category)) {
+                    return 0;
+                }
+                let picked = picks.iter().filter(|&&c| c as usize == class).count() as u128;
+                (count as u128 - picked) * weight as u128 / count as u128
+            };
+            let total: u128 = (0..classes.len()).map(remaining_weight).sum();
+            require!(total > 0, SportsError::InsufficientPlayersAvailable);
+
+            for (class, class_expected) in expected.iter_mut().enumerate() {
+                let weight = remaining_weight(class);
+                if weight == 0 {
+                    continue;
+                }
+                let pick_probability = probability * weight / total;
+                *class_expected += pick_probability;
+                if is_last_slot {
+                    continue;
+                }
+
+                let mut next_picks = [0u8; PackageConfig::MAX_SLOTS];
+                next_picks[..slot].copy_from_slice(picks);
+                next_picks[slot] = class as u8;
+                next_picks[..=slot].sort_unstable();
+                match next_states.binary_search_by(|(p, _)| p.cmp(&next_picks)) {
+                    Ok(pos) => next_states[pos].1 += pick_probability,
+                    Err(pos) => {
+                        if next_states.len() == MAX_ODDS_STATES {
+                            return Ok(None);
+                        }
+                        next_states.insert(pos, (next_picks, pick_probability));
+                    }
+                }
+            }
+        }
+        std::mem::swap(&mut states, &mut next_states);
+    }
+
+    // A class' expected picks are shared in proportion to weight (equal shares for exact classes)
+    let scale_down = ODDS_INTERNAL_SCALE / ODDS_SCALE as u128;
+    Ok(Some(players.iter()
+        .zip(player_class.iter())
+        .map(|(player, &class)| match class {
+            NO_CLASS => 0,
+            class => {
+                let (_, _, weight, _) = classes[class as usize];
+                let share = expected[class as usize] * player.available_tokens as u128 / weight as u128;
+                (share / scale_down).min(ODDS_SCALE as u128) as u64
+            }
+        })
+        .collect()))
+}
+
+// One page of quote_pack_odds return data
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackOddsPage {
+    pub package_id: u16,
+    pub total_players: u16,     // Length of GameState.players
+    pub offset: u16,
+    pub entries: Vec<PlayerOdds>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerOdds {
+    pub player_id: u16,
+    pub probability: u32,       // ODDS_SCALE = 100%
+}
+
+impl PackOddsPage {
+    pub const VERSION: u8 = 1;
+    // Layout: version (1) + package_id (2) + total_players (2) + offset (2) + count (2)
+    pub const HEADER_SIZE: usize = 1 + 2 + 2 + 2 + 2;
+    pub const ENTRY_SIZE: usize = 2 + 4;
+    // Fits in the 1024-byte return data limit
+    pub const MAX_ENTRIES: usize = 160;
+
+    // Offset of the next page, if any
+    pub fn next_offset(&self) -> Option<u16> {
+        let next = self.offset as usize + self.entries.len();
+        (next < self.total_players as usize).then_some(next as u16)
+    }
+
+    // Client side: decode the return data of quote_pack_odds
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let read_u16 = |at: usize| -> Result<u16> {
+            data.get(at..at + 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .ok_or_else(|| SportsError::InvalidOddsData.into())
+        };
+
+        require!(data.first() == Some(&Self::VERSION), SportsError::InvalidOddsData);
+        let package_id = read_u16(1)?;
+        let total_players = read_u16(3)?;
+        let offset = read_u16(5)?;
+        let count = read_u16(7)? as usize;
+        require!(
+            data.len() == Self::HEADER_SIZE + count * Self::ENTRY_SIZE,
+            SportsError::InvalidOddsData
+        );
+
+        let entries = data[Self::HEADER_SIZE..]
+            .chunks_exact(Self::ENTRY_SIZE)
+            .map(|entry| PlayerOdds {
+                player_id: u16::from_le_bytes([entry[0], entry[1]]),
+                probability: u32::from_le_bytes([entry[2], entry[3], entry[4], entry[5]]),
+            })
+            .collect();
+        Ok(PackOddsPage { package_id, total_players, offset, entries })
+    }
+}
+
+// Serialize the page of odds starting at `offset`
+fn encode_pack_odds_page(package_id: u16, players: &[PlayerSummary], odds: &[u64], offset: u16) -> Vec<u8> {
+    let start = (offset as usize).min(players.len());
+    let end = (start + PackOddsPage::MAX_ENTRIES).min(players.len());
+
+    let mut data = Vec::with_capacity(PackOddsPage::HEADER_SIZE + (end - start) * PackOddsPage::ENTRY_SIZE);
+    data.push(PackOddsPage::VERSION);
+    data.extend_from_slice(&package_id.to_le_bytes());
+    data.extend_from_slice(&(players.len() as u16).to_le_bytes());
+    data.extend_from_slice(&offset.to_le_bytes());
+    data.extend_from_slice(&((end - start) as u16).to_le_bytes());
+    for (player, probability) in players[start..end].iter().zip(odds[start..end].iter()) {
+        data.extend_from_slice(&player.id.to_le_bytes());
+        data.extend_from_slice(&(*probability as u32).to_le_bytes());
+    }
+    data
+}
+
+// Package must be on sale (active, inside its window, `count` packs left) at a reasonable price;
+// returns the price to charge per pack
+fn validate_package_sale(
+    package_config: &PackageConfig,
+    phase: SalePhase,
+    players: &[PlayerSummary],
+    now: i64,
+    count: u32,
+) -> Result<u64> {
+    match package_config.sale_status(now) {
+        PackageSaleStatus::Inactive => return err!(SportsError::PackageInactive),
+        PackageSaleStatus::NotStarted => return err!(SportsError::SaleNotStarted),
+        PackageSaleStatus::Ended => return err!(SportsError::SaleEnded),
+        PackageSaleStatus::SoldOut => return err!(SportsError::PackageSoldOut),
+        PackageSaleStatus::Active => {}
+    }
+    if let Some(remaining) = package_config.remaining_supply() {
+        require!(count <= remaining, SportsError::PackageSoldOut);
+    }
+
+    // Validate package prices are reasonable (prevent overflow attacks)
+    let price_usdc = package_config.price_for(phase, players, now);
+    require!(
+        price_usdc > 0 && price_usdc <= MAX_PACKAGE_PRICE_USDC,
+        SportsError::InvalidPrice
+    );
+    Ok(price_usdc)
+}
+
+// Queue a pricing change, replacing any pending one. Único camino para cambiar precios: base,
+// presale y curva pasan por el mismo timelock. None keeps the package's current value
+fn schedule_price_change(
+    package_config: &mut PackageConfig,
+    game_state: &mut GameState,
+    price_history: &mut PriceHistory,
+    price_usdc: Option<u64>,
+    presale_price_usdc: Option<u64>,
+    pricing_curve: Option<Option<PricingCurve>>,
+    effective_at: i64,
+    now: i64,
+    scheduled_by: Pubkey,
+) -> Result<()> {
+    require!(
+        effective_at >= now.saturating_add(MIN_PRICE_CHANGE_DELAY_SECS),
+        SportsError::InvalidPriceSchedule
+    );
+    // Un cambio ya vencido se aplica (y queda en PriceHistory) antes de reemplazarlo
+    apply_due_price_change(package_config, game_state, Some(price_history), now)?;
+
+    let scheduled = ScheduledPrice {
+        price_usdc: price_usdc.unwrap_or(package_config.price_usdc),
+        presale_price_usdc: presale_price_usdc.unwrap_or(package_config.presale_price_usdc),
+        pricing_curve: pricing_curve.unwrap_or(package_config.pricing_curve),
+        effective_at,
+    };
+    let mut pending_config = package_config.clone();
+    pending_config.price_usdc = scheduled.price_usdc;
+    pending_config.presale_price_usdc = scheduled.presale_price_usdc;
+    pending_config.pricing_curve = scheduled.pricing_curve;
+    validate_package_config(&pending_config)?;
+    package_config.scheduled_price = Some(scheduled);
+
+    emit!(PriceChangeScheduled {
+        package_id: package_config.package_id,
+        old_price_usdc: package_config.price_usdc,
+        price_usdc: scheduled.price_usdc,
+        presale_price_usdc: scheduled.presale_price_usdc,
+        pricing_curve: scheduled.pricing_curve,
+        effective_at,
+        scheduled_by,
+        timestamp: now,
+    });
+    Ok(())
+}
+
+// Persist a due scheduled price: logged in PriceHistory, mirrored into GameState for A/B/C
+fn apply_due_price_change(
+    package_config: &mut PackageConfig,
+    game_state: &mut GameState,
+    price_history: Option<&mut PriceHistory>,
+    now: i64,
+) -> Result<()> {
+    let Some(scheduled) = package_config.due_price(now) else {
+        return Ok(());
+    };
+    let price_history = price_history.ok_or(SportsError::InvalidAccountsProvided)?;
+
+    let old_price_usdc = package_config.price_usdc;
+    package_config.price_usdc = scheduled.price_usdc;
+    package_config.presale_price_usdc = scheduled.presale_price_usdc;
+    package_config.pricing_curve = scheduled.pricing_curve;
+    package_config.scheduled_price = None;
+    match package_config.package_id {
+        PackageConfig::LEGACY_A => game_state.team_price_a = scheduled.price_usdc,
+        PackageConfig::LEGACY_B => game_state.team_price_b = scheduled.price_usdc,
+        PackageConfig::LEGACY_C => game_state.team_price_c = scheduled.price_usdc,
+        _ => {}
+    }
+    price_history.record(PriceHistoryEntry {
+        package_id: package_config.package_id,
+        old_price_usdc,
+        new_price_usdc: scheduled.price_usdc,
+        effective_at: scheduled.effective_at,
+    });
+
+    emit!(PricesUpdated {
+        package_id: package_config.package_id,
+        old_price_usdc,
+        new_price_usdc: scheduled.price_usdc,
+        presale_price_usdc: scheduled.presale_price_usdc,
+        pricing_curve: scheduled.pricing_curve,
+        effective_at: scheduled.effective_at,
+        applied_at: now,
+    });
+    Ok(())
+}
+
+// Hoja del allowlist: keccak(0x00 || wallet); nodos: keccak(0x01 || min(a, b) || max(a, b))
+fn presale_leaf(buyer: &Pubkey) -> [u8; 32] {
+    anchor_lang::solana_program::keccak::hashv(&[&[0u8], buyer.as_ref()]).0
+}
+
+fn verify_presale_proof(proof: &[[u8; 32]], root: &[u8; 32], buyer: &Pubkey) -> bool {
+    let computed = proof.iter().fold(presale_leaf(buyer), |node, sibling| {
+        let (left, right) = if node <= *sibling { (node, *sibling) } else { (*sibling, node) };
+        anchor_lang::solana_program::keccak::hashv(&[&[1u8], &left, &right]).0
+    });
+    computed == *root
+}
+
+// Silver/Gold tokens still available, the stock the pricing curve reacts to
+fn premium_stock(players: &[PlayerSummary]) -> u64 {
+    players.iter()
+        .filter(|p| matches!(p.category, PlayerCategory::Silver | PlayerCategory::Gold))
+        .map(|p| p.available_tokens as u64)
+        .sum()
+}
+
+// The price about to be charged must not exceed what the buyer accepted
+fn require_max_price(price_usdc: u64, max_price: u64) -> Result<()> {
+    require!(price_usdc <= max_price, SportsError::PriceAboveMax);
+    Ok(())
+}
+
+// Multi-pack, commit and draft purchases are closed while the presale runs
+fn require_public_sale(game_state: &GameState, now: i64) -> Result<()> {
+    require!(
+        game_state.sale_phase(now) == SalePhase::Public,
+        SportsError::PresaleBuyTeamOnly
+    );
+    Ok(())
+}
+
+fn emit_package_sale_updated(package_config: &PackageConfig, updated_by: Pubkey, timestamp: i64) {
+    emit!(PackageSaleUpdated {
+        package_id: package_config.package_id,
+        sale_start: package_config.sale_start,
+        sale_end: package_config.sale_end,
+        max_supply: package_config.max_supply,
+        sold: package_config.sold,
+        updated_by,
+        timestamp,
+    });
+}
+
+// Count `count` paid packs against the package supply
+fn record_package_sale(package_config: &mut PackageConfig, count: u32) -> Result<()> {
+    package_config.sold = package_config.sold
+        .checked_add(count)
+        .ok_or(SportsError::TokenOverflow)?;
+    Ok(())
+}
+
+// Validate sufficient players available for every slot and per-category minimum
+fn validate_package_availability(players: &[PlayerSummary], package_config: &PackageConfig) -> Result<()> {
+    let available_players: Vec<&PlayerSummary> = players
+        .iter()
+        .filter(|p| p.available_tokens > 0)
+        .collect();
+
+    // Check minimum players available for the package slots
+    require!(
+        available_players.len() >= package_config.slot_count as usize,
+        SportsError::InsufficientPlayersAvailable
+    );
+
+    // Check availability for every per-category minimum of the package
+    for minimum in package_config.category_minimums.iter() {
+        let eligible_count = available_players.iter()
+            .filter(|p| minimum.matches(p.category))
+            .count();
+        require!(
+            eligible_count >= minimum.count as usize,
+            SportsError::InsufficientPremiumPlayers
+        );
+    }
+    Ok(())
+}
+
+// Select players, update token counts and report accumulators, and initialize the team account.
+// Callers emit the purchase event (TeamPurchased, or TeamsPurchased for buy_teams).
+fn assign_team<'info>(
+    game_state: &mut Account<'info, GameState>,
+    team_account: &mut Account<'info, Team>,
+    package_config: &PackageConfig,
+    pack_history: &mut PackHistory,
+    buyer: Pubkey,                               // Owner of pack_history (who paid)
+    owner: Pubkey,                               // First owner of the team (the gift recipient, or the buyer)
+    price_paid_usdc: u64,
+    terms_accepted: bool,
+    entropy: &[u8; 32],
+    clock: &Clock,
+) -> Result<TeamPurchase> {
+    let package = TeamPackage::from_package_id(package_config.package_id);
+
+    let available_players: Vec<(usize, &PlayerSummary)> = game_state.players
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.available_tokens > 0)
+        .collect();
+
+    // Pity counter: after `pity_threshold` packs without a Gold, force one Gold slot
+    if pack_history.user == Pubkey::default() {
+        pack_history.user = buyer;
+    }
+    let gold_available = available_players.iter()
+        .any(|(_, p)| p.category == PlayerCategory::Gold);
+    let forced_config = if gold_available && pack_history.is_pity_due(game_state.pity_threshold) {
+        with_forced_gold(package_config)
+    } else {
+        None
+    };
+    let gold_guaranteed = forced_config.is_some();
+
+    let selected_indices = select_team_players(
+        &available_players,
+        forced_config.as_ref().unwrap_or(package_config),
+        entropy,
+    )?;
+
+    let got_gold = selected_indices.iter()
+        .any(|&idx| game_state.players[idx].category == PlayerCategory::Gold);
+    pack_history.record_pack(got_gold, gold_guaranteed)?;
+    if gold_guaranteed {
+        msg!("Gold guaranteed after {} packs without one", game_state.pity_threshold);
+    }
+    
+    // Actualizar tokens vendidos y obtener IDs
+    let player_ids = update_team_tokens(game_state, &selected_indices)?;
+
+    let team_id = init_team(
+        game_state,
+        team_account,
+        package.clone(),
+        owner,
+        price_paid_usdc,
+        terms_accepted,
+        &player_ids,
+        clock,
+    )?;
+
+    Ok(TeamPurchase {
+        buyer,
+        package,
+        player_ids,
+        purchase_timestamp: clock.unix_timestamp,
+        purchase_slot: clock.slot,
+        price_paid_usdc,
+        team_id,
+        gold_guaranteed,
+    })
+}
+
+// Take the next team id, add the sale to the current report and initialize the team account
+// with players whose tokens were already taken from available_tokens
+fn init_team<'info>(
+    game_state: &mut Account<'info, GameState>,
+    team_account: &mut Account<'info, Team>,
+    package: TeamPackage,
+    buyer: Pubkey,
+    price_paid_usdc: u64,
+    terms_accepted: bool,
+    player_ids: &[u16],
+    clock: &Clock,
+) -> Result<u64> {
+    // Obtener el report_id actual
+    let report_id = game_state.current_report_id;
+
+    // Crear el equipo
+    let team_id = game_state.next_team_id;
+    
+    // Validate team_id won't overflow and is reasonable
+    require!(
+        team_id > 0 && team_id < 1_000_000_000, // Prevent unreasonable team IDs
+        SportsError::TeamIdOverflow
+    );
+    
+    game_state.next_team_id = game_state.next_team_id.checked_add(1)
+        .ok_or(SportsError::TokenOverflow)?;
+
+    // Validate team_account PDA derivation matches expected team_id
+    let expected_team_pda = anchor_lang::prelude::Pubkey::find_program_address(
+        &[
+            b"team",
+            team_id.to_le_bytes().as_ref(),
+            game_state.key().as_ref(),
+            crate::ID.as_ref(),
+        ],
+        &crate::ID,
+    ).0;
+    
+    require!(
+        team_account.key() == expected_team_pda,
+        SportsError::InvalidAccountsProvided
+    );
+
+    // Actualizar acumulados del reporte actual y la tesorería
+    game_state.current_report_revenue = game_state.current_report_revenue
+        .checked_add(price_paid_usdc)
+        .ok_or(SportsError::TokenOverflow)?;
+    game_state.treasury_received = game_state.treasury_received
+        .checked_add(price_paid_usdc)
+        .ok_or(SportsError::TokenOverflow)?;
+    game_state.current_report_teams = game_state.current_report_teams
+        .checked_add(1)
+        .ok_or(SportsError::TokenOverflow)?;
+    game_state.current_report_tokens = game_state.current_report_tokens
+        .checked_add(player_ids.len() as u32)
+        .ok_or(SportsError::TokenOverflow)?;
+
+    // Inicializar el equipo
+    team_account.first_buyer = buyer;
+    team_account.player_ids = player_ids.to_vec();
+    team_account.category = package.clone();
+    team_account.created_at = clock.unix_timestamp;
+    team_account.transition_timestamp = clock.unix_timestamp;
+    team_account.nft_mint = Pubkey::default(); // Will be set when NFT is minted
+    team_account.state = TeamState::Free;
+    team_account.team_id = team_id;
+    team_account.terms_accepted = terms_accepted;
+
+    // Log team purchase info
+    msg!("Team purchased by: {}", buyer);
+    msg!("Package: {:?}", package);
+    msg!("Selected player IDs: {:?}", team_account.player_ids);
+    msg!("Terms accepted: {}", terms_accepted);
+    msg!("Price paid (USDC): ${}.{:02}", 
+        price_paid_usdc / 1_000_000,
+        (price_paid_usdc % 1_000_000) / 10_000
+    );
+
+    // Emitir evento TokenSold para cada jugador vendido
+    for player_id in player_ids {
+        emit!(TokenSold {
+            player_id: *player_id,
+            team_id,
+            timestamp: clock.unix_timestamp,
+            buyer,
+            report_id,
+        });
+    }
+
+    Ok(team_id)
+}
+
+// Mint the drafted team and return the unpicked tokens to available_tokens
+fn complete_draft(ctx: Context<FinishDraft>, picks: Vec<u8>, auto_completed: bool, clock: &Clock) -> Result<()> {
+    let draft = ctx.accounts.draft.clone().into_inner();
+    let game_state = &mut ctx.accounts.game_state;
+
+    // Check if contract is paused
+    require_not_paused(game_state)?;
+
+    // Verificar que hay un reporte abierto
+    require!(game_state.is_report_open, SportsError::NoOpenReport);
+
+    let offered_categories = draft_offered_categories(&game_state.players, &draft)?;
+    validate_draft_picks(&offered_categories, &picks, &draft.category_minimums)?;
+
+    let picked_ids: Vec<u16> = picks.iter().map(|&i| draft.offered_player_ids[i as usize]).collect();
+    let released_ids: Vec<u16> = draft.offered_player_ids.iter()
+        .enumerate()
+        .filter(|(i, _)| !picks.contains(&(*i as u8)))
+        .map(|(_, id)| *id)
+        .collect();
+
+    // Los tokens no elegidos vuelven a available_tokens
+    for player_id in released_ids.iter() {
+        let player_summary = game_state.players.iter_mut()
+            .find(|p| p.id == *player_id)
+            .ok_or(SportsError::InvalidPlayerId)?;
+        player_summary.available_tokens = player_summary.available_tokens
+            .checked_add(1)
+            .ok_or(SportsError::TokenOverflow)?;
+    }
+
+    let team_id = init_team(
+        game_state,
+        &mut ctx.accounts.team_account,
+        TeamPackage::from_package_id(draft.package_id),
+        draft.buyer,
+        draft.price_paid_usdc,
+        draft.terms_accepted,
+        &picked_ids,
+        clock,
+    )?;
+    // Drafts don't go through pack_history (pack_number 0)
+    let payer_key = ctx.accounts.payer.key();
+    ctx.accounts.purchase_receipt.set_inner(PurchaseReceipt {
+        team_id,
+        buyer: draft.buyer,
+        recipient: draft.buyer,
+        package_id: draft.package_id,
+        price_paid_usdc: draft.price_paid_usdc,
+        payment_mint: ctx.accounts.game_state.mint_usdc,
+        amount_paid: draft.price_paid_usdc,
+        report_id: ctx.accounts.game_state.current_report_id,
+        purchased_at: clock.unix_timestamp,
+        rent_payer: payer_key,
+        ..Default::default()
+    });
+
+    let nft_accounts = TeamNftAccounts {
+        game_state: ctx.accounts.game_state.to_account_info(),
+        nft_mint: ctx.accounts.nft_mint.to_account_info(),
+        user_nft_account: ctx.accounts.user_nft_account.to_account_info(),
+        metadata_account: ctx.accounts.metadata_account.to_account_info(),
+        metadata_program: ctx.accounts.metadata_program.to_account_info(),
+        update_authority: ctx.accounts.update_authority.to_account_info(),
+        payer: ctx.accounts.payer.to_account_info(),
+        token_program: ctx.accounts.token_program.to_account_info(),
+        system_program: ctx.accounts.system_program.to_account_info(),
+        rent: ctx.accounts.rent.to_account_info(),
+    };
+    mint_team_nft(
+        &ctx.accounts.game_state,
+        ctx.bumps.game_state,
+        &mut ctx.accounts.team_account,
+        &ctx.accounts.package_config.name,
+        nft_accounts,
+    )?;
+
+    // Solo el comprador puede delegar su NFT; auto_finish_draft lo firma cualquiera
+    let nft_delegate = if payer_key == draft.buyer {
+        Some(delegate_team_nft(
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.user_nft_account.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.game_state.to_account_info(),
+        )?)
+    } else {
+        None
+    };
+
+    msg!("Draft finished for: {}, team {}, picked: {:?}, released: {:?}", 
+        draft.buyer, 
+        team_id, 
+        picked_ids, 
+        released_ids
+    );
+
+    emit!(TeamPurchased {
+        team_id,
+        buyer: draft.buyer,
+        package_id: draft.package_id,
+        player_ids: picked_ids.clone(),
+        price_paid_usdc: draft.price_paid_usdc,
+        payment_mint: ctx.accounts.game_state.mint_usdc,
+        amount_paid: draft.price_paid_usdc,
+        report_id: ctx.accounts.game_state.current_report_id,
+        gold_guaranteed: false,
+        recipient: draft.buyer,
+        gift_message_hash: None,
+        nft_delegate,
+        timestamp: clock.unix_timestamp,
+    });
+    emit!(DraftFinished {
+        buyer: draft.buyer,
+        team_id,
+        picked_player_ids: picked_ids,
+        released_player_ids: released_ids,
+        auto_completed,
+        timestamp: clock.unix_timestamp,
+    });
+    Ok(())
+}
+
+// Categories of the offered athletes, in offer order
+fn draft_offered_categories(players: &[PlayerSummary], draft: &Draft) -> Result<Vec<PlayerCategory>> {
+    draft.offered_player_ids.iter()
+        .map(|id| {
+            players.iter()
+                .find(|p| p.id == *id)
+                .map(|p| p.category)
+                .ok_or_else(|| SportsError::InvalidPlayerId.into())
+        })
+        .collect()
+}
+
+// Picks must be Draft::PICKS distinct offer indices that satisfy the package minimums
+fn validate_draft_picks(offered: &[PlayerCategory], picks: &[u8], category_minimums: &[CategoryMinimum]) -> Result<()> {
+    require!(picks.len() == Draft::PICKS, SportsError::InvalidDraftPicks);
+    for (n, &pick) in picks.iter().enumerate() {
+        require!(
+            (pick as usize) < offered.len() && !picks[..n].contains(&pick),
+            SportsError::InvalidDraftPicks
+        );
+    }
+
+    // Each minimum needs its own matching picks
+    require!(
+        match_category_minimums(offered, picks, category_minimums).is_some(),
+        SportsError::InvalidDraftPicks
+    );
+    Ok(())
+}
+
+// Candidates (offer indices) that fill every minimum slot with a distinct athlete, in candidate order,
+// or None if impossible.
+// Bipartite matching with augmenting paths: a greedy pass fails with overlapping masks
+// (e.g. a Gold taken by a "premium" slot before the "Gold" slot). Candidates are tried in order.
+fn match_category_minimums(offered: &[PlayerCategory], candidates: &[u8], category_minimums: &[CategoryMinimum]) -> Option<Vec<u8>> {
+    let slots: Vec<&CategoryMinimum> = category_minimums.iter()
+        .flat_map(|m| std::iter::repeat(m).take(m.count as usize))
+        .collect();
+    // slot_of[candidate] = minimum slot it fills
+    let mut slot_of: Vec<Option<usize>> = vec![None; candidates.len()];
+
+    fn augment(slot: usize, slots: &[&CategoryMinimum], offered: &[PlayerCategory], candidates: &[u8], slot_of: &mut [Option<usize>], visited: &mut [bool]) -> bool {
+        for (c, &i) in candidates.iter().enumerate() {
+            if visited[c] || !slots[slot].matches(offered[i as usize]) {
+                continue;
+            }
+            visited[c] = true;
+            let free = match slot_of[c] {
+                None => true,
+                Some(other) => augment(other, slots, offered, candidates, slot_of, visited),
+            };
+            if free {
+                slot_of[c] = Some(slot);
+                return true;
+            }
+        }
+        false
+    }
+
+    for slot in 0..slots.len() {
+        let mut visited = vec![false; candidates.len()];
+        if !augment(slot, &slots, offered, candidates, &mut slot_of, &mut visited) {
+            return None;
+        }
+    }
+    Some(candidates.iter()
+        .zip(slot_of.iter())
+        .filter(|(_, slot)| slot.is_some())
+        .map(|(&i, _)| i)
+        .collect())
+}
+
+// Deterministic picks for abandoned drafts: fill the minimums (matching, preferring the best athletes),
+// then the best remaining (Gold > Silver > Bronze, ties by offer order)
+fn auto_draft_picks(offered: &[PlayerCategory], category_minimums: &[CategoryMinimum]) -> Vec<u8> {
+    let mut by_rank: Vec<u8> = (0..offered.len() as u8).collect();
+    by_rank.sort_by_key(|&i| std::cmp::Reverse(offered[i as usize] as u8));
+
+    let mut picks: Vec<u8> = match_category_minimums(offered, &by_rank, category_minimums).unwrap_or_default();
+    for &i in by_rank.iter() {
+        if picks.len() == Draft::PICKS {
+            break;
+        }
+        if !picks.contains(&i) {
+            picks.push(i);
+        }
+    }
+    picks
+}
+
+// Commission owed to the referrer on a sale of `price_usdc`
+fn referral_commission(price_usdc: u64, commission_bps: u16) -> u64 {
+    (price_usdc as u128 * commission_bps as u128 / 10_000) as u64
+}
+
+// Referrer bound to `buyer` and whether it was bound now, None when the buyer has no referrer.
+// A Referral PDA is created pointing at `referrer_account` only on the buyer's first purchase, rejecting
+// self-referrals and referrers that were referred themselves (one level, so no loops of any length)
+fn bind_referral<'info>(
+    referral_info: &AccountInfo<'info>,
+    referrer_account: Option<&Account<'info, ReferrerAccount>>,
+    referrer_referral_info: Option<&AccountInfo<'info>>,
+    is_first_purchase: bool,
+    buyer: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    game_state_key: &Pubkey,
+    now: i64,
+) -> Result<Option<(Pubkey, bool)>> {
+    let (expected_referral_pda, bump) = Pubkey::find_program_address(
+        &[b"referral", buyer.key.as_ref(), game_state_key.as_ref()],
+        &crate::ID,
+    );
+    require!(
+        referral_info.key() == expected_referral_pda,
+        SportsError::InvalidAccountsProvided
+    );
+
+    if !referral_info.data_is_empty() {
+        require!(referral_info.owner == &crate::ID, SportsError::InvalidAccountsProvided);
+        let referral = Referral::try_deserialize(&mut &referral_info.try_borrow_data()?[..])?;
+        return Ok(Some((referral.referrer, false)));
+    }
+
+    let Some(referrer_account) = referrer_account else {
+        return Ok(None);
+    };
+    require!(is_first_purchase, SportsError::ReferralAfterFirstPurchase);
+    let referrer = referrer_account.referrer;
+    require!(referrer != buyer.key(), SportsError::InvalidReferrer);
+
+    let referrer_referral_info = referrer_referral_info.ok_or(SportsError::InvalidAccountsProvided)?;
+    require!(
+        referrer_referral_info.key() == Pubkey::find_program_address(
+            &[b"referral", referrer.as_ref(), game_state_key.as_ref()],
+            &crate::ID,
+        ).0,
+        SportsError::InvalidAccountsProvided
+    );
+    require!(referrer_referral_info.data_is_empty(), SportsError::ReferralLoop);
+
+    create_pda_account(
+        referral_info,
+        Referral::SPACE,
+        &crate::ID,
+        &[b"referral", buyer.key.as_ref(), game_state_key.as_ref(), &[bump]],
+        payer,
+        system_program,
+    )?;
+    let referral = Referral { buyer: buyer.key(), referrer, bound_at: now };
+    referral.try_serialize(&mut &mut referral_info.try_borrow_mut_data()?[..])?;
+
+    emit!(ReferralBound {
+        buyer: buyer.key(),
+        referrer,
+        timestamp: now,
+    });
+    Ok(Some((referrer, true)))
+}
+
+// Check and count a coupon redemption for `buyer`, creating its CouponRedemption PDA on first use.
+// Returns the discount in micro-USDC
+fn redeem_coupon<'info>(
+    coupon: &mut Account<'info, Coupon>,
+    redemption_info: &AccountInfo<'info>,
+    buyer: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    package_id: u16,
+    price_usdc: u64,
+    now: i64,
+) -> Result<u64> {
+    let coupon_key = coupon.key();
+    let (expected_redemption_pda, bump) = Pubkey::find_program_address(
+        &[b"coupon_redemption", coupon_key.as_ref(), buyer.key.as_ref()],
+        &crate::ID,
+    );
+    require!(
+        redemption_info.key() == expected_redemption_pda,
+        SportsError::InvalidAccountsProvided
+    );
+
+    let mut redemption = if redemption_info.data_is_empty() {
+        create_pda_account(
+            redemption_info,
+            CouponRedemption::SPACE,
+            &crate::ID,
+            &[b"coupon_redemption", coupon_key.as_ref(), buyer.key.as_ref(), &[bump]],
+            payer,
+            system_program,
+        )?;
+        CouponRedemption { coupon: coupon_key, buyer: buyer.key(), redemptions: 0 }
+    } else {
+        require!(redemption_info.owner == &crate::ID, SportsError::InvalidAccountsProvided);
+        CouponRedemption::try_deserialize(&mut &redemption_info.try_borrow_data()?[..])?
+    };
+
+    let discount = coupon.discount_for(package_id, price_usdc, now, redemption.redemptions)?;
+    coupon.redemptions = coupon.redemptions.checked_add(1).ok_or(SportsError::TokenOverflow)?;
+    redemption.redemptions = redemption.redemptions.checked_add(1).ok_or(SportsError::TokenOverflow)?;
+    redemption.try_serialize(&mut &mut redemption_info.try_borrow_mut_data()?[..])?;
+    Ok(discount)
+}
+
+// Count a presale pack against the wallet cap. The counter PDA is keyed by the current allowlist
+// root and created on the wallet's first purchase of this presale
+fn record_presale_purchase<'info>(
+    presale_purchases_info: &AccountInfo<'info>,
+    game_state: &GameState,
+    game_state_key: &Pubkey,
+    buyer: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let (expected_pda, bump) = Pubkey::find_program_address(
+        &[b"presale_purchases", game_state.presale_merkle_root.as_ref(), buyer.key.as_ref(), game_state_key.as_ref()],
+        &crate::ID,
+    );
+    require!(
+        presale_purchases_info.key() == expected_pda,
+        SportsError::InvalidAccountsProvided
+    );
+
+    let mut presale_purchases = if presale_purchases_info.data_is_empty() {
+        create_pda_account(
+            presale_purchases_info,
+            PresalePurchases::SPACE,
+            &crate::ID,
+            &[b"presale_purchases", game_state.presale_merkle_root.as_ref(), buyer.key.as_ref(), game_state_key.as_ref(), &[bump]],
+            payer,
+            system_program,
+        )?;
+        PresalePurchases { buyer: buyer.key(), purchased: 0 }
+    } else {
+        require!(presale_purchases_info.owner == &crate::ID, SportsError::InvalidAccountsProvided);
+        PresalePurchases::try_deserialize(&mut &presale_purchases_info.try_borrow_data()?[..])?
+    };
+
+    presale_purchases.record_purchase(game_state.presale_wallet_cap)?;
+    presale_purchases.try_serialize(&mut &mut presale_purchases_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+// Undo a refunded coupon redemption on the buyer's counter (the coupon's own counter is
+// rolled back by the caller)
+fn release_coupon_redemption(
+    redemption_info: &AccountInfo,
+    coupon_key: &Pubkey,
+    buyer: &Pubkey,
+) -> Result<()> {
+    let expected_redemption_pda = Pubkey::find_program_address(
+        &[b"coupon_redemption", coupon_key.as_ref(), buyer.as_ref()],
+        &crate::ID,
+    ).0;
+    require!(
+        redemption_info.key() == expected_redemption_pda && redemption_info.owner == &crate::ID,
+        SportsError::InvalidAccountsProvided
+    );
+    let mut redemption = CouponRedemption::try_deserialize(&mut &redemption_info.try_borrow_data()?[..])?;
+    redemption.redemptions = redemption.redemptions.saturating_sub(1);
+    redemption.try_serialize(&mut &mut redemption_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+// Give a refunded presale pack back to the wallet cap of the presale it was bought in
+fn release_presale_purchase(
+    presale_purchases_info: &AccountInfo,
+    presale_root: &[u8; 32],
+    buyer: &Pubkey,
+    game_state_key: &Pubkey,
+) -> Result<()> {
+    let expected_pda = Pubkey::find_program_address(
+        &[b"presale_purchases", presale_root.as_ref(), buyer.as_ref(), game_state_key.as_ref()],
+        &crate::ID,
+    ).0;
+    require!(
+        presale_purchases_info.key() == expected_pda && presale_purchases_info.owner == &crate::ID,
+        SportsError::InvalidAccountsProvided
+    );
+    let mut presale_purchases = PresalePurchases::try_deserialize(&mut &presale_purchases_info.try_borrow_data()?[..])?;
+    presale_purchases.purchased = presale_purchases.purchased.saturating_sub(1);
+    presale_purchases.try_serialize(&mut &mut presale_purchases_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+// Clawback de la comisión de una compra reembolsada. Lo que deja de estar pendiente se libera
+// de lo adeudado a referidores y de la reserva de la tesorería
+fn claw_back_referral_commission(
+    game_state: &mut GameState,
+    referrer_account: &mut ReferrerAccount,
+    commission: u64,
+) {
+    let clawed_back = referrer_account.claw_back(commission);
+    game_state.referral_commissions_owed = game_state.referral_commissions_owed.saturating_sub(clawed_back);
+    game_state.treasury_reserved = game_state.treasury_reserved.saturating_sub(clawed_back);
+}
+
+// Accounts whose counters a team refund rolls back (refund_team and refund_teams)
+struct RefundedSaleAccounts<'a, 'info> {
+    package_config: &'a mut Account<'info, PackageConfig>,
+    pack_history: Option<&'a mut Account<'info, PackHistory>>,
+    accepted_mint: Option<&'a mut Account<'info, AcceptedMint>>,
+    coupon: Option<&'a mut Account<'info, Coupon>>,
+    coupon_redemption: Option<&'a AccountInfo<'info>>,
+    presale_purchases: Option<&'a AccountInfo<'info>>,
+    referrer_account: Option<&'a mut Account<'info, ReferrerAccount>>,
+    player_infos: &'info [AccountInfo<'info>],
+}
+
+// Deshacer una venta reembolsada: tokens (y los Player PDAs del equipo, obligatorios), acumulados
+// del reporte abierto, ledger de la tesorería, vendidos del paquete, cupón, cupo de presale,
+// contadores de pity y comisión del referidor. Devuelve si la venta era del reporte abierto
+fn roll_back_refunded_sale<'info>(
+    game_state: &mut Account<'info, GameState>,
+    receipt: &PurchaseReceipt,
+    player_ids: &[u16],
+    accounts: RefundedSaleAccounts<'_, 'info>,
+) -> Result<bool> {
+    // Devolver los tokens
+    for player_id in player_ids.iter() {
+        let player_summary = game_state.players.iter_mut()
+            .find(|p| p.id == *player_id)
+            .ok_or(SportsError::InvalidPlayerId)?;
+        player_summary.available_tokens = player_summary.available_tokens
+            .checked_add(1)
+            .ok_or(SportsError::TokenOverflow)?;
+    }
+
+    // Resync every Player PDA of the team from the restored summaries
+    let game_state_key = game_state.key();
+    require!(
+        accounts.player_infos.len() == player_ids.len(),
+        SportsError::InvalidAccountsProvided
+    );
+    let mut synced_player_ids: Vec<u16> = Vec::with_capacity(player_ids.len());
+    for player_info in accounts.player_infos.iter() {
+        let mut player_account: Account<'info, Player> = Account::try_from(player_info)?;
+        require!(
+            player_ids.contains(&player_account.id)
+                && !synced_player_ids.contains(&player_account.id)
+                && player_info.key() == Pubkey::find_program_address(
+                    &[
+                        b"player",
+                        player_account.id.to_le_bytes().as_ref(),
+                        game_state_key.as_ref(),
+                        crate::ID.as_ref(),
+                    ],
+                    &crate::ID,
+                ).0,
+            SportsError::InvalidAccountsProvided
+        );
+        synced_player_ids.push(player_account.id);
+        sync_player_from_summary(&game_state.players, &mut player_account);
+        player_account.exit(&crate::ID)?;
+    }
+
+    // Revertir el reporte abierto y el ledger del mint con que se pagó
+    let report_rolled_back = game_state.is_report_open && receipt.report_id == game_state.current_report_id;
+    let is_primary_mint = receipt.payment_mint == game_state.mint_usdc;
+    let is_sol = receipt.payment_mint == NATIVE_SOL_MINT;
+    let has_coupon = receipt.coupon != Pubkey::default();
+    if report_rolled_back {
+        if is_primary_mint {
+            game_state.current_report_revenue = game_state.current_report_revenue
+                .saturating_sub(receipt.price_paid_usdc);
+        } else if is_sol {
+            game_state.current_report_sol_revenue = game_state.current_report_sol_revenue
+                .saturating_sub(receipt.amount_paid);
+        }
+        game_state.current_report_teams = game_state.current_report_teams.saturating_sub(1);
+        game_state.current_report_tokens = game_state.current_report_tokens
+            .saturating_sub(player_ids.len() as u32);
+        if has_coupon {
+            game_state.current_report_coupon_redemptions = game_state.current_report_coupon_redemptions.saturating_sub(1);
+            game_state.current_report_coupon_discount = game_state.current_report_coupon_discount
+                .saturating_sub(receipt.coupon_discount_usdc);
         }
     }
- }
-// Struct for player update data
-#[derive(Clone, Debug, PartialEq)]
-struct PlayerUpdateData {
-    provider_id: Option<u16>,
-    category: Option<PlayerCategory>,
-    total_tokens: Option<u32>,
-    metadata_uri: Option<Option<String>>,
-    name: Option<String>,
-    discipline: Option<String>,
-    country: Option<String>,    
+    if is_primary_mint {
+        game_state.treasury_received = game_state.treasury_received.saturating_sub(receipt.price_paid_usdc);
+    } else if !is_sol {
+        let accepted_mint = accounts.accepted_mint.ok_or(SportsError::PaymentMintNotAccepted)?;
+        accepted_mint.record_refund(receipt.report_id, receipt.amount_paid);
+    }
+    accounts.package_config.sold = accounts.package_config.sold.saturating_sub(1);
+
+    // Canje del cupón y cupo de presale vuelven a estar disponibles
+    if has_coupon {
+        let coupon = accounts.coupon
+            .filter(|coupon| coupon.key() == receipt.coupon)
+            .ok_or(SportsError::InvalidCoupon)?;
+        coupon.redemptions = coupon.redemptions.saturating_sub(1);
+        let coupon_redemption = accounts.coupon_redemption.ok_or(SportsError::InvalidAccountsProvided)?;
+        release_coupon_redemption(coupon_redemption, &receipt.coupon, &receipt.buyer)?;
+    }
+    if receipt.presale_root != [0u8; 32] {
+        let presale_purchases = accounts.presale_purchases.ok_or(SportsError::InvalidAccountsProvided)?;
+        release_presale_purchase(presale_purchases, &receipt.presale_root, &receipt.buyer, &game_state_key)?;
+    }
+    if receipt.pack_number > 0 {
+        let pack_history = accounts.pack_history.ok_or(SportsError::InvalidAccountsProvided)?;
+        pack_history.unrecord_pack(receipt.pack_number, receipt.packs_without_gold_before, receipt.gold_guaranteed);
+    }
+
+    // Clawback de la comisión del referidor (de lo pendiente o de futuras comisiones)
+    if receipt.referral_commission > 0 {
+        let referrer_account = accounts.referrer_account
+            .filter(|account| account.referrer == receipt.referrer)
+            .ok_or(SportsError::InvalidReferrer)?;
+        claw_back_referral_commission(game_state, referrer_account, receipt.referral_commission);
+    }
+
+    Ok(report_rolled_back)
 }
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = GameState::SPACE,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+// Sacar de circulación el NFT de un equipo reembolsado. Lo firma el titular o, en un reembolso
+// de staff, game_state como delegado (delegate_team_nft; un regalo, un equipo revelado por un
+// crank o una delegación revocada no lo tienen)
+fn burn_refunded_nft<'info>(
+    token_program: &AccountInfo<'info>,
+    nft_mint: &AccountInfo<'info>,
+    holder_nft_account: &Account<'info, TokenAccount>,
+    holder: Option<&AccountInfo<'info>>,
+    game_state: &AccountInfo<'info>,
+    game_state_bump: u8,
+) -> Result<()> {
+    // Still held by the original recipient
+    require!(holder_nft_account.amount == 1, SportsError::TeamNotRefundable);
+    let burn_accounts = Burn {
+        mint: nft_mint.clone(),
+        from: holder_nft_account.to_account_info(),
+        authority: holder.unwrap_or(game_state).clone(),
+    };
+    if holder.is_some() {
+        return token::burn(CpiContext::new(token_program.clone(), burn_accounts), 1);
+    }
+
+    require!(
+        holder_nft_account.delegate == anchor_lang::solana_program::program_option::COption::Some(game_state.key())
+            && holder_nft_account.delegated_amount >= 1,
+        SportsError::TeamNotRefundable
+    );
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        b"game_state",
+        crate::ID.as_ref(),
+        &[game_state_bump],
+    ]];
+    token::burn(CpiContext::new_with_signer(token_program.clone(), burn_accounts, signer_seeds), 1)
 }
 
-#[derive(Accounts)]
-#[instruction(provider_id: u16, category: PlayerCategory, total_tokens: u32, metadata_uri: Option<String>)]
-pub struct CreatePlayer<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(
-        init,
-        payer = user,
-        space = Player::SPACE,
-        seeds = [b"player", game_state.next_player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
-        bump
-    )]
-    pub player_account: Account<'info, Player>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
+// Tesorerías de las que sale el pago de un equipo reembolsado
+struct RefundPaymentAccounts<'a, 'info> {
+    treasury_token_account: Option<AccountInfo<'info>>,
+    payment_mint_account: Option<&'a InterfaceAccount<'info, token_interface::Mint>>,
+    payment_token_program: Option<AccountInfo<'info>>,
+    program_usdc_authority: AccountInfo<'info>,
+    program_usdc_authority_bump: u8,
+    sol_treasury: Option<(AccountInfo<'info>, u8)>,
+    system_program: AccountInfo<'info>,
 }
 
-#[derive(Accounts)]
-#[instruction(player_id: u16)]
-pub struct AddTokens<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(
-        mut,
-        seeds = [b"player", player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
-        bump
-    )]
-    pub player_account: Account<'info, Player>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
+// Devolver el pago de un equipo reembolsado: lamports desde la sol_treasury (buy_team_with_sol)
+// o el token con que se pagó desde la cuenta del programa en ese mint
+fn return_refunded_payment<'info>(
+    receipt: &PurchaseReceipt,
+    buyer: &AccountInfo<'info>,
+    buyer_token_account: Option<AccountInfo<'info>>,
+    game_state_key: &Pubkey,
+    accounts: &RefundPaymentAccounts<'_, 'info>,
+) -> Result<()> {
+    if receipt.payment_mint == NATIVE_SOL_MINT {
+        let (sol_treasury, bump) = accounts.sol_treasury.as_ref().ok_or(SportsError::InvalidAccountsProvided)?;
+        return anchor_lang::system_program::transfer(
+            CpiContext::new_with_signer(
+                accounts.system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: sol_treasury.clone(),
+                    to: buyer.clone(),
+                },
+                &[&[b"sol_treasury", game_state_key.as_ref(), &[*bump]]],
+            ),
+            receipt.amount_paid,
+        );
+    }
+
+    let (Some(treasury_token_account), Some(buyer_token_account), Some(payment_mint_account), Some(payment_token_program)) = (
+        accounts.treasury_token_account.as_ref(),
+        buyer_token_account.as_ref(),
+        accounts.payment_mint_account,
+        accounts.payment_token_program.as_ref(),
+    ) else {
+        return err!(SportsError::InvalidAccountsProvided);
+    };
+    transfer_payment_checked(
+        treasury_token_account,
+        buyer_token_account,
+        &accounts.program_usdc_authority,
+        payment_mint_account,
+        payment_token_program,
+        receipt.amount_paid,
+        &[&[b"usdc_authority", game_state_key.as_ref(), &[accounts.program_usdc_authority_bump]]],
+    )
 }
 
-#[derive(Accounts)]
-pub struct ManageStaff<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
+// Create a PDA account owned by `owner`, paid by `payer`.
+// Como el `init` de Anchor: si alguien ya depositó lamports en la dirección, create_account fallaría,
+// así que se completa la renta y se hace allocate + assign
+fn create_pda_account<'info>(
+    new_account: &AccountInfo<'info>,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let lamports = Rent::get()?.minimum_balance(space);
+    let current_lamports = new_account.lamports();
+    if current_lamports == 0 {
+        return anchor_lang::system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                anchor_lang::system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: new_account.clone(),
+                },
+                &[signer_seeds],
+            ),
+            lamports,
+            space as u64,
+            owner,
+        );
+    }
+
+    require!(
+        new_account.owner == &anchor_lang::system_program::ID && new_account.data_is_empty(),
+        SportsError::InvalidAccountsProvided
+    );
+    let top_up = lamports.saturating_sub(current_lamports);
+    if top_up > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: new_account.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    anchor_lang::system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Allocate { account_to_allocate: new_account.clone() },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+    anchor_lang::system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            anchor_lang::system_program::Assign { account_to_assign: new_account.clone() },
+            &[signer_seeds],
+        ),
+        owner,
+    )
 }
 
-#[derive(Accounts)]
-#[instruction(player_id: u16)]
-pub struct UpdatePlayer<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(
-        mut,
-        seeds = [b"player", player_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
-        bump
-    )]
-    pub player_account: Account<'info, Player>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
+// Same account as BuyTeam's `init` team_account, for teams passed in remaining_accounts
+fn create_team_account<'info>(
+    team_info: &AccountInfo<'info>,
+    team_id: u64,
+    game_state_key: &Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let team_id_bytes = team_id.to_le_bytes();
+    let (expected_team_pda, bump) = Pubkey::find_program_address(
+        &[b"team", team_id_bytes.as_ref(), game_state_key.as_ref(), crate::ID.as_ref()],
+        &crate::ID,
+    );
+    require!(
+        team_info.key() == expected_team_pda,
+        SportsError::InvalidAccountsProvided
+    );
+
+    create_pda_account(
+        team_info,
+        Team::SPACE,
+        &crate::ID,
+        &[b"team", team_id_bytes.as_ref(), game_state_key.as_ref(), crate::ID.as_ref(), &[bump]],
+        payer,
+        system_program,
+    )?;
+    team_info.try_borrow_mut_data()?[..8].copy_from_slice(Team::DISCRIMINATOR);
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct BuyTeam<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(
-        init,
-        payer = user,
-        space = Team::SPACE,
-        seeds = [b"team", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
-        bump
-    )]
-    pub team_account: Account<'info, Team>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    /// User's USDC token account (source)
-    #[account(
-        mut,
-        constraint = user_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
-        constraint = user_usdc_account.owner == user.key() @ SportsError::InvalidTokenAccount,
-    )]
-    pub user_usdc_account: Account<'info, TokenAccount>,
-    
-    /// Program's USDC token account (destination/treasury)
-    #[account(
-        mut,
-        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
-        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
-    )]
-    pub program_usdc_account: Account<'info, TokenAccount>,
-    
-    /// PDA authority for program's USDC account
-    /// CHECK: This is validated through constraint and used as authority
-    #[account(
-        seeds = [b"usdc_authority", game_state.key().as_ref()],
-        bump
-    )]
-    pub program_usdc_authority: UncheckedAccount<'info>,
-    
-    pub clock: Sysvar<'info, Clock>,
-    
-    pub system_program: Program<'info, System>,
-    
-    pub token_program: Program<'info, Token>,
-    
-    // NFT Minting accounts
-    #[account(
-        init,
-        payer = user,
-        mint::decimals = 0,
-        mint::authority = game_state,
-        mint::freeze_authority = game_state,
-        seeds = [b"nft_mint", game_state.next_team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
-        bump
-    )]
-    pub nft_mint: Account<'info, Mint>,
+// Same account as BuyTeam's `init` purchase_receipt, written with `receipt`
+fn create_purchase_receipt<'info>(
+    receipt_info: &AccountInfo<'info>,
+    receipt: &PurchaseReceipt,
+    game_state_key: &Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let team_id_bytes = receipt.team_id.to_le_bytes();
+    let (expected_receipt_pda, bump) = Pubkey::find_program_address(
+        &[b"purchase_receipt", team_id_bytes.as_ref(), game_state_key.as_ref()],
+        &crate::ID,
+    );
+    require!(
+        receipt_info.key() == expected_receipt_pda,
+        SportsError::InvalidAccountsProvided
+    );
+
+    create_pda_account(
+        receipt_info,
+        PurchaseReceipt::SPACE,
+        &crate::ID,
+        &[b"purchase_receipt", team_id_bytes.as_ref(), game_state_key.as_ref(), &[bump]],
+        payer,
+        system_program,
+    )?;
+    receipt.try_serialize(&mut &mut receipt_info.try_borrow_mut_data()?[..])
+}
+
+// El titular delega su NFT en game_state para que staff pueda quemarlo en un reembolso
+// (p. ej. al cancelar una venta). Devuelve el delegado, que se publica en el evento de compra
+fn delegate_team_nft<'info>(
+    token_program: &AccountInfo<'info>,
+    holder_nft_account: &AccountInfo<'info>,
+    holder: &AccountInfo<'info>,
+    game_state: &AccountInfo<'info>,
+) -> Result<Pubkey> {
+    token::approve(
+        CpiContext::new(
+            token_program.clone(),
+            Approve {
+                to: holder_nft_account.clone(),
+                delegate: game_state.clone(),
+                authority: holder.clone(),
+            },
+        ),
+        1,
+    )?;
+    Ok(game_state.key())
+}
+
+// Same mint as BuyTeam's `init` nft_mint: 0 decimals, game_state as mint and freeze authority
+fn create_team_nft_mint<'info>(
+    nft_mint: &AccountInfo<'info>,
+    team_id: u64,
+    game_state_key: &Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    use anchor_lang::solana_program::program_pack::Pack;
+
+    let team_id_bytes = team_id.to_le_bytes();
+    let (expected_mint_pda, bump) = Pubkey::find_program_address(
+        &[b"nft_mint", team_id_bytes.as_ref(), game_state_key.as_ref(), crate::ID.as_ref()],
+        &crate::ID,
+    );
+    require!(
+        nft_mint.key() == expected_mint_pda,
+        SportsError::InvalidAccountsProvided
+    );
+
+    create_pda_account(
+        nft_mint,
+        anchor_spl::token::spl_token::state::Mint::LEN,
+        &token::ID,
+        &[b"nft_mint", team_id_bytes.as_ref(), game_state_key.as_ref(), crate::ID.as_ref(), &[bump]],
+        payer,
+        system_program,
+    )?;
+    token::initialize_mint2(
+        CpiContext::new(
+            token_program.clone(),
+            token::InitializeMint2 { mint: nft_mint.clone() },
+        ),
+        0,
+        game_state_key,
+        Some(game_state_key),
+    )
+}
+
+// Accounts needed to mint a team NFT and create its metadata
+struct TeamNftAccounts<'info> {
+    game_state: AccountInfo<'info>,
+    nft_mint: AccountInfo<'info>,
+    user_nft_account: AccountInfo<'info>,
+    metadata_account: AccountInfo<'info>,
+    metadata_program: AccountInfo<'info>,
+    update_authority: AccountInfo<'info>,
+    payer: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    rent: AccountInfo<'info>,
+}
+
+// Mint the team NFT (game_state is mint authority) and create its metadata
+fn mint_team_nft<'info>(
+    game_state: &GameState,
+    game_state_bump: u8,
+    team_account: &mut Team,
+    package_name: &str,
+    accounts: TeamNftAccounts<'info>,
+) -> Result<()> {
+    let team_id = team_account.team_id;
+
+    // MINT DEL NFT DEL EQUIPO - METADATA SIMPLIFICADA
+    // Usar solo datos esenciales para evitar límite de URI
+    let essential_metadata = serde_json::json!({
+        "name": format!("Team FR v1 #{}", team_id),
+        "symbol": "TEAM FR",
+        "description": format!("Team with {} players from package {}", 
+            team_account.player_ids.len(), package_name),
+        "image": format!("{}?ids={}", game_state.nft_image_url, 
+            team_account.player_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")),
+        "attributes": [
+            {
+                "trait_type": "Package",
+                "value": package_name
+            },
+            {
+                "trait_type": "Team ID",
+                "value": team_id
+            }
+        ]
+    });
     
-    /// CHECK: Metadata account PDA derivado correctamente
-    #[account(
-        mut,
-        seeds = [
-            b"metadata",
-            mpl_token_metadata::ID.as_ref(),
-            nft_mint.key().as_ref(),
-        ],
-        bump,
-        seeds::program = mpl_token_metadata::ID,
-    )]
-    pub metadata_account: UncheckedAccount<'info>,
+    // Convertir a string compacto
+    let metadata_json = serde_json::to_string(&essential_metadata)
+        .unwrap_or_else(|_| "Error serializing metadata".to_string());
     
-    #[account(
-        init,
-        payer = user,
-        associated_token::mint = nft_mint,
-        associated_token::authority = user,
-    )]
-    pub user_nft_account: Account<'info, TokenAccount>,
+    msg!("✅ Team NFT metadata: {}", metadata_json);
     
-    /// CHECK: Solo se usa para la verificación de la dirección
+    // MINT REAL DEL NFT
+    // 1. Mint 1 token al usuario usando game_state como autoridad
+    let mint_accounts = token::MintTo {
+        mint: accounts.nft_mint.clone(),
+        to: accounts.user_nft_account.clone(),
+        authority: accounts.game_state.clone(),
+    };
     
-    /// CHECK: Metaplex Token Metadata Program
-    #[account(
-        constraint = metadata_program.key() == mpl_token_metadata::ID @ SportsError::InvalidAccountsProvided
-    )]
-    pub metadata_program: UncheckedAccount<'info>,
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        b"game_state",
+        crate::ID.as_ref(),
+        &[game_state_bump],
+    ]];
     
-    /// CHECK: Update authority account for NFT metadata
-    #[account(
-        constraint = update_authority.key() == game_state.nft_update_authority @ SportsError::InvalidAccountsProvided
-    )]
-    pub update_authority: UncheckedAccount<'info>,
+    let cpi_ctx = CpiContext::new_with_signer(
+        accounts.token_program.clone(),
+        mint_accounts,
+        signer_seeds,
+    );
+    token::mint_to(cpi_ctx, 1)?;
     
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
+    // 2. Crear metadata del NFT con datos esenciales
+    let creators = Some(vec![
+        Creator {
+            address: game_state.nft_update_authority,
+            verified: false,
+            share: 100,
+        }
+    ]);
+
+    // OPCIÓN 1: Almacenar metadata en el campo 'name' del DataV2
+    // Esto garantiza que toda la información esté completamente on-chain
+    let data = DataV2 {
+        name: format!("Team FR v1 #{}", team_id), // Nombre limpio para visualización
+        symbol: "TEAM FR v1".to_string(),
+        uri: format!("{}?ids={}", game_state.nft_image_url, 
+            team_account.player_ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",")), // URI simple con imagen
+        seller_fee_basis_points: 0,
+        creators,
+        collection: None,
+        uses: None,
+    };
 
-    /// CHECK: Chainlink SOL/USD feed (validado en runtime)
-    pub sol_usd_feed: AccountInfo<'info>,
-    /// CHECK: Chainlink program (validado en runtime)
-    pub chainlink_program: AccountInfo<'info>,
+    CreateMetadataAccountV3Cpi::new(
+        &accounts.metadata_program,
+        mpl_token_metadata::instructions::CreateMetadataAccountV3CpiAccounts {
+            metadata: &accounts.metadata_account,
+            mint: &accounts.nft_mint,
+            mint_authority: &accounts.game_state,
+            payer: &accounts.payer,
+            update_authority: (&accounts.update_authority, false),
+            system_program: &accounts.system_program,
+            rent: Some(&accounts.rent),
+        },
+        mpl_token_metadata::instructions::CreateMetadataAccountV3InstructionArgs {
+            data,
+            is_mutable: true,
+            collection_details: None,
+        }
+    )
+    .invoke_signed(signer_seeds)?;
+    
+    // Guardar el mint address en el team_account
+    team_account.nft_mint = accounts.nft_mint.key();
+    
+    msg!("NFT minted successfully! Mint address: {}", accounts.nft_mint.key());
+    msg!("Metadata account: {}", accounts.metadata_account.key());
+    Ok(())
 }
 
-#[derive(Accounts)]
-pub struct UpdateTeamPrices<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
+// Copy of the package with one slot turned into a Gold-only slot (None if no slot can be converted)
+fn with_forced_gold(package_config: &PackageConfig) -> Option<PackageConfig> {
+    let gold = CategoryMinimum {
+        category_mask: 1 << PlayerCategory::Gold as u8,
+        count: 1,
+    };
+    let mut forced = package_config.clone();
+
+    if forced.general_slots() == 0 {
+        // Every slot is reserved: take the Gold slot from a minimum that already allows Gold
+        let minimum = forced.category_minimums.iter_mut()
+            .rev()
+            .find(|m| m.matches(PlayerCategory::Gold))?;
+        minimum.count -= 1;
+        forced.category_minimums.retain(|m| m.count > 0);
+    }
+
+    forced.category_minimums.push(gold);
+    Some(forced)
 }
 
-#[derive(Accounts)]
-pub struct SetNftImageUrl<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
+// Decode an account still in a previous layout, detected by being smaller than `space`
+fn read_legacy_account<T: AnchorDeserialize>(info: &AccountInfo, discriminator: &[u8], space: usize) -> Result<T> {
+    require!(info.owner == &crate::ID, SportsError::InvalidAccountsProvided);
+    let data = info.try_borrow_data()?;
+    require!(data.len() < space, SportsError::AccountAlreadyMigrated);
+    require!(
+        data.len() >= 8 && &data[..8] == discriminator,
+        SportsError::InvalidAccountsProvided
+    );
+    T::deserialize(&mut &data[8..]).map_err(|_| error!(SportsError::InvalidAccountsProvided))
 }
 
-#[derive(Accounts)]
-#[instruction(revenue: u64, teams_sold: u32, tokens_sold: u32, staker_pool: u64, stakers_count: u32)]
-pub struct CloseReport<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(
-        init,
-        payer = user,
-        space = Report::SPACE,
-        seeds = [b"report", game_state.current_report_id.to_le_bytes().as_ref(), crate::ID.as_ref()],
-        bump
-    )]
-    pub report: Account<'info, Report>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
+// Grow a legacy account to `space` (payer tops up the rent) and rewrite it in the current layout
+fn write_migrated_account<'info, T: AccountSerialize>(
+    info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    account: &T,
+) -> Result<()> {
+    let top_up = Rent::get()?.minimum_balance(space).saturating_sub(info.lamports());
+    if top_up > 0 {
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: payer.clone(),
+                    to: info.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+    info.realloc(space, true)?;
+    let mut data = info.try_borrow_mut_data()?;
+    account.try_serialize(&mut &mut data[..])?;
+    Ok(())
 }
 
+// A/B/C packages as PackageConfigs, priced from the legacy GameState fields
+fn legacy_package_configs(game_state: &GameState) -> [PackageConfig; 3] {
+    let premium = |count: u8| CategoryMinimum {
+        category_mask: CategoryMinimum::PREMIUM_MASK,
+        count,
+    };
 
-// Context for initializing user stake state
-#[derive(Accounts)]
-pub struct InitializeUserStakeState<'info> {
-    #[account(
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
+    [
+        PackageConfig {
+            package_id: PackageConfig::LEGACY_A,
+            name: "A".to_string(),
+            slot_count: 5,
+            category_minimums: Vec::new(),
+            price_usdc: game_state.team_price_a,
+            is_active: true,
+            max_supply: 0,
+            sold: 0,
+            sale_start: 0,
+            sale_end: 0,
+            presale_price_usdc: 0,
+            pricing_curve: None,
+            scheduled_price: None,
+        },
+        PackageConfig {
+            package_id: PackageConfig::LEGACY_B,
+            name: "B".to_string(),
+            slot_count: 5,
+            category_minimums: vec![premium(1)],
+            price_usdc: game_state.team_price_b,
+            is_active: true,
+            max_supply: 0,
+            sold: 0,
+            sale_start: 0,
+            sale_end: 0,
+            presale_price_usdc: 0,
+            pricing_curve: None,
+            scheduled_price: None,
+        },
+        PackageConfig {
+            package_id: PackageConfig::LEGACY_C,
+            name: "C".to_string(),
+            slot_count: 5,
+            category_minimums: vec![premium(2)],
+            price_usdc: game_state.team_price_c,
+            is_active: true,
+            max_supply: 0,
+            sold: 0,
+            sale_start: 0,
+            sale_end: 0,
+            presale_price_usdc: 0,
+            pricing_curve: None,
+            scheduled_price: None,
+        },
+    ]
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+fn validate_package_config(package_config: &PackageConfig) -> Result<()> {
+    require!(
+        !package_config.name.is_empty() && package_config.name.len() <= PackageConfig::MAX_NAME_LEN,
+        SportsError::InvalidPackage
+    );
+    require!(
+        package_config.slot_count > 0 && package_config.slot_count as usize <= PackageConfig::MAX_SLOTS,
+        SportsError::InvalidPackage
+    );
+    require!(
+        package_config.category_minimums.len() <= PackageConfig::MAX_MINIMUMS,
+        SportsError::InvalidPackage
+    );
 
-    #[account(
-        init,
-        payer = user,
-        space = UserStakeState::SPACE,
-        seeds = [b"user_stake_state", user.key().as_ref(), game_state.key().as_ref()],
-        bump
-    )]
-    pub user_stake_state: Account<'info, UserStakeState>,
+    let all_categories = (1 << PlayerCategory::Bronze as u8)
+        | (1 << PlayerCategory::Silver as u8)
+        | (1 << PlayerCategory::Gold as u8);
+    for minimum in package_config.category_minimums.iter() {
+        require!(
+            minimum.count > 0 && minimum.category_mask != 0 && minimum.category_mask & !all_categories == 0,
+            SportsError::InvalidPackage
+        );
+    }
+    require!(
+        package_config.reserved_slots() <= package_config.slot_count as usize,
+        SportsError::InvalidPackage
+    );
+    require!(
+        package_config.presale_price_usdc <= MAX_PACKAGE_PRICE_USDC,
+        SportsError::InvalidPrice
+    );
+    if let Some(curve) = package_config.pricing_curve.as_ref() {
+        require!(
+            curve.reference_premium_stock > 0
+                && curve.min_price_usdc > 0
+                && curve.min_price_usdc <= curve.max_price_usdc
+                && curve.max_price_usdc <= MAX_PACKAGE_PRICE_USDC,
+            SportsError::InvalidPrice
+        );
+    }
+    require!(
+        package_config.sale_start >= 0
+            && (package_config.sale_end == 0 || package_config.sale_end > package_config.sale_start),
+        SportsError::InvalidSaleWindow
+    );
 
-    pub system_program: Program<'info, System>,
+    // Validate prices are reasonable (non-zero and at most MAX_PACKAGE_PRICE_USDC)
+    require!(
+        package_config.price_usdc > 0 && package_config.price_usdc <= MAX_PACKAGE_PRICE_USDC,
+        SportsError::InvalidPrice
+    );
+    Ok(())
 }
 
-// Context for staking a team
-#[derive(Accounts)]
-#[instruction(team_id: u64)]
-pub struct StakeTeam<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
+// Function to update token counts for selected players
+fn update_team_tokens(
+    game_state: &mut GameState,
+    selected_indices: &[usize],
+) -> Result<Vec<u16>> {
+    let mut player_ids = Vec::new();
     
-    #[account(
-        mut,
-        seeds = [b"team", team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
-        bump
-    )]
-    pub team_account: Account<'info, Team>,
+    for &idx in selected_indices {
+        let player_summary = &mut game_state.players[idx];
+        
+        // Verify sufficient tokens
+        require!(
+            player_summary.available_tokens > 0,
+            SportsError::InsufficientTokens
+        );
+        
+        // Subtract 1 token
+        player_summary.available_tokens = player_summary.available_tokens
+            .checked_sub(1)
+            .ok_or(SportsError::InsufficientTokens)?;
+        
+        // Add player ID to result
+        player_ids.push(player_summary.id);
+    }
     
-    #[account(mut)]
-    pub user: Signer<'info>,
+    Ok(player_ids)
+}
+
+// Function to transfer USDC payment from the buyer to the program treasury
+fn transfer_usdc_payment<'info>(
+    user_usdc_account: AccountInfo<'info>,
+    program_usdc_account: AccountInfo<'info>,
+    user: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    // Validar que el amount sea mayor que 0
+    require!(
+        amount > 0,
+        SportsError::InvalidAmount
+    );
     
-    /// User stake state tracking account (list of team IDs)
-    #[account(
-        init_if_needed,
-        payer = user,
-        space = UserStakeState::SPACE,
-        seeds = [b"user_stake_state", user.key().as_ref(), game_state.key().as_ref()],
-        bump
-    )]
-    pub user_stake_state: Account<'info, UserStakeState>,
+    // Crear el contexto de transferencia
+    let transfer_accounts = Transfer {
+        from: user_usdc_account,
+        to: program_usdc_account,
+        authority: user,
+    };
+    
+    // Crear CpiContext para la transferencia
+    let cpi_ctx = CpiContext::new(
+        token_program,
+        transfer_accounts,
+    );
+    
+    // Ejecutar la transferencia real
+    token::transfer(cpi_ctx, amount)?;
+    
+    msg!("✅ USDC transferido exitosamente del usuario al programa: {} USDC", amount);
+    
+    Ok(())
+}
+
+// Amount in a mint with `decimals` equivalent to `price_usdc` (6 decimals), rounded up
+fn scale_usdc_amount(price_usdc: u64, decimals: u8) -> Result<u64> {
+    let amount = if decimals >= 6 {
+        10u128.checked_pow((decimals - 6) as u32)
+            .and_then(|scale| (price_usdc as u128).checked_mul(scale))
+    } else {
+        Some((price_usdc as u128).div_ceil(10u128.pow((6 - decimals) as u32)))
+    };
+    amount
+        .and_then(|amount| u64::try_from(amount).ok())
+        .ok_or(SportsError::TokenOverflow.into())
+}
+
+// Lamports equivalent to `price_usdc` at `sol_price_usdc` micro-USDC per SOL (rounded up)
+fn usdc_to_lamports(price_usdc: u64, sol_price_usdc: u64) -> Result<u64> {
+    require!(sol_price_usdc > 0, SportsError::InvalidPriceFeed);
+    let lamports = (price_usdc as u128 * 1_000_000_000).div_ceil(sol_price_usdc as u128);
+    u64::try_from(lamports).map_err(|_| SportsError::TokenOverflow.into())
+}
+
+// Function to calculate reward distribution
+fn calculate_reward_distribution(
+    total_amount: u64,
+    num_teams: u64,
+) -> Result<u64> {
+    // Simple equal distribution
+    if num_teams == 0 {
+        return Err(SportsError::NoEligibleTeams.into());
+    }
+    
+    Ok(total_amount / num_teams)
+}
+
+
+// Function to check if team should be auto-transitioned to OnField
+fn should_auto_transition_to_on_field(
+    team: &Team,
+    current_timestamp: i64,
+) -> bool {
+    const TWENTY_FOUR_HOURS: i64 = 24 * 60 * 60;
+    
+    if team.state == TeamState::WarmingUp {
+        let time_elapsed = current_timestamp - team.transition_timestamp;
+        
+        return time_elapsed >= TWENTY_FOUR_HOURS;
+    }
+    false
+}
+
+// Enhanced function to check if team is eligible (includes auto-transition logic)
+fn is_team_eligible_with_auto_transition(
+    team: &Team,
+    player_id: u16,
+    current_timestamp: i64,
+) -> (bool, bool) { // Returns (is_eligible, needs_transition)
+    // Check if team contains the player
+    if !team.player_ids.contains(&player_id) {
+        return (false, false);
+    }
     
-    /// Individual team stake state account (detailed state)
-    #[account(
-        init,
-        payer = user,
-        space = TeamStakeState::SPACE,
-        seeds = [b"team_stake_state", user.key().as_ref(), team_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub team_stake_state: Account<'info, TeamStakeState>,
+    // If already OnField, it's eligible
+    if team.state == TeamState::OnField {
+        return (true, false);
+    }
     
-    /// User's NFT account (source)
-    #[account(
-        mut,
-        constraint = user_nft_account.mint == team_account.nft_mint @ SportsError::InvalidNftMint,
-        constraint = user_nft_account.owner == user.key() @ SportsError::InvalidTokenAccount,
-    )]
-    pub user_nft_account: Account<'info, TokenAccount>,
+    // If in WarmingUp and 24 hours have passed, it should transition
+    if should_auto_transition_to_on_field(team, current_timestamp) {
+        return (true, true); // Eligible AND needs transition
+    }
     
-    /// Program's NFT account (destination)
-    #[account(
-        mut,
-        constraint = program_nft_account.mint == team_account.nft_mint @ SportsError::InvalidNftMint,
-        constraint = program_nft_account.owner == program_nft_authority.key() @ SportsError::InvalidTokenAccount,
-    )]
-    pub program_nft_account: Account<'info, TokenAccount>,
+    // Otherwise not eligible
+    (false, false)
+}
+
+// Function to get eligible teams from remaining accounts
+fn get_eligible_teams_from_remaining_accounts<'info>(
+    remaining_accounts: &[AccountInfo<'info>],
+    player_id: u16,
+    current_timestamp: i64,
+) -> Result<(Vec<(u64, Pubkey)>, Vec<u64>)> {  // Returns (eligible_teams, teams_needing_transition)
+    let mut eligible_teams = Vec::new();
+    let mut teams_needing_transition = Vec::new();
     
-    /// PDA authority for program's NFT account
-    /// CHECK: This is validated through constraint and used as authority
-    #[account(
-        seeds = [b"nft_authority", game_state.key().as_ref()],
-        bump
-    )]
-    pub program_nft_authority: UncheckedAccount<'info>,
+    // Process remaining accounts in pairs (team_account, team_owner_info)
+    if remaining_accounts.len() % 2 != 0 {
+        return Err(SportsError::InvalidAccountsProvided.into());
+    }
     
-    /// Clock for timestamp
-    pub clock: Sysvar<'info, Clock>,
+    for i in (0..remaining_accounts.len()).step_by(2) {
+        let team_account_info = &remaining_accounts[i];
+        let _team_owner_info = &remaining_accounts[i + 1];
+        
+        // Deserialize team account
+        let team_data = team_account_info.try_borrow_data()?;
+        
+        // Skip if not enough data for discriminator
+        if team_data.len() < 8 {
+            continue;
+        }
+        
+        // Try to deserialize as Team
+        match Team::try_deserialize(&mut &team_data[8..]) {
+            Ok(team) => {
+                // Check if team is eligible with auto-transition logic
+                let (is_eligible, needs_transition) = is_team_eligible_with_auto_transition(
+                    &team, 
+                    player_id, 
+                    current_timestamp
+                );
+                
+                if is_eligible {
+                    eligible_teams.push((team.team_id, team.first_buyer));
+                }
+                
+                if needs_transition {
+                    teams_needing_transition.push(team.team_id);
+                    msg!("Team {} needs auto-transition from WarmingUp to OnField", team.team_id);
+                }
+            }
+            Err(_) => {
+                // Skip if not a valid Team account
+                continue;
+            }
+        }
+    }
     
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    Ok((eligible_teams, teams_needing_transition))
 }
 
-// Context for withdrawing a team
-#[derive(Accounts)]
-#[instruction(team_id: u64)]
-pub struct WithdrawTeam<'info> {
-    #[account(
-        mut,
-        seeds = [b"game_state", crate::ID.as_ref()],
-        bump
-    )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(
-        mut,
-        seeds = [b"team", team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
-        bump
-    )]
-    pub team_account: Account<'info, Team>,
+// Transfer in a payment mint with transfer_checked, valid for SPL Token and Token-2022 mints.
+// `signer_seeds` when the authority is the usdc_authority PDA
+fn transfer_payment_checked<'info>(
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    mint: &InterfaceAccount<'info, token_interface::Mint>,
+    token_program: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require!(amount > 0, SportsError::InvalidAmount);
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.clone(),
+            TransferChecked {
+                from: from.clone(),
+                mint: mint.to_account_info(),
+                to: to.clone(),
+                authority: authority.clone(),
+            },
+            signer_seeds,
+        ),
+        amount,
+        mint.decimals,
+    )
+}
+
+// Function to transfer USDC to team owner
+fn transfer_usdc_to_team_owner<'info>(
+    program_usdc_account: &AccountInfo<'info>,
+    team_owner_usdc_account: &AccountInfo<'info>,
+    program_usdc_authority: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    amount: u64,
+    game_state_key: &Pubkey,
+    program_usdc_authority_bump: u8,
+    _team_owner: &Pubkey,
+) -> Result<()> {
+    // Validar que el amount sea mayor que 0
+    require!(
+        amount > 0,
+        SportsError::InvalidAmount
+    );
     
-    #[account(mut)]
-    pub user: Signer<'info>,
+    // Crear el contexto de transferencia
+    let transfer_accounts = Transfer {
+        from: program_usdc_account.to_account_info(),
+        to: team_owner_usdc_account.to_account_info(),
+        authority: program_usdc_authority.to_account_info(),
+    };
     
-    /// User stake state tracking account (list of team IDs)
-    #[account(
-        mut,
-        seeds = [b"user_stake_state", user.key().as_ref(), game_state.key().as_ref()],
-        bump
-    )]
-    pub user_stake_state: Account<'info, UserStakeState>,
+    // Seeds para firmar con el PDA
+    let authority_seeds = &[
+        b"usdc_authority",
+        game_state_key.as_ref(),
+        &[program_usdc_authority_bump],
+    ];
+    let signer_seeds = &[&authority_seeds[..]];
     
-    /// Individual team stake state account (detailed state)
-    #[account(
-        init_if_needed,
-        payer = user,
-        space = TeamStakeState::SPACE,
-        seeds = [b"team_stake_state", user.key().as_ref(), team_id.to_le_bytes().as_ref()],
-        bump
-    )]
-    pub team_stake_state: Account<'info, TeamStakeState>,
+    // Crear CpiContext con las seeds para firmar
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        transfer_accounts,
+        signer_seeds,
+    );
     
-    /// User's NFT token account (destination)
-    #[account(
-        mut,
-        constraint = user_nft_account.mint == team_account.nft_mint @ SportsError::InvalidNftMint,
-        constraint = user_nft_account.owner == user.key() @ SportsError::InvalidTokenAccount,
-    )]
-    pub user_nft_account: Account<'info, TokenAccount>,
+    // Ejecutar la transferencia real
+    token::transfer(cpi_ctx, amount)?;
     
-    /// Program's NFT token account (source)
-    #[account(
-        mut,
-        constraint = program_nft_account.mint == team_account.nft_mint @ SportsError::InvalidNftMint,
-        constraint = program_nft_account.owner == program_nft_authority.key() @ SportsError::InvalidTokenAccount,
-    )]
-    pub program_nft_account: Account<'info, TokenAccount>,
+    msg!("✅ USDC transferido exitosamente al propietario del equipo: {} USDC", amount);
     
-    /// PDA authority for program's NFT account
-    /// CHECK: This is validated through constraint and used as authority
-    #[account(
-        seeds = [b"nft_authority", game_state.key().as_ref()],
-        bump
-    )]
-    pub program_nft_authority: UncheckedAccount<'info>,
+    Ok(())
+}
+
+// Function to transfer NFT from user to program (stub)
+fn transfer_nft_to_program(
+    user: &Pubkey,
+    nft_mint: &Pubkey,
+) -> Result<()> {
+    // TODO: Implement NFT transfer
+    // This will require:
+    // 1. User's token account for the NFT
+    // 2. Program's token account for the NFT (PDA)
+    // 3. Token program
+    // 4. SPL token transfer instruction
+    // 5. Verify NFT ownership
     
-    /// Clock for timestamp
-    pub clock: Sysvar<'info, Clock>,
+    msg!("NFT transfer to program pending implementation: mint {}", nft_mint);
+    msg!("Would transfer NFT from user {} to program", user);
     
-    pub token_program: Program<'info, Token>,
+    Ok(())
+}
 
-    pub system_program: Program<'info, System>,
+// Function to transfer NFT from program back to user (stub)
+fn transfer_nft_to_user(
+    nft_mint: &Pubkey,
+    owner: &Pubkey,
+) -> Result<()> {
+    // TODO: Implement NFT transfer
+    // This will require:
+    // 1. Program's token account for the NFT (PDA)
+    // 2. User's token account for the NFT
+    // 3. Token program
+    // 4. SPL token transfer instruction with PDA signing
+    
+    msg!("NFT transfer to user pending implementation: mint {}", nft_mint);
+    msg!("Would transfer NFT from program to user {}", owner);
+    
+    Ok(())
 }
 
-// Context for refreshing team status
+
+
 #[derive(Accounts)]
-#[instruction(team_id: u64)]
-pub struct RefreshTeamStatus<'info> {
+#[instruction(mint: Pubkey)]
+pub struct ProposeWithdrawal<'info> {
     #[account(
         mut,
-        seeds = [b"team", team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        seeds = [b"game_state", crate::ID.as_ref()],
         bump
     )]
-    pub team_account: Account<'info, Team>,
-    
+    pub game_state: Account<'info, GameState>,
+
     #[account(
-        seeds = [b"game_state", crate::ID.as_ref()],
+        init,
+        payer = user,
+        space = WithdrawalProposal::SPACE,
+        seeds = [b"withdrawal_proposal", game_state.next_withdrawal_proposal_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
         bump
     )]
-    pub game_state: Account<'info, GameState>,
-    
-    #[account(mut)]
-    pub user: Signer<'info>,
-    
-    /// Clock for timestamp
-    pub clock: Sysvar<'info, Clock>,
-}
+    pub proposal: Account<'info, WithdrawalProposal>,
 
-// Main game account containing vec with minimal data
-#[account]
-pub struct GameState {
-    pub owner: Pubkey,
-    pub staff: Vec<Pubkey>,
-    pub players: Vec<PlayerSummary>,
-    pub next_player_id: u16,
-    pub mint_usdc: Pubkey,               // USDC token mint address
-    pub team_price_a: u64,
-    pub team_price_b: u64,
-    pub team_price_c: u64,
-    pub next_team_id: u64,
-    pub next_reward_id: u64,
-    pub nft_update_authority: Pubkey,    // update authority para NFTs
-    pub nft_image_url: String,           // NUEVO: url de imagen para NFTs
-    // Tracking del reporte actual
-    pub current_report_id: u64,        // ID del reporte que se está acumulando
-    pub current_report_start: i64,     // Timestamp de inicio del reporte actual
-    pub is_report_open: bool,          // Si el reporte actual está abierto para acumular
-    // Acumulados del reporte actual
-    pub current_report_revenue: u64,    // Revenue total acumulado en el reporte actual
-    pub current_report_teams: u32,      // Cantidad de equipos vendidos en el reporte actual
-    pub current_report_tokens: u32,     // Cantidad de tokens vendidos en el reporte actual
-    // Pausable functionality
-    pub is_paused: bool,                // Si el contrato está pausado
-    // Withdrawal security system
-    pub pending_withdrawal: Option<WithdrawalRequest>,  // Solicitud de retiro pendiente
-    // Time lock
-    pub time_lock: i64,                 // Tiempo de bloqueo
-}
+    /// Registry entry when withdrawing a mint other than mint_usdc or SOL
+    #[account(
+        seeds = [b"accepted_mint", mint.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub accepted_mint: Option<Account<'info, AcceptedMint>>,
 
-impl GameState {
-    // Space estimation: 8 (discriminator) + 32 (owner) + 4 (staff vec len) + (3 staff * 32) + 4 (players vec len) + (1300 players * PlayerSummary::SIZE) + 2 (next_player_id) + 32 (mint_usdc) + 24 (3 team prices u64) + 8 (next_team_id) + 8 (next_reward_id) + 8 (current_report_id) + 8 (current_report_start) + 1 (is_report_open) + 8 (current_report_revenue) + 4 (current_report_teams) + 4 (current_report_tokens) + 1 (is_paused) + 1 (option) + WithdrawalRequest::SIZE + 32 (nft_update_authority) + 1 (nft_image_url) + 1 (string)
-    // Total: 8 + 32 + 4 + 96 + 4 + (1300 * 7) + 2 + 32 + 24 + 8 + 8 + 8 + 8 + 1 + 8 + 4 + 4 + 1 + 1 + (32 + 8 + 8) + 32 + 1 + 1 + 100 = 9,664 bytes
-    pub const SPACE: usize = 8 + 32 + 4 + (3 * 32) + 4 + (1300 * PlayerSummary::SIZE) + 2 + 32 + 24 + 8 + 8 + 8 + 8 + 1 + 8 + 4 + 4 + 1 + 1 + WithdrawalRequest::SIZE + 32 + 1 + 1 + 100 + 8;
-}
+    #[account(mut)]
+    pub user: Signer<'info>,
 
-// Estructura para solicitudes de retiro pendientes
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
-pub struct WithdrawalRequest {
-    pub requester: Pubkey,              // Quien hizo la primera solicitud
-    pub amount: u64,                    // Cantidad a retirar
-    pub timestamp: i64,                 // Cuándo se hizo la solicitud
+    pub system_program: Program<'info, System>,
 }
 
-impl WithdrawalRequest {
-    // Space: 32 (requester) + 8 (amount) + 8 (timestamp)
-    pub const SIZE: usize = 32 + 8 + 8;
-}
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct VoteWithdrawal<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
 
-#[test]
-fn test_game_state_space_calculation() {
-    // Verify the space calculation is correct
-    let expected = 8 + 32 + 4 + (3 * 32) + 4 + (1300 * 7) + 2 + 32 + 24 + 8 + 8 + 8 + 8 + 1 + 8 + 4 + 4 + 1 + 1 + WithdrawalRequest::SIZE + 32 + 1 + 1 + 100;
-    assert_eq!(GameState::SPACE, expected);
-    assert_eq!(GameState::SPACE, 9664);
-}
+    #[account(
+        mut,
+        seeds = [b"withdrawal_proposal", proposal_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, WithdrawalProposal>,
 
-// Individual PDA account for each player with complete information
-#[account]
-pub struct Player {
-    pub id: u16,
-    pub provider_id: u16,
-    pub category: PlayerCategory,  // Cambiado de u8 a PlayerCategory
-    pub total_tokens: u32,
-    pub tokens_sold: u32,
-    pub metadata_uri: Option<String>,  // Cambiado de String a Option<String>
-    pub name: String,
-    pub discipline: String,
-    pub country: String
+    pub user: Signer<'info>,
 }
 
-impl Player {
-    // Space: 8 (discriminator) + 2 (id u16) + 2 (provider_id) + 1 (category) + 4 (total_tokens) + 4 (tokens_sold) + 4 (option) + 100 (string max) + 100 (name) + 100 (discipline) + 100 (country)
-    pub const SPACE: usize = 8 + 2 + 2 + 1 + 4 + 4 + 4 + 100 + 100 + 100 + 100;
-}
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct CloseWithdrawalProposal<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
 
-// Team account representing a purchased team
-#[account]
-pub struct Team {
-    pub first_buyer: Pubkey,                    // 32 bytes
-    pub player_ids: Vec<u16>,             // 4 + (5 * 2) = 14 bytes
-    pub category: TeamPackage,            // 1 byte (enum)
-    pub created_at: i64,                  // 8 bytes
-    pub transition_timestamp: i64,        // 8 bytes - timestamp cuando cambió de estado
-    pub nft_mint: Pubkey,                 // 32 bytes
-    pub state: TeamState,                 // 1 byte (enum)
-    pub team_id: u64,                     // 8 bytes - unique identifier
-    pub terms_accepted: bool,             // 1 byte - si acepta términos y condiciones
-}
+    #[account(
+        mut,
+        close = proposer,
+        seeds = [b"withdrawal_proposal", proposal_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, WithdrawalProposal>,
 
-impl Team {
-    // Space: 8 (discriminator) + 32 (owner) + 14 (player_ids vec) + 1 (category) + 8 (created_at) + 8 (transition_timestamp) + 32 (nft_mint) + 1 (state) + 8 (team_id) + 1 (terms_accepted)
-    pub const SPACE: usize = 8 + 32 + 14 + 1 + 8 + 8 + 32 + 1 + 8 + 1;
-}
+    /// CHECK: Paid the proposal's rent (bound by the proposal), gets it back
+    #[account(
+        mut,
+        constraint = proposer.key() == proposal.proposer @ SportsError::InvalidAccountsProvided,
+    )]
+    pub proposer: UncheckedAccount<'info>,
 
-// Report tracking structure
-#[account]
-pub struct Report {
-    pub report_id: u64,
-    pub epoch: u64,
-    pub start_timestamp: i64,
-    pub end_timestamp: i64,
-    pub revenue: u64,
-    pub teams_sold: u32,
-    pub tokens_sold: u32,
-    pub staker_pool: u64,
-    pub stakers_count: u32,
-    pub reward_per_staker: u64,
+    pub user: Signer<'info>,
 }
 
-impl Report {
-    pub const SPACE: usize = 8 + 8 + 8 + 8 + 8 + 4 + 4 + 8 + 4 + 8;
-}
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExecuteWithdrawal<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
 
-// User stake state tracking - PDA per user for efficient stake queries
-// Only stores team IDs, detailed state is in individual TeamStakeState PDAs
-#[account]
-pub struct UserStakeState {
-    pub user: Pubkey,                           // 32 bytes - owner of this stake state
-    pub staked_team_ids: Vec<u64>,              // 4 + (n * 8) bytes - only team IDs
-}
+    #[account(
+        mut,
+        seeds = [b"withdrawal_proposal", proposal_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub proposal: Box<Account<'info, WithdrawalProposal>>,
 
-impl UserStakeState {
-    // Space: 8 (discriminator) + 32 (user) + 4 (vec len) + (1000 * 8)
-    // Assuming max 1000 staked teams per user (8 bytes per team ID)
-    pub const SPACE: usize = 8 + 32 + 4 + (1000 * 8);
-}
+    /// Treasury (mint_usdc) or the AcceptedMint vault of the proposal's mint
+    #[account(
+        mut,
+        constraint = source_token_account.mint == proposal.mint @ SportsError::InvalidUsdcMint,
+        constraint = source_token_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub source_token_account: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
 
-// Individual team stake state - PDA per user+team for detailed tracking
-#[account]
-pub struct TeamStakeState {
-    pub user: Pubkey,                          // 32 bytes - owner of this stake
-    pub team_id: u64,                          // 8 bytes - team identifier
-    pub state: TeamState,                      // 1 byte - current team state
-    pub transition_timestamp: i64,             // 8 bytes - when state changed
-    pub rewards_earned: u64,                   // 8 bytes - accumulated rewards
-}
+    #[account(
+        mut,
+        constraint = destination.key() == proposal.destination @ SportsError::InvalidTokenAccount,
+        constraint = destination.mint == proposal.mint @ SportsError::InvalidUsdcMint,
+    )]
+    pub destination: Box<InterfaceAccount<'info, token_interface::TokenAccount>>,
 
-impl TeamStakeState {
-    pub const SPACE: usize = 8 + 32 + 8 + 1 + 8 + 8; // discriminator + user + team_id + state + timestamp + rewards
-}
+    /// The proposal's mint (transfer_checked)
+    #[account(address = proposal.mint @ SportsError::InvalidUsdcMint)]
+    pub mint: Box<InterfaceAccount<'info, token_interface::Mint>>,
 
-// Minimal structure for the vec in GameState
-#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
-pub struct PlayerSummary {
-    pub id: u16,
-    pub category: PlayerCategory,
-    pub available_tokens: u32,
-    
-}
+    /// Required when the proposal's mint is not game_state.mint_usdc
+    #[account(
+        mut,
+        seeds = [b"accepted_mint", proposal.mint.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub accepted_mint: Option<Box<Account<'info, AcceptedMint>>>,
 
-impl PlayerSummary {
-    pub const SIZE: usize = 2 + 1 + 4; // id (u16) + category + available_tokens
-}
+    /// CHECK: PDA authority for the program's token accounts
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
 
-// Enum for player categories
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Copy)]
-pub enum PlayerCategory {
-    Bronze = 0,
-    Silver = 1,
-    Gold = 2,
-}
+    pub user: Signer<'info>,
 
-// Enum for team packages
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
-pub enum TeamPackage {
-    A, // 5 random players
-    B, // 4 random + 1 Silver/Gold
-    C, // 3 random + 2 Silver/Gold
+    /// Program that owns the proposal's mint (SPL Token or Token-2022)
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-impl TeamPackage {
-    // Get price from game state
-    pub fn price_usdc(&self, game_state: &GameState) -> u64 {
-        match self {
-            TeamPackage::A => game_state.team_price_a,
-            TeamPackage::B => game_state.team_price_b,
-            TeamPackage::C => game_state.team_price_c,
-        }
-    }
-    
-    pub fn total_players(&self) -> usize {
-        5 // All packages have 5 players
-    }
-}
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExecuteSolWithdrawal<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
 
-// Enum for team states
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
-pub enum TeamState {
-    Free,        // Free/Available (was Libre)
-    WarmingUp,   // Warming up (was Calentando)
-    OnField,     // On field/Playing (was EnCancha)
-    ToWithdraw,  // To be retired/withdrawn (was ARetirar)
-}
+    #[account(
+        mut,
+        seeds = [b"withdrawal_proposal", proposal_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub proposal: Box<Account<'info, WithdrawalProposal>>,
 
-// Struct to represent selected players for a team
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
-pub struct TeamPurchase {
-    pub buyer: Pubkey,
-    pub package: TeamPackage,
-    pub player_ids: Vec<u16>,
-    pub purchase_timestamp: i64,
-    pub purchase_slot: u64,
-    pub price_paid_usdc: u64,
-}
+    /// Tesorería SOL del programa (pagos de buy_team_with_sol)
+    #[account(
+        mut,
+        seeds = [b"sol_treasury", game_state.key().as_ref()],
+        bump
+    )]
+    pub sol_treasury: SystemAccount<'info>,
 
-// Events
-#[event]
-pub struct TeamStartedWarmup {
-    pub team_id: u64,
-    pub timestamp: i64,
-}
+    /// CHECK: Receives the lamports; must be the proposal's destination
+    #[account(
+        mut,
+        constraint = destination.key() == proposal.destination @ SportsError::InvalidAccountsProvided
+    )]
+    pub destination: UncheckedAccount<'info>,
 
-#[event]
-pub struct TeamEnteredField {
-    pub team_id: u64,
-    pub timestamp: i64,
-}
+    pub user: Signer<'info>,
 
-#[event]
-pub struct TeamStartedWithdrawal {
-    pub team_id: u64,
-    pub timestamp: i64,
+    pub system_program: Program<'info, System>,
 }
 
-#[event]
-pub struct TeamWithdrawn {
-    pub team_id: u64,
-    pub timestamp: i64,
-}
+#[derive(Accounts)]
+pub struct SetWithdrawalThreshold<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
 
-// Eventos
-#[event]
-pub struct TokenSold {
-    pub player_id: u16,               // ID del jugador vendido
-    pub team_id: u64,                 // ID del equipo donde se vendió
-    pub timestamp: i64,               // Cuándo se vendió
-    pub buyer: Pubkey,                // Quién compró el equipo
-    pub report_id: u64,               // ID del reporte al que pertenece esta venta
+    pub user: Signer<'info>,
 }
 
-// Custom errors
-#[error_code]
-pub enum SportsError {
-    #[msg("Insufficient tokens available")]
-    InsufficientTokens,
-    #[msg("Invalid player ID")]
-    InvalidPlayerId,
-    #[msg("Token overflow")]
-    TokenOverflow,
-    #[msg("Unauthorized access")]
-    UnauthorizedAccess,
-    #[msg("Staff limit exceeded")]
-    StaffLimitExceeded,
-    #[msg("Staff already exists")]
-    StaffAlreadyExists,
-    #[msg("Staff not found")]
-    StaffNotFound,
-    #[msg("Invalid token update")]
-    InvalidTokenUpdate,
-    #[msg("Insufficient players available")]
-    InsufficientPlayersAvailable,
-    #[msg("Insufficient premium players")]
-    InsufficientPremiumPlayers,
-    #[msg("Random selection failed")]
-    RandomSelectionFailed,
-    #[msg("Invalid price")]
-    InvalidPrice,
-    #[msg("Invalid state transition")]
-    InvalidStateTransition,
-    #[msg("Invalid amount")]
-    InvalidAmount,
-    #[msg("Reward already distributed")]
-    RewardAlreadyDistributed,
-    #[msg("No eligible teams")]
-    NoEligibleTeams,
-    #[msg("Invalid accounts provided")]
-    InvalidAccountsProvided,
-    #[msg("Invalid team ID")]
-    InvalidTeamId,
-    #[msg("Team not in correct state for this operation")]
-    InvalidTeamState,
-    #[msg("Waiting period not complete")]
-    WaitingPeriodNotComplete,
-    #[msg("NFT transfer failed")]
-    NftTransferFailed,
-    #[msg("No hay un reporte abierto para acumular ventas")]
-    NoOpenReport,
-    #[msg("Invalid report ID")]
-    InvalidReportId,
-    #[msg("No rewards available to claim")]
-    NoRewardsAvailable,
-    #[msg("Report expired")]
-    ReportExpired,
-    #[msg("Invalid game state")]
-    InvalidGameState,
-    #[msg("Insufficient funds")]
-    InsufficientFunds,
-    #[msg("Contract is paused")]
-    ContractPaused,
-    #[msg("Terms and conditions must be accepted")]
-    TermsNotAccepted,
-    #[msg("Invalid package selected")]
-    InvalidPackage,
-    #[msg("Team ID overflow protection")]
-    TeamIdOverflow,
-    #[msg("Invalid USDC mint address")]
-    InvalidUsdcMint,
-    #[msg("Invalid token account")]
-    InvalidTokenAccount,
-    #[msg("Withdrawal request already exists")]
-    WithdrawalRequestExists,
-    #[msg("No withdrawal request pending")]
-    NoWithdrawalRequestPending,
-    #[msg("Cannot approve own withdrawal request")]
-    CannotApproveSelfWithdrawal,
-    #[msg("Withdrawal amount mismatch")]
-    WithdrawalAmountMismatch,
-    #[msg("User does not own the NFT")]
-    UserDoesNotOwnNft,
-    #[msg("Invalid NFT mint")]
-    InvalidNftMint,
-    #[msg("Invalid price feed")]
-    InvalidPriceFeed,
-}
+#[derive(Accounts)]
+#[instruction(team_id: u64)]
+pub struct RefundTeam<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
 
-// Function to generate entropy for randomness
-fn generate_entropy(
-    buyer: &Pubkey,
-    clock: &Clock,
-) -> [u8; 32] {
-    use anchor_lang::solana_program::keccak;
-    
-    // TODO: Get from Chainlink oracle
-    let chainlink_price = 1000000u64; // Placeholder: $1.00 with 6 decimals
-    
-    let mut data = Vec::new();
-    data.extend_from_slice(&clock.slot.to_le_bytes());
-    data.extend_from_slice(&clock.unix_timestamp.to_le_bytes());
-    data.extend_from_slice(buyer.as_ref());
-    data.extend_from_slice(&chainlink_price.to_le_bytes());
-    
-    keccak::hash(&data).0
-}
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"team", team_id.to_le_bytes().as_ref(), game_state.key().as_ref(), crate::ID.as_ref()],
+        bump
+    )]
+    pub team_account: Box<Account<'info, Team>>,
 
-fn generate_entropy_with_chainlink<'info>(
-    buyer: &Pubkey,
-    clock: &Clock,
-    sol_usd_feed: &AccountInfo<'info>,
-    chainlink_program: &AccountInfo<'info>,
-    next_team_id: u64, // pásalo desde buy_team
-) -> Result<[u8; 32]> {
-    use anchor_lang::solana_program::keccak;
-    let round = chainlink::latest_round_data(chainlink_program.clone(), sol_usd_feed.clone())
-        .map_err(|_| SportsError::InvalidPriceFeed)?;
-    let decimals = chainlink::decimals(chainlink_program.clone(), sol_usd_feed.clone())
-        .map_err(|_| SportsError::InvalidPriceFeed)?;
-    require!(decimals >= 6, SportsError::InvalidPriceFeed);
-    let scale = 10u64.pow(decimals as u32 - 6);
-    let price_6 = (round.answer / scale as i128) as i64;
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"purchase_receipt", team_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub purchase_receipt: Box<Account<'info, PurchaseReceipt>>,
 
-    let mut data = Vec::with_capacity(128);
-    data.extend_from_slice(&clock.slot.to_le_bytes());
-    data.extend_from_slice(&clock.unix_timestamp.to_le_bytes());
-    data.extend_from_slice(buyer.as_ref());
-    data.extend_from_slice(&next_team_id.to_le_bytes());
-    data.extend_from_slice(&round.round_id.to_le_bytes());
-    data.extend_from_slice(&price_6.to_le_bytes());
-    Ok(keccak::hash(&data).0)
-}
+    #[account(
+        mut,
+        seeds = [b"package", purchase_receipt.package_id.to_le_bytes().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub package_config: Box<Account<'info, PackageConfig>>,
 
-fn select_random_players_weighted(
-    available_players: &[(usize, &PlayerSummary)],
-    count: usize,
-    entropy: &[u8; 32],
-) -> Result<Vec<usize>> {
-    use anchor_lang::solana_program::keccak;
+    /// CHECK: Original buyer (bound by the receipt), gets the payment back (lamports for SOL purchases)
+    #[account(
+        mut,
+        constraint = buyer.key() == purchase_receipt.buyer @ SportsError::InvalidAccountsProvided,
+    )]
+    pub buyer: UncheckedAccount<'info>,
 
-    let mut sel = Vec::with_capacity(count);
-    let mut used = std::collections::HashSet::new();
-    let mut state = *entropy;
+    /// CHECK: Whoever paid the rent at purchase (bound by the receipt), receives the rent of the closed accounts
+    #[account(
+        mut,
+        constraint = rent_payer.key() == purchase_receipt.rent_payer @ SportsError::InvalidAccountsProvided,
+    )]
+    pub rent_payer: UncheckedAccount<'info>,
 
-    while sel.len() < count {
-        // encadenado por pick (no sólo iteration++)
-        state = keccak::hash(&state).0;
+    /// NFT holder (within the refund window) or staff
+    pub authority: Signer<'info>,
 
-        let total: u128 = available_players.iter()
-            .filter(|(i, _)| !used.contains(i))
-            .map(|(_, p)| p.available_tokens as u128)
-            .sum();
-        require!(total > 0, SportsError::InsufficientPlayersAvailable);
+    #[account(
+        mut,
+        constraint = nft_mint.key() == team_account.nft_mint @ SportsError::InvalidAccountsProvided,
+    )]
+    pub nft_mint: Box<Account<'info, Mint>>,
 
-        // u128 + rejection sampling para evitar sesgo del módulo
-        let mut r = u128::from_le_bytes(state[0..16].try_into().unwrap());
-        let bound = (u128::MAX / total) * total;
-        while r >= bound {
-            state = keccak::hash(&state).0;
-            r = u128::from_le_bytes(state[0..16].try_into().unwrap());
-        }
-        let mut target = r % total;
+    /// NFT account of the holder (the gift recipient, or the buyer)
+    #[account(
+        mut,
+        constraint = buyer_nft_account.mint == nft_mint.key() @ SportsError::InvalidAccountsProvided,
+        constraint = buyer_nft_account.owner == purchase_receipt.recipient @ SportsError::InvalidTokenAccount,
+    )]
+    pub buyer_nft_account: Box<Account<'info, TokenAccount>>,
 
-        let mut chosen = None;
-        for (idx, p) in available_players.iter() {
-            if used.contains(idx) { continue; }
-            let w = p.available_tokens as u128;
-            if w > target {
-                chosen = Some(*idx);
-                break;
-            }
-            target -= w;
-        }
-        require!(chosen.is_some(), SportsError::RandomSelectionFailed);
-        used.insert(chosen.unwrap());
-        sel.push(chosen.unwrap());
-    }
-    Ok(sel)
-}
+    /// Program's token account in the mint the team was paid with (not needed for SOL purchases)
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == purchase_receipt.payment_mint @ SportsError::InvalidUsdcMint,
+        constraint = treasury_token_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub treasury_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
 
-fn generate_entropy_with_chainlink2<'info>(
-    buyer: &Pubkey,
-    clock: &Clock,
-    sol_usd_feed: &AccountInfo<'info>,
-    chainlink_program: &AccountInfo<'info>,
-) -> Result<[u8; 32]> {
-    use anchor_lang::solana_program::keccak;
+    /// Buyer's token account in the same mint (destination)
+    #[account(
+        mut,
+        constraint = buyer_token_account.mint == purchase_receipt.payment_mint @ SportsError::InvalidUsdcMint,
+        constraint = buyer_token_account.owner == buyer.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub buyer_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
 
-    let price = get_current_sol_usdc_price_from_chainlink(sol_usd_feed, chainlink_program)?;
+    /// The mint the team was paid with
+    #[account(address = purchase_receipt.payment_mint @ SportsError::InvalidUsdcMint)]
+    pub payment_mint_account: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
 
-    let mut data = Vec::new();
-    data.extend_from_slice(&clock.slot.to_le_bytes());
-    data.extend_from_slice(&clock.unix_timestamp.to_le_bytes());
-    data.extend_from_slice(buyer.as_ref());
-    data.extend_from_slice(&price.to_le_bytes());
-    Ok(keccak::hash(&data).0)
-}
+    /// Program that owns the payment mint (SPL Token or Token-2022)
+    pub payment_token_program: Option<Interface<'info, TokenInterface>>,
 
-// Function to select players for a team based on package type (optimized version)
-fn select_team_players(
-    available_players: &[(usize, &PlayerSummary)],
-    package: &TeamPackage,
-    entropy: &[u8; 32],
-) -> Result<Vec<usize>> {
-    match package {
-        TeamPackage::A => {
-            // Select 5 random players from available players
-            select_random_players_weighted(available_players, 5, entropy)
-        },
-        TeamPackage::B => {
-            // Select 4 random players + 1 Silver/Gold (ensuring no duplicates)
-            let mut indices = select_random_players_weighted(available_players, 4, entropy)?;
-            
-            // Filter premium players that are NOT already selected
-            let premium_players: Vec<(usize, &PlayerSummary)> = available_players
-                .iter()
-                .filter(|(idx, p)| {
-                    (p.category == PlayerCategory::Silver || p.category == PlayerCategory::Gold) &&
-                    !indices.contains(idx)
-                })
-                .copied()
-                .collect();
-            
-            require!(
-                !premium_players.is_empty(),
-                SportsError::InsufficientPremiumPlayers
-            );
-            
-            let premium_selected = select_random_players_weighted(&premium_players, 1, entropy)?;
-            indices.extend(premium_selected);
-            Ok(indices)
-        },
-        TeamPackage::C => {
-            // Select 3 random players + 2 Silver/Gold (ensuring no duplicates)
-            let mut indices = select_random_players_weighted(available_players, 3, entropy)?;
-            
-            // Filter premium players that are NOT already selected
-            let premium_players: Vec<(usize, &PlayerSummary)> = available_players
-                .iter()
-                .filter(|(idx, p)| {
-                    (p.category == PlayerCategory::Silver || p.category == PlayerCategory::Gold) &&
-                    !indices.contains(idx)
-                })
-                .copied()
-                .collect();
-            
-            require!(
-                premium_players.len() >= 2,
-                SportsError::InsufficientPremiumPlayers
-            );
-            
-            let premium_selected = select_random_players_weighted(&premium_players, 2, entropy)?;
-            indices.extend(premium_selected);
-            Ok(indices)
-        },
-    }
-}
+    /// Tesorería SOL, for teams bought with buy_team_with_sol
+    #[account(
+        mut,
+        seeds = [b"sol_treasury", game_state.key().as_ref()],
+        bump
+    )]
+    pub sol_treasury: Option<SystemAccount<'info>>,
 
-// Function to select random players with weighted selection based on available tokens
-fn select_random_players_weighted2(
-    available_players: &[(usize, &PlayerSummary)],
-    count: usize,
-    entropy: &[u8; 32],
-) -> Result<Vec<usize>> {
-    let mut selected_indices = Vec::new();
-    let mut used_indices = std::collections::HashSet::new();
-    
-    let mut iteration = 0u8;
-    while selected_indices.len() < count {
-        // Create additional entropy for each iteration
-        let mut hash_input = entropy.to_vec();
-        hash_input.push(iteration);
-        let hash = anchor_lang::solana_program::keccak::hash(&hash_input);
-        
-        // Use first 8 bytes as u64 for weighted selection
-        let random_value = u64::from_le_bytes(hash.0[0..8].try_into().unwrap());
-        
-        // Calculate total available tokens
-        let total_tokens: u64 = available_players
-            .iter()
-            .filter(|(idx, _)| !used_indices.contains(idx))
-            .map(|(_, p)| p.available_tokens as u64)
-            .sum();
-        
-        if total_tokens == 0 {
-            return Err(SportsError::InsufficientPlayersAvailable.into());
-        }
-        
-        // Weighted selection based on available tokens
-        let target_token = random_value % total_tokens;
-        let mut current_sum = 0u64;
-        let mut selected_idx = None;
-        
-        for (idx, player) in available_players {
-            if !used_indices.contains(idx) {
-                current_sum += player.available_tokens as u64;
-                if current_sum > target_token {
-                    selected_idx = Some(*idx);
-                    break;
-                }
-            }
-        }
-        
-        if let Some(idx) = selected_idx {
-            selected_indices.push(idx);
-            used_indices.insert(idx);
-        }
-        
-        iteration = iteration.wrapping_add(1);
-        
-        // Prevent infinite loop
-        if iteration == 255 {
-            return Err(SportsError::RandomSelectionFailed.into());
-        }
-    }
-    
-    Ok(selected_indices)
-}
+    /// CHECK: PDA authority for the program's token accounts
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    /// Registry entry when the team was paid with a mint other than game_state.mint_usdc
+    #[account(
+        mut,
+        seeds = [b"accepted_mint", purchase_receipt.payment_mint.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub accepted_mint: Option<Box<Account<'info, AcceptedMint>>>,
+
+    /// Referrer credited on this purchase, when the receipt has a commission
+    #[account(
+        mut,
+        seeds = [b"referrer", purchase_receipt.referrer.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub referrer_account: Option<Box<Account<'info, ReferrerAccount>>>,
+
+    /// Buyer's pity counters, rolled back with the pack (not needed for drafts)
+    #[account(
+        mut,
+        seeds = [b"pack_history", buyer.key().as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub pack_history: Option<Box<Account<'info, PackHistory>>>,
 
-// Function to update token counts for selected players
-fn update_team_tokens(
-    game_state: &mut GameState,
-    selected_indices: &[usize],
-) -> Result<Vec<u16>> {
-    let mut player_ids = Vec::new();
-    
-    for &idx in selected_indices {
-        let player_summary = &mut game_state.players[idx];
-        
-        // Verify sufficient tokens
-        require!(
-            player_summary.available_tokens > 0,
-            SportsError::InsufficientTokens
-        );
-        
-        // Subtract 1 token
-        player_summary.available_tokens = player_summary.available_tokens
-            .checked_sub(1)
-            .ok_or(SportsError::InsufficientTokens)?;
-        
-        // Add player ID to result
-        player_ids.push(player_summary.id);
-    }
-    
-    Ok(player_ids)
-}
+    /// Coupon redeemed on this purchase, when the receipt has one
+    #[account(
+        mut,
+        address = purchase_receipt.coupon @ SportsError::InvalidCoupon
+    )]
+    pub coupon: Option<Box<Account<'info, Coupon>>>,
 
-// Function to transfer USDC payment
-fn transfer_usdc_payment(
-    ctx: Context<BuyTeam>,
-    amount: u64,
-) -> Result<()> {
-    // Validar que el amount sea mayor que 0
-    require!(
-        amount > 0,
-        SportsError::InvalidAmount
-    );
-    
-    // Crear el contexto de transferencia
-    let transfer_accounts = Transfer {
-        from: ctx.accounts.user_usdc_account.to_account_info(),
-        to: ctx.accounts.program_usdc_account.to_account_info(),
-        authority: ctx.accounts.user.to_account_info(),
-    };
-    
-    // Crear CpiContext para la transferencia
-    let cpi_ctx = CpiContext::new(
-        ctx.accounts.token_program.to_account_info(),
-        transfer_accounts,
-    );
-    
-    // Ejecutar la transferencia real
-    token::transfer(cpi_ctx, amount)?;
-    
-    msg!("✅ USDC transferido exitosamente del usuario al programa: {} USDC", amount);
-    
-    Ok(())
-}
+    /// CHECK: Buyer's CouponRedemption counter for that coupon (validated in release_coupon_redemption)
+    #[account(mut)]
+    pub coupon_redemption: Option<UncheckedAccount<'info>>,
 
-// Function to calculate reward distribution
-fn calculate_reward_distribution(
-    total_amount: u64,
-    num_teams: u64,
-) -> Result<u64> {
-    // Simple equal distribution
-    if num_teams == 0 {
-        return Err(SportsError::NoEligibleTeams.into());
-    }
-    
-    Ok(total_amount / num_teams)
+    /// CHECK: Buyer's PresalePurchases counter when bought in a presale (validated in release_presale_purchase)
+    #[account(mut)]
+    pub presale_purchases: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(payment_mint: Pubkey)]
+pub struct RefundTeams<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Box<Account<'info, GameState>>,
 
-// Function to check if team should be auto-transitioned to OnField
-fn should_auto_transition_to_on_field(
-    team: &Team,
-    current_timestamp: i64,
-) -> bool {
-    const TWENTY_FOUR_HOURS: i64 = 24 * 60 * 60;
-    
-    if team.state == TeamState::WarmingUp {
-        let time_elapsed = current_timestamp - team.transition_timestamp;
-        
-        return time_elapsed >= TWENTY_FOUR_HOURS;
-    }
-    false
-}
+    /// Owner or staff
+    pub authority: Signer<'info>,
 
-// Enhanced function to check if team is eligible (includes auto-transition logic)
-fn is_team_eligible_with_auto_transition(
-    team: &Team,
-    player_id: u16,
-    current_timestamp: i64,
-) -> (bool, bool) { // Returns (is_eligible, needs_transition)
-    // Check if team contains the player
-    if !team.player_ids.contains(&player_id) {
-        return (false, false);
-    }
-    
-    // If already OnField, it's eligible
-    if team.state == TeamState::OnField {
-        return (true, false);
-    }
-    
-    // If in WarmingUp and 24 hours have passed, it should transition
-    if should_auto_transition_to_on_field(team, current_timestamp) {
-        return (true, true); // Eligible AND needs transition
-    }
-    
-    // Otherwise not eligible
-    (false, false)
-}
+    /// Program's token account in the mint every team of the batch was paid with (not for SOL batches)
+    #[account(
+        mut,
+        constraint = treasury_token_account.mint == payment_mint @ SportsError::InvalidUsdcMint,
+        constraint = treasury_token_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+    )]
+    pub treasury_token_account: Option<Box<InterfaceAccount<'info, token_interface::TokenAccount>>>,
 
-// Function to get eligible teams from remaining accounts
-fn get_eligible_teams_from_remaining_accounts<'info>(
-    remaining_accounts: &[AccountInfo<'info>],
-    player_id: u16,
-    current_timestamp: i64,
-) -> Result<(Vec<(u64, Pubkey)>, Vec<u64>)> {  // Returns (eligible_teams, teams_needing_transition)
-    let mut eligible_teams = Vec::new();
-    let mut teams_needing_transition = Vec::new();
-    
-    // Process remaining accounts in pairs (team_account, team_owner_info)
-    if remaining_accounts.len() % 2 != 0 {
-        return Err(SportsError::InvalidAccountsProvided.into());
-    }
-    
-    for i in (0..remaining_accounts.len()).step_by(2) {
-        let team_account_info = &remaining_accounts[i];
-        let _team_owner_info = &remaining_accounts[i + 1];
-        
-        // Deserialize team account
-        let team_data = team_account_info.try_borrow_data()?;
-        
-        // Skip if not enough data for discriminator
-        if team_data.len() < 8 {
-            continue;
-        }
-        
-        // Try to deserialize as Team
-        match Team::try_deserialize(&mut &team_data[8..]) {
-            Ok(team) => {
-                // Check if team is eligible with auto-transition logic
-                let (is_eligible, needs_transition) = is_team_eligible_with_auto_transition(
-                    &team, 
-                    player_id, 
-                    current_timestamp
-                );
-                
-                if is_eligible {
-                    eligible_teams.push((team.team_id, team.first_buyer));
-                }
-                
-                if needs_transition {
-                    teams_needing_transition.push(team.team_id);
-                    msg!("Team {} needs auto-transition from WarmingUp to OnField", team.team_id);
-                }
-            }
-            Err(_) => {
-                // Skip if not a valid Team account
-                continue;
-            }
-        }
-    }
-    
-    Ok((eligible_teams, teams_needing_transition))
-}
+    #[account(address = payment_mint @ SportsError::InvalidUsdcMint)]
+    pub payment_mint_account: Option<Box<InterfaceAccount<'info, token_interface::Mint>>>,
 
-// Function to transfer USDC to team owner
-fn transfer_usdc_to_team_owner<'info>(
-    program_usdc_account: &AccountInfo<'info>,
-    team_owner_usdc_account: &AccountInfo<'info>,
-    program_usdc_authority: &AccountInfo<'info>,
-    token_program: &AccountInfo<'info>,
-    amount: u64,
-    game_state_key: &Pubkey,
-    program_usdc_authority_bump: u8,
-    _team_owner: &Pubkey,
-) -> Result<()> {
-    // Validar que el amount sea mayor que 0
-    require!(
-        amount > 0,
-        SportsError::InvalidAmount
-    );
-    
-    // Crear el contexto de transferencia
-    let transfer_accounts = Transfer {
-        from: program_usdc_account.to_account_info(),
-        to: team_owner_usdc_account.to_account_info(),
-        authority: program_usdc_authority.to_account_info(),
-    };
-    
-    // Seeds para firmar con el PDA
-    let authority_seeds = &[
-        b"usdc_authority",
-        game_state_key.as_ref(),
-        &[program_usdc_authority_bump],
-    ];
-    let signer_seeds = &[&authority_seeds[..]];
-    
-    // Crear CpiContext con las seeds para firmar
-    let cpi_ctx = CpiContext::new_with_signer(
-        token_program.to_account_info(),
-        transfer_accounts,
-        signer_seeds,
-    );
-    
-    // Ejecutar la transferencia real
-    token::transfer(cpi_ctx, amount)?;
-    
-    msg!("✅ USDC transferido exitosamente al propietario del equipo: {} USDC", amount);
-    
-    Ok(())
+    /// Program that owns the payment mint (SPL Token or Token-2022)
+    pub payment_token_program: Option<Interface<'info, TokenInterface>>,
+
+    /// Tesorería SOL, for a batch bought with buy_team_with_sol (payment_mint = NATIVE_SOL_MINT)
+    #[account(
+        mut,
+        seeds = [b"sol_treasury", game_state.key().as_ref()],
+        bump
+    )]
+    pub sol_treasury: Option<SystemAccount<'info>>,
+
+    /// CHECK: PDA authority for the program's token accounts
+    #[account(
+        seeds = [b"usdc_authority", game_state.key().as_ref()],
+        bump
+    )]
+    pub program_usdc_authority: UncheckedAccount<'info>,
+
+    /// Registry entry when the batch was paid with a mint other than game_state.mint_usdc
+    #[account(
+        mut,
+        seeds = [b"accepted_mint", payment_mint.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub accepted_mint: Option<Box<Account<'info, AcceptedMint>>>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
-// Function to transfer NFT from user to program (stub)
-fn transfer_nft_to_program(
-    user: &Pubkey,
-    nft_mint: &Pubkey,
-) -> Result<()> {
-    // TODO: Implement NFT transfer
-    // This will require:
-    // 1. User's token account for the NFT
-    // 2. Program's token account for the NFT (PDA)
-    // 3. Token program
-    // 4. SPL token transfer instruction
-    // 5. Verify NFT ownership
-    
-    msg!("NFT transfer to program pending implementation: mint {}", nft_mint);
-    msg!("Would transfer NFT from user {} to program", user);
+#[derive(Accounts)]
+pub struct SetRefundWindow<'info> {
+    #[account(
+        mut,
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
     
-    Ok(())
+    #[account(mut)]
+    pub user: Signer<'info>,
 }
 
-// Function to transfer NFT from program back to user (stub)
-fn transfer_nft_to_user(
-    nft_mint: &Pubkey,
-    owner: &Pubkey,
-) -> Result<()> {
-    // TODO: Implement NFT transfer
-    // This will require:
-    // 1. Program's token account for the NFT (PDA)
-    // 2. User's token account for the NFT
-    // 3. Token program
-    // 4. SPL token transfer instruction with PDA signing
-    
-    msg!("NFT transfer to user pending implementation: mint {}", nft_mint);
-    msg!("Would transfer NFT from program to user {}", owner);
-    
-    Ok(())
+#[derive(Accounts)]
+#[instruction(code_hash: [u8; 32])]
+pub struct CreateCoupon<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        init,
+        payer = user,
+        space = Coupon::SPACE,
+        seeds = [b"coupon", code_hash.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub coupon: Account<'info, Coupon>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(code_hash: [u8; 32])]
+pub struct UpdateCoupon<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [b"coupon", code_hash.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub coupon: Account<'info, Coupon>,
 
+    pub user: Signer<'info>,
+}
 
 #[derive(Accounts)]
-pub struct Withdraw<'info> {
+pub struct AddAcceptedMint<'info> {
     #[account(
         mut,
         seeds = [b"game_state", crate::ID.as_ref()],
         bump
     )]
     pub game_state: Account<'info, GameState>,
-    
-    /// Program's USDC token account (treasury)
+
+    /// SPL Token or Token-2022 mint (PYUSD)
+    pub mint: InterfaceAccount<'info, token_interface::Mint>,
+
     #[account(
-        mut,
-        constraint = program_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
-        constraint = program_usdc_account.owner == program_usdc_authority.key() @ SportsError::InvalidTokenAccount,
+        init,
+        payer = user,
+        space = AcceptedMint::SPACE,
+        seeds = [b"accepted_mint", mint.key().as_ref(), game_state.key().as_ref()],
+        bump
     )]
-    pub program_usdc_account: Account<'info, TokenAccount>,
-    
-    /// PDA authority for program's USDC account
-    /// CHECK: This is validated through constraint and used as authority
+    pub accepted_mint: Account<'info, AcceptedMint>,
+
+    /// Bóveda del mint, propiedad del PDA usdc_authority
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = program_usdc_authority,
+        associated_token::token_program = token_program,
+    )]
+    pub vault: InterfaceAccount<'info, token_interface::TokenAccount>,
+
+    /// CHECK: PDA authority for the program's token accounts
     #[account(
         seeds = [b"usdc_authority", game_state.key().as_ref()],
         bump
     )]
     pub program_usdc_authority: UncheckedAccount<'info>,
-    
-    /// User's USDC token account (destination)
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    /// Program that owns `mint`
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct UpdateAcceptedMint<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
     #[account(
         mut,
-        constraint = user_usdc_account.mint == game_state.mint_usdc @ SportsError::InvalidUsdcMint,
-        constraint = user_usdc_account.owner == user.key() @ SportsError::InvalidTokenAccount,
+        seeds = [b"accepted_mint", mint.as_ref(), game_state.key().as_ref()],
+        bump
     )]
-    pub user_usdc_account: Account<'info, TokenAccount>,
-    
+    pub accepted_mint: Account<'info, AcceptedMint>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(sponsor: Pubkey)]
+pub struct AddSponsor<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        init,
+        payer = user,
+        space = Sponsor::SPACE,
+        seeds = [b"sponsor", sponsor.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub sponsor_account: Account<'info, Sponsor>,
+
     #[account(mut)]
     pub user: Signer<'info>,
-    
-    /// Clock for timestamps
-    pub clock: Sysvar<'info, Clock>,
-    
-    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
 }
 
-// Function to transfer USDC to user (implementación real)
-fn transfer_usdc_to_user(
-    ctx: &Context<Withdraw>,
-    user: &Pubkey,
-    amount: u64,
-) -> Result<()> {
-    // Validar que el amount sea mayor que 0
-    require!(
-        amount > 0,
-        SportsError::InvalidAmount
-    );
-    
-    // Validar que el usuario coincida con el de la cuenta destino
-    require!(
-        ctx.accounts.user_usdc_account.owner == *user,
-        SportsError::InvalidTokenAccount
-    );
-    
-    // Validar que tenemos suficiente balance en la cuenta del programa
-    require!(
-        ctx.accounts.program_usdc_account.amount >= amount,
-        SportsError::InsufficientFunds
-    );
-    
-    // Crear el contexto de transferencia
-    let transfer_accounts = Transfer {
-        from: ctx.accounts.program_usdc_account.to_account_info(),
-        to: ctx.accounts.user_usdc_account.to_account_info(),
-        authority: ctx.accounts.program_usdc_authority.to_account_info(),
-    };
-    
-    // Seeds para firmar con el PDA
-    let game_state_key = ctx.accounts.game_state.key();
-    let authority_seeds = &[
-        b"usdc_authority",
-        game_state_key.as_ref(),
-        &[ctx.bumps.program_usdc_authority],
-    ];
-    let signer_seeds = &[&authority_seeds[..]];
-    
-    // Crear CpiContext con las seeds para firmar
-    let cpi_ctx = CpiContext::new_with_signer(
-        ctx.accounts.token_program.to_account_info(),
-        transfer_accounts,
-        signer_seeds,
-    );
-    
-    // Ejecutar la transferencia
-    token::transfer(cpi_ctx, amount)?;
-    
-    msg!("Transferred {} USDC to user {}", 
-        amount as f64 / 1_000_000.0,
-        user
-    );
-    
-    Ok(())
+#[derive(Accounts)]
+#[instruction(sponsor: Pubkey)]
+pub struct UpdateSponsor<'info> {
+    #[account(
+        seeds = [b"game_state", crate::ID.as_ref()],
+        bump
+    )]
+    pub game_state: Account<'info, GameState>,
+
+    #[account(
+        mut,
+        seeds = [b"sponsor", sponsor.as_ref(), game_state.key().as_ref()],
+        bump
+    )]
+    pub sponsor_account: Account<'info, Sponsor>,
+
+    pub user: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -2692,23 +10921,58 @@ pub struct PauseContract<'info> {
 
 
 
+// Cliente (off-chain): arma una compra patrocinada. El sponsor firma primero como fee payer y la
+// transacción viaja al wallet del comprador, que solo añade su firma para el pago en USDC.
+#[cfg(not(target_os = "solana"))]
+pub mod sponsored_purchase {
+    use super::*;
+    use anchor_lang::solana_program::{hash::Hash, instruction::Instruction, message::Message};
+    use anchor_lang::InstructionData;
+    use solana_signer::Signer;
+    use solana_transaction::Transaction;
+
+    // buy_team with accounts.fee_payer (the sponsor) paying the transaction fee
+    pub fn build_message(
+        accounts: crate::accounts::BuyTeam,
+        args: crate::instruction::BuyTeam,
+        recent_blockhash: Hash,
+    ) -> Message {
+        let fee_payer = accounts.fee_payer;
+        let instruction = Instruction {
+            program_id: crate::ID,
+            accounts: accounts.to_account_metas(None),
+            data: args.data(),
+        };
+        Message::new_with_blockhash(&[instruction], Some(&fee_payer), &recent_blockhash)
+    }
+
+    // Serialized transaction signed by the sponsor only; the buyer's signature is added by its wallet
+    pub fn partially_sign(message: Message, sponsor: &impl Signer) -> Result<Vec<u8>> {
+        let recent_blockhash = message.recent_blockhash;
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction
+            .try_partial_sign(&[sponsor], recent_blockhash)
+            .map_err(|_| SportsError::SponsorNotAllowed)?;
+        Ok(bincode::serialize(&transaction).map_err(|_| SportsError::SponsorNotAllowed)?)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    
-    #[test]
-    fn test_is_authorized_with_owner() {
-        let owner = Pubkey::new_unique();
-        let game_state = GameState {
-            owner,
+
+    // Default GameState for unit tests; tests override fields with struct-update syntax
+    fn test_game_state() -> GameState {
+        GameState {
+            owner: Pubkey::new_unique(),
             staff: Vec::new(),
             players: Vec::new(),
-            next_player_id: 0,
+            next_player_id: 1,
             mint_usdc: Pubkey::default(),
-            team_price_a: 0,
-            team_price_b: 0,
-            team_price_c: 0,
-            next_team_id: 0,
+            team_price_a: 10_000_000,
+            team_price_b: 15_000_000,
+            team_price_c: 20_000_000,
+            next_team_id: 1,
             next_reward_id: 0,
             current_report_id: 0,
             current_report_start: 0,
@@ -2721,6 +10985,39 @@ mod tests {
             nft_update_authority: Pubkey::default(),
             nft_image_url: "".to_string(),
             time_lock: 24 * 60 * 60,
+            pity_threshold: 0,
+            reveal_window_slots: 150,
+            expired_commit_policy: ExpiredCommitPolicy::AutoReveal,
+            entropy_source: EntropySourceKind::Chainlink,
+            entropy_oracle: Pubkey::default(),
+            presale_merkle_root: [0; 32],
+            presale_end: 0,
+            presale_wallet_cap: 0,
+            current_report_sol_revenue: 0,
+            current_report_coupon_redemptions: 0,
+            current_report_coupon_discount: 0,
+            referral_commission_bps: 0,
+            referral_commissions_owed: 0,
+            refund_window_secs: 0,
+            sol_usd_feed: Pubkey::default(),
+            oracle_program: Pubkey::default(),
+            max_price_staleness_secs: 0,
+            withdrawal_threshold: 2,
+            next_withdrawal_proposal_id: 1,
+            treasury_received: 0,
+            treasury_withdrawn: 0,
+            treasury_reserved: 0,
+            accepted_mint_count: 0,
+            treasury_ledger_seeded: false,
+        }
+    }
+    
+    #[test]
+    fn test_is_authorized_with_owner() {
+        let owner = Pubkey::new_unique();
+        let game_state = GameState {
+            owner,
+            ..test_game_state()
         };
         
         assert!(is_authorized(&owner, &game_state));
@@ -2733,25 +11030,7 @@ mod tests {
         let game_state = GameState {
             owner,
             staff: vec![staff_member],
-            players: Vec::new(),
-            next_player_id: 1,
-            mint_usdc: Pubkey::default(),
-            team_price_a: 0,
-            team_price_b: 0,
-            team_price_c: 0,
-            next_team_id: 0,
-            next_reward_id: 0,
-            current_report_id: 0,
-            current_report_start: 0,
-            is_report_open: false,
-            current_report_revenue: 0,
-            current_report_teams: 0,
-            current_report_tokens: 0,
-            is_paused: false,
-            pending_withdrawal: None,
-            nft_update_authority: Pubkey::default(),
-            nft_image_url: "".to_string(),
-            time_lock: 24 * 60 * 60,
+            ..test_game_state()
         };
 
         assert!(is_authorized(&staff_member, &game_state));
@@ -2765,25 +11044,7 @@ mod tests {
         let game_state = GameState {
             owner,
             staff: vec![staff_member],
-            players: Vec::new(),
-            next_player_id: 1,
-            mint_usdc: Pubkey::default(),
-            team_price_a: 0,
-            team_price_b: 0,
-            team_price_c: 0,
-            next_team_id: 0,
-            next_reward_id: 0,
-            current_report_id: 0,
-            current_report_start: 0,
-            is_report_open: false,
-            current_report_revenue: 0,
-            current_report_teams: 0,
-            current_report_tokens: 0,
-            is_paused: false,
-            pending_withdrawal: None,
-            nft_update_authority: Pubkey::default(),
-            nft_image_url: "".to_string(),
-            time_lock: 24 * 60 * 60,
+            ..test_game_state()
         };
 
         assert!(!is_authorized(&unauthorized, &game_state));
@@ -2797,17 +11058,17 @@ mod tests {
     #[test]
     fn test_game_state_space_calculation() {
         // Verify the space calculation is correct
-        let expected = 8 + 32 + 4 + (3 * 32) + 4 + (1300 * 7) + 2 + 32 + 24 + 8 + 8 + 8 + 8 + 1 + 8 + 4 + 4 + 1 + 1 + WithdrawalRequest::SIZE + 32 + 1 + 1 + 100;
+        let expected = 8 + 32 + 4 + (3 * 32) + 4 + (1300 * 7) + 2 + 32 + 24 + 8 + 8 + 8 + 8 + 1 + 8 + 4 + 4 + 1 + 1 + WithdrawalRequest::SIZE + 32 + 1 + 1 + 100 + 8 + 4 + 8 + 1 + 1 + 32 + 32 + 8 + 4 + 8 + 4 + 8 + 2 + 8 + 8 + 32 + 32 + 8 + 1 + 8 + 8 + 8 + 8 + 1 + 1;
         assert_eq!(GameState::SPACE, expected);
-        assert_eq!(GameState::SPACE, 9664);
+        assert_eq!(GameState::SPACE, 9778);
     }
     
     #[test]
     fn test_player_space_calculation() {
         // Verify the space calculation is correct
-        let expected = 8 + 2 + 2 + 1 + 4 + 4 + 4 + 100;
+        let expected = 8 + 2 + 2 + 1 + 4 + 4 + 4 + 100 + 100 + 100 + 100 + 4;
         assert_eq!(Player::SPACE, expected);
-        assert_eq!(Player::SPACE, 125);
+        assert_eq!(Player::SPACE, 429);
     }
     
     #[test]
@@ -2872,6 +11133,7 @@ mod tests {
             name: "Player 1".to_string(),
             discipline: "Discipline 1".to_string(),
             country: "Country 1".to_string(),
+            version: 0,
         };
         
         let player_data = PlayerData {
@@ -2912,6 +11174,7 @@ mod tests {
             name: "Player 1".to_string(),
             discipline: "Tennis".to_string(),
             country: "USA".to_string(),
+            version: 0,
         };
         
         let result = update_player_data(
@@ -2948,6 +11211,7 @@ mod tests {
             name: "Player 1".to_string(),
             discipline: "Tennis".to_string(),
             country: "USA".to_string(),
+            version: 0,
         };
         
         // Try to set total_tokens to less than tokens_sold
@@ -2977,6 +11241,7 @@ mod tests {
             name: "Player 1".to_string(),
             discipline: "Tennis".to_string(),
             country: "USA".to_string(),
+            version: 0,
         };
         
         let update_data = PlayerUpdateData {
@@ -2988,19 +11253,53 @@ mod tests {
             discipline: Some("Tennis".to_string()),
             country: Some("USA".to_string()),
         };
-        
-        apply_player_updates(&mut player, &update_data);
-        
-        // Check that only specified fields were updated
-        assert_eq!(player.id, 1); // Should not change
-        assert_eq!(player.provider_id, 2000); // Updated
-        assert_eq!(player.category, PlayerCategory::Silver); // Updated
-        assert_eq!(player.total_tokens, 150); // Updated
-        assert_eq!(player.tokens_sold, 50); // Should not change
-        assert_eq!(player.metadata_uri, Some("https://new.com/metadata.json".to_string())); // Updated
-        assert_eq!(player.name, "Player 1".to_string()); // Should not change
-        assert_eq!(player.discipline, "Tennis".to_string()); // Should not change
-        assert_eq!(player.country, "USA".to_string()); // Should not change
+        
+        apply_player_updates(&mut player, &update_data);
+        
+        // Check that only specified fields were updated
+        assert_eq!(player.id, 1); // Should not change
+        assert_eq!(player.provider_id, 2000); // Updated
+        assert_eq!(player.category, PlayerCategory::Silver); // Updated
+        assert_eq!(player.total_tokens, 150); // Updated
+        assert_eq!(player.tokens_sold, 50); // Should not change
+        assert_eq!(player.metadata_uri, Some("https://new.com/metadata.json".to_string())); // Updated
+        assert_eq!(player.name, "Player 1".to_string()); // Should not change
+        assert_eq!(player.discipline, "Tennis".to_string()); // Should not change
+        assert_eq!(player.country, "USA".to_string()); // Should not change
+    }
+    
+    #[test]
+    fn test_diff_player_updates() {
+        let player = Player {
+            id: 1,
+            provider_id: 1000,
+            category: PlayerCategory::Bronze,
+            total_tokens: 100,
+            tokens_sold: 50,
+            metadata_uri: None,
+            name: "Player 1".to_string(),
+            discipline: "Tennis".to_string(),
+            country: "USA".to_string(),
+            version: 3,
+        };
+
+        // Fields set to their current value are not reported
+        let update_data = PlayerUpdateData {
+            provider_id: Some(1000),
+            category: Some(PlayerCategory::Gold),
+            total_tokens: Some(150),
+            metadata_uri: Some(Some("https://new.com/metadata.json".to_string())),
+            name: Some("Player 1".to_string()),
+            discipline: None,
+            country: None,
+        };
+
+        let changes = diff_player_updates(&player, &update_data);
+        assert_eq!(changes, vec![
+            PlayerFieldChange::Category { old: PlayerCategory::Bronze, new: PlayerCategory::Gold },
+            PlayerFieldChange::TotalTokens { old: 100, new: 150 },
+            PlayerFieldChange::MetadataUri { old: None, new: Some("https://new.com/metadata.json".to_string()) },
+        ]);
     }
     
     #[test]
@@ -3015,6 +11314,7 @@ mod tests {
             name: "Player 1".to_string(),
             discipline: "Tennis".to_string(),
             country: "USA".to_string(),
+            version: 0,
         };
         
         // Only update category
@@ -3044,111 +11344,72 @@ mod tests {
     
     #[test]
     fn test_team_package_price() {
-        let game_state = GameState {
-            owner: Pubkey::new_unique(),
-            staff: Vec::new(),
-            players: Vec::new(),
-            next_player_id: 1,
-            mint_usdc: Pubkey::default(),
-            team_price_a: 10_000_000,  // $10
-            team_price_b: 15_000_000,  // $15
-            team_price_c: 20_000_000,  // $20
-            next_team_id: 1,
-            next_reward_id: 0,
-            current_report_id: 0,
-            current_report_start: 0,
-            is_report_open: false,
-            current_report_revenue: 0,
-            current_report_teams: 0,
-            current_report_tokens: 0,
-            is_paused: false,
-            pending_withdrawal: None,
-            nft_update_authority: Pubkey::default(),
-            nft_image_url: "".to_string(),
-            time_lock: 24 * 60 * 60,
-        };
-
-        assert_eq!(TeamPackage::A.price_usdc(&game_state), 10_000_000); // $10
-        assert_eq!(TeamPackage::B.price_usdc(&game_state), 15_000_000); // $15
-        assert_eq!(TeamPackage::C.price_usdc(&game_state), 20_000_000); // $20
+        let game_state = test_game_state();
+
+        let [config_a, config_b, config_c] = legacy_package_configs(&game_state);
+        assert_eq!(config_a.price_usdc, 10_000_000); // $10
+        assert_eq!(config_b.price_usdc, 15_000_000); // $15
+        assert_eq!(config_c.price_usdc, 20_000_000); // $20
+        assert!(validate_package_config(&config_a).is_ok());
+        assert!(validate_package_config(&config_b).is_ok());
+        assert!(validate_package_config(&config_c).is_ok());
     }
 
     #[test]
     fn test_team_package_total_players() {
-        let game_state = GameState {
-            owner: Pubkey::new_unique(),
-            staff: Vec::new(),
-            players: Vec::new(),
-            next_player_id: 1,
-            mint_usdc: Pubkey::default(),
-            team_price_a: 0,
-            team_price_b: 0,
-            team_price_c: 0,
-            next_team_id: 1,
-            next_reward_id: 0,
-            current_report_id: 0,
-            current_report_start: 0,
-            is_report_open: false,
-            current_report_revenue: 0,
-            current_report_teams: 0,
-            current_report_tokens: 0,
-            is_paused: false,
-            pending_withdrawal: None,
-            nft_update_authority: Pubkey::default(),
-            nft_image_url: "".to_string(),
-            time_lock: 24 * 60 * 60,
-        };
-
-        assert_eq!(TeamPackage::A.total_players(), 5);
-        assert_eq!(TeamPackage::B.total_players(), 5);
-        assert_eq!(TeamPackage::C.total_players(), 5);
+        let game_state = test_game_state();
+
+        let [config_a, config_b, config_c] = legacy_package_configs(&game_state);
+        assert_eq!(config_a.slot_count, 5);
+        assert_eq!(config_b.slot_count, 5);
+        assert_eq!(config_c.slot_count, 5);
+
+        // A: 5 random, B: 4 random + 1 Silver/Gold, C: 3 random + 2 Silver/Gold
+        assert_eq!((config_a.general_slots(), config_a.reserved_slots()), (5, 0));
+        assert_eq!((config_b.general_slots(), config_b.reserved_slots()), (4, 1));
+        assert_eq!((config_c.general_slots(), config_c.reserved_slots()), (3, 2));
+        assert!(config_b.category_minimums[0].matches(PlayerCategory::Gold));
+        assert!(config_b.category_minimums[0].matches(PlayerCategory::Silver));
+        assert!(!config_b.category_minimums[0].matches(PlayerCategory::Bronze));
+
+        assert_eq!(TeamPackage::from_package_id(1), TeamPackage::B);
+        assert_eq!(TeamPackage::from_package_id(7), TeamPackage::Custom(7));
+        assert_eq!(TeamPackage::Custom(7).package_id(), 7);
     }
     
     #[test]
     fn test_generate_entropy() {
-        let buyer = Pubkey::new_unique();
-        let clock = Clock {
+        let seed = EntropySeed {
+            buyer: Pubkey::new_unique(),
             slot: 123,
-            epoch_start_timestamp: 0,
-            epoch: 0,
-            leader_schedule_epoch: 123,
             unix_timestamp: 0,
-        };
-        let game_state = GameState {
-            owner: Pubkey::new_unique(),
-            staff: Vec::new(),
-            players: Vec::new(),
-            next_player_id: 1,
-            mint_usdc: Pubkey::default(),
-            team_price_a: 10_000_000,
-            team_price_b: 15_000_000,
-            team_price_c: 20_000_000,
             next_team_id: 1,
-            next_reward_id: 0,
-            current_report_id: 0,
-            current_report_start: 0,
-            is_report_open: false,
-            current_report_revenue: 0,
-            current_report_teams: 0,
-            current_report_tokens: 0,
-            is_paused: false,
-            pending_withdrawal: None,
-            nft_update_authority: Pubkey::default(),
-            nft_image_url: "".to_string(),
-            time_lock: 24 * 60 * 60,
         };
 
-        let entropy = generate_entropy(&buyer, &clock);
+        let entropy = seed.mix(b"source");
         assert_eq!(entropy.len(), 32);
         
         // Verificar que la entropía es determinista
-        let entropy2 = generate_entropy(&buyer, &clock);
-        assert_eq!(entropy, entropy2);
+        assert_eq!(entropy, seed.mix(b"source"));
         
-        // Verificar que diferentes compradores generan diferentes entropías
-        let buyer2 = Pubkey::new_unique();
-        let entropy3 = generate_entropy(&buyer2, &clock);
-        assert_ne!(entropy, entropy3);
+        // Verificar que diferentes compradores y fuentes generan diferentes entropías
+        let seed2 = EntropySeed { buyer: Pubkey::new_unique(), ..seed };
+        assert_ne!(entropy, seed2.mix(b"source"));
+        assert_ne!(entropy, seed.mix(b"other source"));
+
+        // Oracle randomness: discriminator, randomness, fulfilled slot
+        let mut data = vec![0u8; 8];
+        data.extend_from_slice(&[7u8; 32]);
+        data.extend_from_slice(&100u64.to_le_bytes());
+        assert_eq!(parse_oracle_randomness(&data, 120).unwrap(), [7u8; 32]);
+        assert!(parse_oracle_randomness(&data, 100 + OracleEntropy::MAX_STALENESS_SLOTS + 1).is_err());
+        assert!(parse_oracle_randomness(&data, 99).is_err());
+        assert!(parse_oracle_randomness(&data[..40], 120).is_err());
+
+        assert!(EntropySourceKind::Chainlink.is_available());
+        assert_eq!(EntropySourceKind::Deterministic.is_available(), cfg!(feature = "test-entropy"));
+        assert_eq!(EntropySourceKind::SlotHashes.is_available(), cfg!(feature = "test-entropy"));
+        assert_eq!(EntropySourceKind::Oracle.is_available(), cfg!(feature = "test-entropy"));
     }
     
     #[test]
@@ -3163,29 +11424,7 @@ mod tests {
             epoch_start_timestamp: 1234567800,
             leader_schedule_epoch: 123,
         };
-        let game_state = GameState {
-            owner: Pubkey::new_unique(),
-            staff: Vec::new(),
-            players: Vec::new(),
-            next_player_id: 1,
-            mint_usdc: Pubkey::default(),
-            team_price_a: 0,
-            team_price_b: 0,
-            team_price_c: 0,
-            next_team_id: 1,
-            next_reward_id: 0,
-            current_report_id: 0,
-            current_report_start: 0,
-            is_report_open: false,
-            current_report_revenue: 0,
-            current_report_teams: 0,
-            current_report_tokens: 0,
-            is_paused: false,
-            pending_withdrawal: None,
-            nft_update_authority: Pubkey::default(),
-            nft_image_url: "".to_string(),
-            time_lock: 24 * 60 * 60,
-        };
+        let game_state = test_game_state();
         
         // Test TeamPurchase creation directly
         let team_purchase = TeamPurchase {
@@ -3195,6 +11434,8 @@ mod tests {
             purchase_timestamp: clock.unix_timestamp,
             purchase_slot: clock.slot,
             price_paid_usdc: 20_000_000,
+            team_id: 1,
+            gold_guaranteed: false,
         };
         
         assert_eq!(team_purchase.buyer, buyer);
@@ -3257,6 +11498,7 @@ mod tests {
             name: "Player 1".to_string(),
             discipline: "Tennis".to_string(),
             country: "USA".to_string(),
+            version: 0,
         };
         
         let player_summary = PlayerSummary {
@@ -3279,6 +11521,7 @@ mod tests {
             name: "Player 2".to_string(),
             discipline: "Golf".to_string(),
             country: "Spain".to_string(),
+            version: 0,
         };
         
         let player_summary_updated = PlayerSummary {
@@ -3307,6 +11550,7 @@ mod tests {
             name: "Player 3".to_string(),
             discipline: "Discipline 3".to_string(),
             country: "France".to_string(),
+            version: 0,
         };
         
         let player_summary = PlayerSummary {
@@ -3325,6 +11569,973 @@ mod tests {
         assert_eq!(player.tokens_sold, 500);
     }
     
+    #[test]
+    fn test_audit_and_repair_player() {
+        let mut player = Player {
+            id: 7,
+            provider_id: 1007,
+            category: PlayerCategory::Gold,
+            total_tokens: 100,
+            tokens_sold: 10, // Outdated value
+            metadata_uri: None,
+            name: "Player 7".to_string(),
+            discipline: "Tennis".to_string(),
+            country: "Chile".to_string(),
+            version: 0,
+        };
+        let mut players = vec![PlayerSummary {
+            id: 7,
+            category: PlayerCategory::Silver,
+            available_tokens: 60,
+        }];
+
+        let mismatches = audit_player(&player, &players);
+        let kinds: Vec<AuditMismatchKind> = mismatches.iter().map(|m| m.kind).collect();
+        assert_eq!(kinds, vec![AuditMismatchKind::AvailableTokensMismatch, AuditMismatchKind::CategoryMismatch]);
+
+        repair_player(&mut player, &mut players).unwrap();
+        assert_eq!(player.tokens_sold, 40);
+        assert_eq!(players[0].category, PlayerCategory::Gold);
+        assert!(audit_player(&player, &players).is_empty());
+
+        // A PDA without summary gets one rebuilt from its own counters
+        let mut players = Vec::new();
+        let kinds: Vec<AuditMismatchKind> = audit_player(&player, &players).iter().map(|m| m.kind).collect();
+        assert_eq!(kinds, vec![AuditMismatchKind::MissingSummary]);
+        repair_player(&mut player, &mut players).unwrap();
+        assert_eq!(players[0].available_tokens, 60);
+        assert!(audit_player(&player, &players).is_empty());
+    }
+    
+    #[test]
+    fn test_slot_hash_at_or_after() {
+        // Newest first, slot 104 was skipped
+        let entries: [(u64, u8); 4] = [(106, 6), (105, 5), (103, 3), (102, 2)];
+        let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+        for (slot, fill) in entries.iter() {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[*fill; 32]);
+        }
+
+        assert_eq!(slot_hash_at_or_after(&data, 103), Some([3; 32]));
+        assert_eq!(slot_hash_at_or_after(&data, 104), Some([5; 32]));
+        assert_eq!(slot_hash_at_or_after(&data, 102), Some([2; 32]));
+        // Slot 100 aged out: the oldest remaining hash must not stand in for it
+        assert_eq!(slot_hash_at_or_after(&data, 100), None);
+        assert_eq!(slot_hash_at_or_after(&data, 107), None);
+        assert_eq!(slot_hash_at_or_after(&[], 100), None);
+    }
+
+    #[test]
+    fn test_pity_counter() {
+        let mut pack_history = PackHistory {
+            user: Pubkey::new_unique(),
+            packs_without_gold: 0,
+            total_packs: 0,
+            gold_guarantees: 0,
+        };
+
+        for _ in 0..3 {
+            pack_history.record_pack(false, false).unwrap();
+        }
+        assert!(!pack_history.is_pity_due(0)); // Disabled
+        assert!(!pack_history.is_pity_due(4));
+        assert!(pack_history.is_pity_due(3));
+
+        pack_history.record_pack(true, true).unwrap();
+        assert_eq!(pack_history.packs_without_gold, 0);
+        assert_eq!(pack_history.gold_guarantees, 1);
+        assert_eq!(pack_history.total_packs, 4);
+        assert!(!pack_history.is_pity_due(3));
+    }
+
+    #[test]
+    fn test_with_forced_gold() {
+        let game_state = test_game_state();
+        let [_, config_b, _] = legacy_package_configs(&game_state);
+
+        // B: one general slot becomes Gold-only, slot count unchanged
+        let forced = with_forced_gold(&config_b).unwrap();
+        assert_eq!(forced.slot_count, 5);
+        assert_eq!((forced.general_slots(), forced.reserved_slots()), (3, 2));
+        assert!(validate_package_config(&forced).is_ok());
+
+        // All slots premium: one premium slot is narrowed to Gold
+        let mut all_premium = config_b.clone();
+        all_premium.category_minimums[0].count = 5;
+        let forced = with_forced_gold(&all_premium).unwrap();
+        assert_eq!(forced.category_minimums[0].count, 4);
+        assert_eq!(forced.category_minimums[1].category_mask, 1 << PlayerCategory::Gold as u8);
+        assert_eq!(forced.reserved_slots(), 5);
+    }
+
+    // ============ MONTE CARLO DE SELECCIÓN ============
+    // `cargo test --release -p sports monte_carlo_selection -- --ignored --nocapture`
+    // SELECTION_DRAWS (default 1_000_000 per package) and SELECTION_POOL_CSV (default data/players.csv)
+
+    // Pool shaped like data/players.csv: token_id, ..., Stock ("1.000" = 1000), Rarity
+    fn load_selection_pool(csv: &str) -> Vec<PlayerSummary> {
+        csv.lines()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let cols: Vec<&str> = line.split(',').collect();
+                PlayerSummary {
+                    id: cols[0].trim().parse().unwrap(),
+                    available_tokens: cols[4].trim().replace('.', "").parse().unwrap(),
+                    category: match cols[5].trim() {
+                        "Gold" => PlayerCategory::Gold,
+                        "Silver" => PlayerCategory::Silver,
+                        _ => PlayerCategory::Bronze,
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn selection_test_packages() -> Vec<PackageConfig> {
+        let package = |name: &str, premium: u8| PackageConfig {
+            package_id: 0,
+            name: name.to_string(),
+            slot_count: 5,
+            category_minimums: if premium > 0 {
+                vec![CategoryMinimum { category_mask: CategoryMinimum::PREMIUM_MASK, count: premium }]
+            } else {
+                Vec::new()
+            },
+            price_usdc: 1,
+            is_active: true,
+            max_supply: 0,
+            sold: 0,
+            sale_start: 0,
+            sale_end: 0,
+            presale_price_usdc: 0,
+            pricing_curve: None,
+            scheduled_price: None,
+        };
+        vec![package("A", 0), package("B", 1), package("C", 2)]
+    }
+
+    // Runs `draws` packs and checks, for every slot and player, the observed pick count against
+    // the exact conditional probability available_tokens / remaining weight of that slot.
+    // Slots after the first are bucketed by the decile of slot 0's pick, so any correlation
+    // between slot streams shows up as a deviation. Returns the worst z-score.
+    fn run_selection_monte_carlo(pool: &[PlayerSummary], config: &PackageConfig, draws: u64) -> f64 {
+        use anchor_lang::solana_program::keccak;
+        use std::collections::HashMap;
+
+        const BUCKETS: usize = 10;
+        let available: Vec<(usize, &PlayerSummary)> = pool.iter().enumerate()
+            .filter(|(_, p)| p.available_tokens > 0)
+            .collect();
+        let general_slots = config.general_slots();
+        let slot_count = config.slot_count as usize;
+
+        // (slot, bucket, player) -> (observed, expected, variance)
+        let mut cells: HashMap<(usize, usize, usize), (f64, f64, f64)> = HashMap::new();
+
+        for draw in 0..draws {
+            let entropy = keccak::hash(&draw.to_le_bytes()).0;
+            let picks = select_team_players(&available, config, &entropy).unwrap();
+            assert_eq!(picks.len(), slot_count);
+
+            // Sin repetidos y mínimos por categoría garantizados
+            let mut unique = picks.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), slot_count, "duplicated player in draw {}", draw);
+            for minimum in config.category_minimums.iter() {
+                let matching = picks.iter().filter(|&&i| minimum.matches(pool[i].category)).count();
+                assert!(matching >= minimum.count as usize, "minimum not met in draw {}", draw);
+            }
+
+            let mut bucket = 0;
+            let mut slot_minimum = config.category_minimums.iter()
+                .flat_map(|m| std::iter::repeat(m).take(m.count as usize));
+            for (slot, &pick) in picks.iter().enumerate() {
+                let minimum = if slot < general_slots { None } else { slot_minimum.next() };
+                let candidates: Vec<usize> = available.iter()
+                    .map(|(i, _)| *i)
+                    .filter(|i| !picks[..slot].contains(i))
+                    .filter(|&i| minimum.map_or(true, |m| m.matches(pool[i].category)))
+                    .collect();
+                let total: f64 = candidates.iter().map(|&i| pool[i].available_tokens as f64).sum();
+                assert!(candidates.contains(&pick), "pick outside slot candidates in draw {}", draw);
+
+                if slot == 0 {
+                    let before: f64 = candidates.iter()
+                        .take_while(|&&i| i != pick)
+                        .map(|&i| pool[i].available_tokens as f64)
+                        .sum();
+                    let quantile = (before + pool[pick].available_tokens as f64 / 2.0) / total;
+                    bucket = ((quantile * BUCKETS as f64) as usize).min(BUCKETS - 1);
+                }
+                let cell_bucket = if slot == 0 { 0 } else { bucket };
+                for &i in candidates.iter() {
+                    let p = pool[i].available_tokens as f64 / total;
+                    let cell = cells.entry((slot, cell_bucket, i)).or_insert((0.0, 0.0, 0.0));
+                    cell.1 += p;
+                    cell.2 += p * (1.0 - p);
+                    if i == pick {
+                        cell.0 += 1.0;
+                    }
+                }
+            }
+        }
+
+        cells.values()
+            .filter(|(_, expected, _)| *expected >= 20.0)
+            .map(|(observed, expected, variance)| ((observed - expected) / variance.sqrt()).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_select_team_players_streams() {
+        let entropy = [9u8; 32];
+        assert_ne!(SlotStream::new(&entropy, 0).state, SlotStream::new(&entropy, 1).state);
+
+        // Rejection sampling stays below the bound
+        let mut stream = SlotStream::new(&entropy, 0);
+        assert!((0..100).all(|_| stream.next_below(7) < 7));
+
+        // Quick pass of the Monte Carlo harness over data/players.csv
+        let pool = load_selection_pool(include_str!("../../../data/players.csv"));
+        assert_eq!(pool.len(), 82);
+        for config in selection_test_packages() {
+            let worst_z = run_selection_monte_carlo(&pool, &config, 1_000);
+            assert!(worst_z < 5.5, "package {}: z = {:.2}", config.name, worst_z);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn monte_carlo_selection() {
+        let draws: u64 = std::env::var("SELECTION_DRAWS")
+            .map(|v| v.parse().unwrap())
+            .unwrap_or(1_000_000);
+        let pool = match std::env::var("SELECTION_POOL_CSV") {
+            Ok(path) => load_selection_pool(&std::fs::read_to_string(path).unwrap()),
+            Err(_) => load_selection_pool(include_str!("../../../data/players.csv")),
+        };
+
+        for config in selection_test_packages() {
+            let worst_z = run_selection_monte_carlo(&pool, &config, draws);
+            assert!(worst_z < 5.5, "package {}: {} draws, worst z-score {:.2}", config.name, draws, worst_z);
+        }
+    }
+
+    #[test]
+    fn test_package_sale_status() {
+        let mut config = selection_test_packages().remove(0);
+        config.price_usdc = 5_000_000;
+        assert_eq!(config.sale_status(100), PackageSaleStatus::Active);
+        assert_eq!(config.remaining_supply(), None);
+        assert!(validate_package_sale(&config, SalePhase::Public, &[], 100, 50).is_ok());
+
+        // Window [1_000, 2_000)
+        config.sale_start = 1_000;
+        config.sale_end = 2_000;
+        assert!(validate_package_config(&config).is_ok());
+        assert_eq!(config.sale_status(999), PackageSaleStatus::NotStarted);
+        assert_eq!(config.sale_status(1_000), PackageSaleStatus::Active);
+        assert_eq!(config.sale_status(2_000), PackageSaleStatus::Ended);
+        assert_eq!(validate_package_sale(&config, SalePhase::Public, &[], 999, 1).unwrap_err(), SportsError::SaleNotStarted.into());
+        assert_eq!(validate_package_sale(&config, SalePhase::Public, &[], 2_000, 1).unwrap_err(), SportsError::SaleEnded.into());
+
+        // Supply cap, including a multi-pack purchase that would overshoot it
+        config.max_supply = 3;
+        record_package_sale(&mut config, 2).unwrap();
+        assert_eq!(config.remaining_supply(), Some(1));
+        assert_eq!(validate_package_sale(&config, SalePhase::Public, &[], 1_500, 1).unwrap(), 5_000_000);
+        assert_eq!(validate_package_sale(&config, SalePhase::Public, &[], 1_500, 2).unwrap_err(), SportsError::PackageSoldOut.into());
+        record_package_sale(&mut config, 1).unwrap();
+        assert_eq!(config.sale_status(1_500), PackageSaleStatus::SoldOut);
+
+        // Inactive wins over everything else
+        config.is_active = false;
+        assert_eq!(config.sale_status(1_500), PackageSaleStatus::Inactive);
+
+        config.sale_end = config.sale_start;
+        assert!(validate_package_config(&config).is_err());
+    }
+
+    #[test]
+    fn test_presale_allowlist() {
+        use anchor_lang::solana_program::keccak;
+        let node = |a: [u8; 32], b: [u8; 32]| {
+            let (l, r) = if a <= b { (a, b) } else { (b, a) };
+            keccak::hashv(&[&[1u8], &l, &r]).0
+        };
+
+        // Allowlist of 3 wallets: root = node(node(w0, w1), w2)
+        let wallets: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let leaves: Vec<[u8; 32]> = wallets.iter().map(presale_leaf).collect();
+        let root = node(node(leaves[0], leaves[1]), leaves[2]);
+
+        assert!(verify_presale_proof(&[leaves[1], leaves[2]], &root, &wallets[0]));
+        assert!(verify_presale_proof(&[leaves[0], leaves[2]], &root, &wallets[1]));
+        assert!(verify_presale_proof(&[node(leaves[0], leaves[1])], &root, &wallets[2]));
+        assert!(!verify_presale_proof(&[leaves[1], leaves[2]], &root, &Pubkey::new_unique()));
+        assert!(!verify_presale_proof(&[], &root, &wallets[0]));
+        // An inner node is not a valid leaf
+        assert!(!verify_presale_proof(&[leaves[2]], &root, &wallets[0]));
+
+        // Per-wallet cap
+        let mut purchases = PresalePurchases { buyer: wallets[0], purchased: 0 };
+        purchases.record_purchase(2).unwrap();
+        purchases.record_purchase(2).unwrap();
+        assert_eq!(purchases.record_purchase(2).unwrap_err(), SportsError::PresaleWalletCapReached.into());
+        assert!(purchases.record_purchase(0).is_ok());
+
+        // Presale price falls back to the public price when unset
+        let mut config = selection_test_packages().remove(0);
+        config.price_usdc = 10_000_000;
+        assert_eq!(validate_package_sale(&config, SalePhase::Presale, &[], 0, 1).unwrap(), 10_000_000);
+        config.presale_price_usdc = 7_500_000;
+        assert_eq!(validate_package_sale(&config, SalePhase::Presale, &[], 0, 1).unwrap(), 7_500_000);
+        assert_eq!(validate_package_sale(&config, SalePhase::Public, &[], 0, 1).unwrap(), 10_000_000);
+    }
+
+    #[test]
+    fn test_usdc_to_lamports() {
+        // $10 at $150/SOL = 0.0666... SOL, rounded up in favour of the treasury
+        assert_eq!(usdc_to_lamports(10_000_000, 150_000_000).unwrap(), 66_666_667);
+        // $20 at $200/SOL = exactly 0.1 SOL
+        assert_eq!(usdc_to_lamports(20_000_000, 200_000_000).unwrap(), 100_000_000);
+        assert!(usdc_to_lamports(10_000_000, 0).is_err());
+        // Absurdly low SOL price overflows u64 lamports
+        assert!(usdc_to_lamports(20_000_000_000, 1).is_err());
+    }
+
+    #[test]
+    fn test_accepted_mint_accounting() {
+        // $10 in 6, 8 and 2 decimal stablecoins
+        assert_eq!(scale_usdc_amount(10_000_000, 6).unwrap(), 10_000_000);
+        assert_eq!(scale_usdc_amount(10_000_000, 8).unwrap(), 1_000_000_000);
+        assert_eq!(scale_usdc_amount(10_000_001, 2).unwrap(), 1_001);
+        assert!(scale_usdc_amount(10_000_000_000, 18).is_err());
+
+        let mut accepted_mint = AcceptedMint {
+            mint: Pubkey::new_unique(),
+            decimals: 6,
+            vault: Pubkey::new_unique(),
+            enabled: true,
+            report_id: 1,
+            report_revenue: 0,
+            total_received: 0,
+            total_withdrawn: 0,
+            pending_withdrawal: None,
+        };
+        accepted_mint.record_revenue(1, 10_000_000).unwrap();
+        accepted_mint.record_revenue(1, 5_000_000).unwrap();
+        assert_eq!(accepted_mint.report_revenue, 15_000_000);
+        // Closing report 1 reads 15 USDC; report 2 has nothing until its first sale
+        assert_eq!(accepted_mint.revenue_for(1), 15_000_000);
+        assert_eq!(accepted_mint.revenue_for(2), 0);
+
+        // A new report starts its own accumulator; the balance keeps adding up
+        accepted_mint.record_revenue(2, 7_000_000).unwrap();
+        assert_eq!((accepted_mint.report_id, accepted_mint.report_revenue), (2, 7_000_000));
+        accepted_mint.total_withdrawn = 12_000_000;
+        assert_eq!(accepted_mint.available(), 10_000_000);
+    }
+
+    #[test]
+    fn test_coupon_discount() {
+        let mut coupon = Coupon {
+            code_hash: [7; 32],
+            discount: CouponDiscount::Bps(2_500),
+            eligible_packages: vec![1, 2],
+            max_redemptions: 2,
+            redemptions: 0,
+            per_wallet_limit: 1,
+            expires_at: 1_000,
+            enabled: true,
+            created_by: Pubkey::new_unique(),
+        };
+        assert_eq!(coupon.discount_for(1, 20_000_000, 500, 0).unwrap(), 5_000_000);
+        assert_eq!(coupon.discount_for(0, 20_000_000, 500, 0).unwrap_err(), SportsError::CouponNotRedeemable.into());
+        assert_eq!(coupon.discount_for(1, 20_000_000, 1_000, 0).unwrap_err(), SportsError::CouponExpired.into());
+        assert_eq!(coupon.discount_for(1, 20_000_000, 500, 1).unwrap_err(), SportsError::CouponRedemptionLimit.into());
+
+        coupon.redemptions = 2;
+        assert_eq!(coupon.discount_for(2, 20_000_000, 500, 0).unwrap_err(), SportsError::CouponRedemptionLimit.into());
+
+        // Fixed discounts cannot make the pack free
+        coupon.redemptions = 0;
+        coupon.discount = CouponDiscount::Fixed(5_000_000);
+        assert_eq!(coupon.discount_for(2, 20_000_000, 500, 0).unwrap(), 5_000_000);
+        assert!(coupon.discount_for(2, 5_000_000, 500, 0).is_err());
+
+        coupon.enabled = false;
+        assert_eq!(coupon.discount_for(2, 20_000_000, 500, 0).unwrap_err(), SportsError::CouponNotRedeemable.into());
+
+        // The coupon PDA is derived from the code's hash, which is case sensitive
+        assert_eq!(coupon_code_hash("SPRING25"), anchor_lang::solana_program::keccak::hash(b"SPRING25").0);
+        assert_ne!(coupon_code_hash("SPRING25"), coupon_code_hash("spring25"));
+    }
+
+    #[test]
+    fn test_referral_commission() {
+        assert_eq!(referral_commission(10_000_000, 500), 500_000); // 5% of $10
+        assert_eq!(referral_commission(10_000_000, 0), 0);
+        assert_eq!(referral_commission(3, 5_000), 1); // Rounds down
+
+        let mut referrer_account = ReferrerAccount {
+            referrer: Pubkey::new_unique(),
+            accrued: 1_500_000,
+            claimed: 0,
+            referred_buyers: 2,
+        };
+        assert_eq!(referrer_account.claimable(), 1_500_000);
+        referrer_account.claimed = 1_500_000;
+        assert_eq!(referrer_account.claimable(), 0);
+    }
+
+    #[test]
+    fn test_pricing_curve() {
+        let curve = PricingCurve {
+            reference_premium_stock: 1_000,
+            max_markup_bps: 5_000, // Up to +50%
+            min_price_usdc: 8_000_000,
+            max_price_usdc: 14_000_000,
+        };
+        assert_eq!(curve.price(10_000_000, 1_500), 10_000_000); // Plenty of stock: base price
+        assert_eq!(curve.price(10_000_000, 1_000), 10_000_000);
+        assert_eq!(curve.price(10_000_000, 500), 12_500_000);   // Half the reference: +25%
+        assert_eq!(curve.price(10_000_000, 0), 14_000_000);     // +50% capped at max
+        assert_eq!(curve.price(5_000_000, 2_000), 8_000_000);   // Floored at min
+
+        // The curve reads the Silver/Gold stock from the players
+        let players = vec![
+            PlayerSummary { id: 1, category: PlayerCategory::Bronze, available_tokens: 900 },
+            PlayerSummary { id: 2, category: PlayerCategory::Silver, available_tokens: 300 },
+            PlayerSummary { id: 3, category: PlayerCategory::Gold, available_tokens: 200 },
+        ];
+        assert_eq!(premium_stock(&players), 500);
+        let mut config = selection_test_packages().remove(0);
+        config.price_usdc = 10_000_000;
+        config.pricing_curve = Some(curve);
+        assert!(validate_package_config(&config).is_ok());
+        assert_eq!(validate_package_sale(&config, SalePhase::Public, &players, 0, 1).unwrap(), 12_500_000);
+        assert_eq!(require_max_price(12_500_000, 12_000_000).unwrap_err(), SportsError::PriceAboveMax.into());
+    }
+
+    #[test]
+    fn test_purchase_receipt_space() {
+        let receipt = PurchaseReceipt {
+            team_id: 7,
+            buyer: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            package_id: 1,
+            price_paid_usdc: 10_000_000,
+            payment_mint: Pubkey::new_unique(),
+            amount_paid: 10_000_000,
+            report_id: 3,
+            purchased_at: 1_700_000_000,
+            referrer: Pubkey::new_unique(),
+            referral_commission: 500_000,
+            coupon: Pubkey::new_unique(),
+            coupon_discount_usdc: 1_000_000,
+            presale_root: [7u8; 32],
+            pack_number: 3,
+            packs_without_gold_before: 4,
+            gold_guaranteed: true,
+            rent_payer: Pubkey::new_unique(),
+        };
+        let mut data = Vec::new();
+        receipt.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), PurchaseReceipt::SPACE);
+
+        // El clawback consume lo pendiente y el resto se descuenta de futuras comisiones
+        let mut game_state = test_game_state();
+        game_state.referral_commissions_owed = 200_000;
+        game_state.treasury_reserved = 300_000;
+        let mut referrer_account = ReferrerAccount { referrer: receipt.referrer, accrued: 800_000, claimed: 600_000, referred_buyers: 1 };
+        claw_back_referral_commission(&mut game_state, &mut referrer_account, receipt.referral_commission);
+        assert_eq!(game_state.referral_commissions_owed, 0);
+        assert_eq!(game_state.treasury_reserved, 100_000);
+        assert_eq!(referrer_account.claimable(), 0);
+        referrer_account.accrued += 500_000;
+        assert_eq!(referrer_account.claimable(), 200_000);
+
+        // Pity: el contador se restaura solo si el pack reembolsado es el último
+        let mut pack_history = PackHistory { user: receipt.buyer, packs_without_gold: 0, total_packs: 3, gold_guarantees: 1 };
+        pack_history.unrecord_pack(receipt.pack_number, receipt.packs_without_gold_before, receipt.gold_guaranteed);
+        assert_eq!((pack_history.packs_without_gold, pack_history.total_packs, pack_history.gold_guarantees), (4, 2, 0));
+        let mut pack_history = PackHistory { user: receipt.buyer, packs_without_gold: 1, total_packs: 4, gold_guarantees: 1 };
+        pack_history.unrecord_pack(receipt.pack_number, receipt.packs_without_gold_before, receipt.gold_guaranteed);
+        assert_eq!((pack_history.packs_without_gold, pack_history.total_packs, pack_history.gold_guarantees), (1, 3, 0));
+    }
+
+    #[test]
+    fn test_price_round_checks() {
+        let now = 1_700_000_000;
+        // 150.25 USD con 8 decimales
+        let round = PriceRound { round_id: 9, answer: 15_025_000_000, timestamp: now - 60, decimals: 8 };
+        assert_eq!(round.micro_usdc_price(now, 300).unwrap(), 150_250_000);
+        // Ronda más antigua que el máximo configurado, o del futuro
+        assert!(round.micro_usdc_price(now, 30).is_err());
+        assert!(PriceRound { timestamp: now + 1, ..round }.micro_usdc_price(now, 300).is_err());
+        // Respuestas no positivas o con menos de 6 decimales
+        assert!(PriceRound { answer: 0, ..round }.micro_usdc_price(now, 300).is_err());
+        assert!(PriceRound { answer: -1, ..round }.micro_usdc_price(now, 300).is_err());
+        assert!(PriceRound { decimals: 5, ..round }.micro_usdc_price(now, 300).is_err());
+    }
+
+    #[cfg(feature = "test-oracle")]
+    #[test]
+    fn test_mock_price_feed() {
+        let data = MockPriceFeed::encode(3, 15_025_000_000, 1_700_000_000, 8);
+        let round = MockPriceFeed::parse(&data).unwrap();
+        assert_eq!(round, PriceRound { round_id: 3, answer: 15_025_000_000, timestamp: 1_700_000_000, decimals: 8 });
+        assert!(MockPriceFeed::parse(&data[..MockPriceFeed::LEN - 1]).is_err());
+        assert!(MockPriceFeed::parse(&[0u8; MockPriceFeed::LEN]).is_err());
+    }
+
+    #[test]
+    fn test_sponsored_purchase_transaction() {
+        use anchor_lang::solana_program::hash::Hash;
+        use solana_keypair::Keypair;
+        use solana_signer::Signer;
+        use solana_transaction::Transaction;
+
+        let sponsor = Keypair::new();
+        let buyer = Pubkey::new_unique();
+        let accounts = crate::accounts::BuyTeam {
+            game_state: Pubkey::new_unique(),
+            team_account: Pubkey::new_unique(),
+            user: buyer,
+            fee_payer: sponsor.pubkey(),
+            sponsor: None,
+            sponsor_fee_account: None,
+            user_usdc_account: Pubkey::new_unique(),
+            program_usdc_account: Pubkey::new_unique(),
+            payment_mint_account: Pubkey::new_unique(),
+            payment_token_program: Pubkey::new_unique(),
+            accepted_mint: None,
+            coupon: None,
+            coupon_redemption: None,
+            referral: Pubkey::new_unique(),
+            referrer_account: None,
+            referrer_referral: None,
+            purchase_receipt: Pubkey::new_unique(),
+            program_usdc_authority: Pubkey::new_unique(),
+            clock: Pubkey::new_unique(),
+            system_program: Pubkey::new_unique(),
+            token_program: Pubkey::new_unique(),
+            nft_mint: Pubkey::new_unique(),
+            metadata_account: Pubkey::new_unique(),
+            user_nft_account: Pubkey::new_unique(),
+            recipient: None,
+            metadata_program: Pubkey::new_unique(),
+            update_authority: Pubkey::new_unique(),
+            associated_token_program: Pubkey::new_unique(),
+            rent: Pubkey::new_unique(),
+            sol_usd_feed: Pubkey::new_unique(),
+            chainlink_program: Pubkey::new_unique(),
+            package_config: Pubkey::new_unique(),
+            price_history: None,
+            pack_history: Pubkey::new_unique(),
+            presale_purchases: None,
+        };
+        let args = crate::instruction::BuyTeam {
+            package_id: PackageConfig::LEGACY_A,
+            terms_accepted: true,
+            presale_proof: vec![],
+            payment_mint: Pubkey::new_unique(),
+            max_price: 10_000_000,
+            gift_message_hash: None,
+            coupon_code: None,
+        };
+        let message = sponsored_purchase::build_message(accounts, args, Hash::new_unique());
+
+        // El sponsor es el fee payer (primera firma) y el comprador el otro firmante
+        assert_eq!(message.header.num_required_signatures, 2);
+        assert_eq!(&message.account_keys[..2], &[sponsor.pubkey(), buyer]);
+
+        // Solo firma el sponsor; la firma del comprador queda vacía
+        let data = sponsored_purchase::partially_sign(message.clone(), &sponsor).unwrap();
+        let transaction: Transaction = bincode::deserialize(&data).unwrap();
+        assert_eq!(transaction.message, message);
+        assert_eq!(transaction.signatures[0], sponsor.sign_message(&message.serialize()));
+        assert_eq!(transaction.signatures[1], Default::default());
+        assert!(sponsored_purchase::partially_sign(message, &Keypair::new()).is_err());
+    }
+
+    #[test]
+    fn test_scheduled_price_and_history() {
+        let mut game_state = test_game_state();
+        let mut config = selection_test_packages().remove(0);
+        config.package_id = PackageConfig::LEGACY_A;
+        config.price_usdc = game_state.team_price_a;
+        config.scheduled_price = Some(ScheduledPrice {
+            price_usdc: 12_000_000,
+            presale_price_usdc: 0,
+            pricing_curve: None,
+            effective_at: 1_000,
+        });
+
+        // Antes de la fecha se cobra el precio actual y no hace falta PriceHistory
+        assert_eq!(config.effective_price_usdc(999), 10_000_000);
+        apply_due_price_change(&mut config, &mut game_state, None, 999).unwrap();
+        assert_eq!(config.price_usdc, 10_000_000);
+
+        // Vencido: sin PriceHistory no se aplica; con él se persiste, se registra y se refleja en GameState
+        assert_eq!(config.effective_price_usdc(1_000), 12_000_000);
+        assert!(apply_due_price_change(&mut config, &mut game_state, None, 1_000).is_err());
+        let mut history = PriceHistory { next_index: 0, entries: vec![] };
+        apply_due_price_change(&mut config, &mut game_state, Some(&mut history), 1_000).unwrap();
+        assert_eq!((config.price_usdc, config.scheduled_price), (12_000_000, None));
+        assert_eq!(game_state.team_price_a, 12_000_000);
+        assert_eq!(history.entries[0].old_price_usdc, 10_000_000);
+
+        // Programar sobre un cambio vencido lo aplica primero; la curva también espera al timelock
+        let curve = PricingCurve { reference_premium_stock: 10, max_markup_bps: 5_000, min_price_usdc: 5_000_000, max_price_usdc: 50_000_000 };
+        config.scheduled_price = Some(ScheduledPrice { price_usdc: 14_000_000, effective_at: 2_000, ..config.pricing_at(0) });
+        let effective_at = 2_000 + MIN_PRICE_CHANGE_DELAY_SECS;
+        assert!(schedule_price_change(&mut config, &mut game_state, &mut history, None, None, Some(Some(curve)), 2_000, 2_000, Pubkey::default()).is_err());
+        schedule_price_change(&mut config, &mut game_state, &mut history, None, None, Some(Some(curve)), effective_at, 2_000, Pubkey::default()).unwrap();
+        assert_eq!((config.price_usdc, config.pricing_curve, game_state.team_price_a), (14_000_000, None, 14_000_000));
+        assert_eq!(history.entries.len(), 2);
+        assert_eq!(config.pricing_at(effective_at - 1).pricing_curve, None);
+        assert_eq!(config.pricing_at(effective_at), ScheduledPrice { price_usdc: 14_000_000, presale_price_usdc: 0, pricing_curve: Some(curve), effective_at });
+
+        // Ring buffer: conserva las últimas CAPACITY entradas en orden
+        for i in 0..(PriceHistory::CAPACITY as i64 + 5) {
+            history.record(PriceHistoryEntry { package_id: 0, old_price_usdc: 0, new_price_usdc: 0, effective_at: i });
+        }
+        let entries = history.chronological();
+        assert_eq!(entries.len(), PriceHistory::CAPACITY);
+        assert_eq!(entries.first().unwrap().effective_at, 5);
+        assert_eq!(entries.last().unwrap().effective_at, PriceHistory::CAPACITY as i64 + 4);
+
+        let mut data = Vec::new();
+        history.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), PriceHistory::SPACE);
+    }
+
+    #[test]
+    fn test_withdrawal_proposal_votes() {
+        let mut game_state = test_game_state();
+        let (staff_a, staff_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        game_state.staff = vec![staff_a, staff_b];
+        let mut proposal = WithdrawalProposal {
+            id: 1,
+            proposer: game_state.owner,
+            mint: Pubkey::new_unique(),
+            destination: Pubkey::new_unique(),
+            amount: 5_000_000,
+            memo: "x".repeat(WithdrawalProposal::MAX_MEMO_LEN),
+            created_at: 0,
+            expires_at: 100,
+            approvals: vec![game_state.owner],
+            rejections: vec![],
+            status: WithdrawalProposalStatus::Pending,
+            executed_at: 0,
+        };
+        assert!(proposal.require_open(99).is_ok());
+        assert!(proposal.require_open(100).is_err());
+
+        // 2-of-3: una aprobación no basta; la de un staff ya removido no cuenta
+        assert_eq!(proposal.valid_approvals(&game_state), 1);
+        proposal.approvals.push(staff_a);
+        assert!(proposal.has_voted(&staff_a));
+        game_state.staff.retain(|s| *s != staff_a);
+        assert_eq!(proposal.valid_approvals(&game_state), 1);
+
+        // Con 2 votantes restantes, un rechazo hace imposible llegar al umbral
+        assert!(proposal.can_reach_threshold(&game_state));
+        proposal.rejections.push(staff_b);
+        assert!(!proposal.can_reach_threshold(&game_state));
+
+        // Los votos de quien ya no está autorizado se descartan antes de votar de nuevo
+        game_state.staff.push(staff_a);
+        proposal.approvals.push(Pubkey::new_unique());
+        proposal.prune_stale_votes(&game_state);
+        assert_eq!(proposal.approvals, vec![game_state.owner, staff_a]);
+        assert_eq!(proposal.rejections, vec![staff_b]);
+
+        // Se puede cerrar una vez vencida o terminada
+        assert!(!proposal.is_closable(99));
+        assert!(proposal.is_closable(100));
+        proposal.status = WithdrawalProposalStatus::Executed;
+        assert!(proposal.is_closable(0));
+
+        proposal.approvals = vec![Pubkey::new_unique(); WithdrawalProposal::MAX_VOTERS];
+        proposal.rejections = vec![Pubkey::new_unique(); WithdrawalProposal::MAX_VOTERS];
+        let mut data = Vec::new();
+        proposal.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), WithdrawalProposal::SPACE);
+    }
+
+    #[test]
+    fn test_legacy_layouts_migrate() {
+        // Los campos nuevos van al final: el layout legacy es un prefijo del actual
+        let legacy = LegacyReport {
+            report_id: 3,
+            epoch: 3,
+            start_timestamp: 100,
+            end_timestamp: 200,
+            revenue: 50_000_000,
+            teams_sold: 5,
+            tokens_sold: 25,
+            staker_pool: 10_000_000,
+            stakers_count: 2,
+            reward_per_staker: 5_000_000,
+        };
+        let legacy_bytes = legacy.try_to_vec().unwrap();
+        let mut report = LegacyReport::deserialize(&mut &legacy_bytes[..]).unwrap().into_current();
+        assert!(report.mint_revenues.is_empty());
+        report.mint_revenues = vec![MintRevenue { mint: Pubkey::new_unique(), revenue: 1 }; Report::MAX_MINTS];
+        let mut data = Vec::new();
+        report.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), Report::SPACE);
+        assert_eq!(&data[8..8 + legacy_bytes.len()], &legacy_bytes[..]);
+
+        let game_state = test_game_state();
+        let legacy = LegacyGameState {
+            owner: game_state.owner,
+            staff: vec![Pubkey::new_unique()],
+            players: vec![PlayerSummary { id: 1, category: PlayerCategory::Gold, available_tokens: 10 }],
+            next_player_id: 2,
+            mint_usdc: game_state.mint_usdc,
+            team_price_a: 10_000_000,
+            team_price_b: 15_000_000,
+            team_price_c: 20_000_000,
+            next_team_id: 7,
+            next_reward_id: 1,
+            nft_update_authority: Pubkey::default(),
+            nft_image_url: "https://example.com/team.png".to_string(),
+            current_report_id: 3,
+            current_report_start: 100,
+            is_report_open: true,
+            current_report_revenue: 1_000_000,
+            current_report_teams: 1,
+            current_report_tokens: 5,
+            is_paused: false,
+            pending_withdrawal: None,
+            time_lock: 60,
+        };
+        let legacy_bytes = legacy.try_to_vec().unwrap();
+        let migrated = LegacyGameState::deserialize(&mut &legacy_bytes[..]).unwrap().into_current();
+        assert_eq!((migrated.next_team_id, migrated.players.len()), (7, 1));
+        assert_eq!(migrated.withdrawal_threshold, 2);
+        let mut data = Vec::new();
+        migrated.try_serialize(&mut data).unwrap();
+        assert_eq!(&data[8..8 + legacy_bytes.len()], &legacy_bytes[..]);
+        assert!(data.len() <= GameState::SPACE);
+    }
+
+    #[test]
+    fn test_treasury_ledger() {
+        let mut game_state = test_game_state();
+        game_state.treasury_received = 100_000_000;
+        game_state.current_report_revenue = 40_000_000;
+
+        // Lo disponible no depende del revenue del reporte abierto
+        assert_eq!(game_state.treasury_available(), 100_000_000);
+        game_state.reserve_treasury(30_000_000).unwrap();
+        assert_eq!(game_state.treasury_available(), 70_000_000);
+        assert!(game_state.reserve_treasury(70_000_001).is_err());
+
+        // Pagar una recompensa reservada no cambia lo disponible
+        game_state.pay_reserved(10_000_000).unwrap();
+        assert_eq!((game_state.treasury_reserved, game_state.treasury_withdrawn), (20_000_000, 10_000_000));
+        assert_eq!(game_state.treasury_available(), 70_000_000);
+        assert_eq!(game_state.current_report_revenue, 40_000_000);
+
+        // Lo pendiente de un reporte cae a cero al liberarlo
+        let mut report = Report {
+            report_id: 1,
+            epoch: 1,
+            start_timestamp: 0,
+            end_timestamp: 0,
+            revenue: 0,
+            teams_sold: 0,
+            tokens_sold: 0,
+            staker_pool: 20_000_000,
+            stakers_count: 2,
+            reward_per_staker: 10_000_000,
+            sol_revenue_lamports: 0,
+            coupon_redemptions: 0,
+            coupon_discount_usdc: 0,
+            mint_revenues: Vec::new(),
+            staker_pool_reserved: 0,
+            staker_pool_paid: 0,
+            staker_pool_released: true,
+        };
+        assert_eq!(report.staker_pool_outstanding(), 0);
+        report.staker_pool_released = false;
+        report.staker_pool_reserved = 20_000_000;
+        report.staker_pool_paid = 10_000_000;
+        assert_eq!(report.staker_pool_outstanding(), 10_000_000);
+        report.staker_pool_released = true;
+        assert_eq!(report.staker_pool_outstanding(), 0);
+    }
+
+    #[test]
+    fn test_compute_pack_odds() {
+        // Brute force over every ordered draw of select_team_players
+        fn brute_force(players: &[PlayerSummary], filters: &[Option<CategoryMinimum>], picked: &mut Vec<usize>, probability: f64, odds: &mut [f64]) {
+            let Some(filter) = filters.get(picked.len()) else {
+                picked.iter().for_each(|&i| odds[i] += probability);
+                return;
+            };
+            let candidates: Vec<usize> = (0..players.len())
+                .filter(|i| !picked.contains(i) && players[*i].available_tokens > 0)
+                .filter(|&i| filter.map_or(true, |m| m.matches(players[i].category)))
+                .collect();
+            let total: f64 = candidates.iter().map(|&i| players[i].available_tokens as f64).sum();
+            for i in candidates {
+                picked.push(i);
+                brute_force(players, filters, picked, probability * players[i].available_tokens as f64 / total, odds);
+                picked.pop();
+            }
+        }
+
+        let player = |id: u16, category: PlayerCategory, available_tokens: u32| PlayerSummary { id, category, available_tokens };
+        let players = vec![
+            player(1, PlayerCategory::Bronze, 1000),
+            player(2, PlayerCategory::Bronze, 1000),
+            player(3, PlayerCategory::Bronze, 400),
+            player(4, PlayerCategory::Silver, 1000),
+            player(5, PlayerCategory::Gold, 100),
+            player(6, PlayerCategory::Gold, 0),
+            player(7, PlayerCategory::Silver, 1000),
+        ];
+        let premium = CategoryMinimum { category_mask: CategoryMinimum::PREMIUM_MASK, count: 1 };
+        let config = PackageConfig {
+            package_id: 9,
+            name: "T".to_string(),
+            slot_count: 3,
+            category_minimums: vec![premium],
+            price_usdc: 1,
+            is_active: true,
+            max_supply: 0,
+            sold: 0,
+            sale_start: 0,
+            sale_end: 0,
+            presale_price_usdc: 0,
+            pricing_curve: None,
+            scheduled_price: None,
+        };
+
+        let odds = compute_pack_odds(&players, &config).unwrap();
+        let mut expected = vec![0.0; players.len()];
+        brute_force(&players, &[None, None, Some(premium)], &mut Vec::new(), 1.0, &mut expected);
+        for (exact, reference) in odds.iter().zip(expected.iter()) {
+            assert!((*exact as f64 / ODDS_SCALE as f64 - reference).abs() < 1e-8);
+        }
+        assert_eq!(odds[0], odds[1]);
+        assert_eq!(odds[5], 0);
+        // Every pack has exactly slot_count players
+        let total: u64 = odds.iter().sum();
+        assert!(total.abs_diff(3 * ODDS_SCALE) < 10);
+
+        // Pagination round trip
+        let page = encode_pack_odds_page(9, &players, &odds, 4);
+        let decoded = PackOddsPage::decode(&page).unwrap();
+        assert_eq!(decoded.total_players, 7);
+        assert_eq!(decoded.entries.len(), 3);
+        assert_eq!(decoded.entries[0], PlayerOdds { player_id: 5, probability: odds[4] as u32 });
+        assert_eq!(decoded.next_offset(), None);
+        assert!(PackOddsPage::decode(&page[..page.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_compute_pack_odds_large_pool() {
+        // 300 jugadores con stock distinto: demasiadas clases exactas, se agrupan por peso
+        let categories = [PlayerCategory::Bronze, PlayerCategory::Silver, PlayerCategory::Gold];
+        let players: Vec<PlayerSummary> = (0..300u16)
+            .map(|i| PlayerSummary {
+                id: i + 1,
+                category: categories[i as usize % 3],
+                available_tokens: 1 + (i as u32 * 7919) % 5000,
+            })
+            .collect();
+        let premium = CategoryMinimum { category_mask: CategoryMinimum::PREMIUM_MASK, count: 2 };
+        let config = PackageConfig {
+            package_id: 9,
+            name: "T".to_string(),
+            slot_count: PackageConfig::MAX_SLOTS as u8,
+            category_minimums: vec![premium],
+            price_usdc: 1,
+            is_active: true,
+            max_supply: 0,
+            sold: 0,
+            sale_start: 0,
+            sale_end: 0,
+            presale_price_usdc: 0,
+            pricing_curve: None,
+            scheduled_price: None,
+        };
+
+        let odds = compute_pack_odds(&players, &config).unwrap();
+        assert!(odds.iter().all(|&o| o > 0 && o <= ODDS_SCALE));
+        let total: u64 = odds.iter().sum();
+        let expected_total = PackageConfig::MAX_SLOTS as u64 * ODDS_SCALE;
+        assert!(total.abs_diff(expected_total) < expected_total / 1000);
+        // Más stock dentro de la misma categoría, más probabilidad
+        let bronze = || (0..300).filter(|i| i % 3 == 0);
+        let bronze_max = bronze().max_by_key(|&i| players[i].available_tokens).unwrap();
+        let bronze_min = bronze().min_by_key(|&i| players[i].available_tokens).unwrap();
+        assert!(odds[bronze_max] > odds[bronze_min]);
+    }
+
+    #[test]
+    fn test_draft_picks() {
+        use PlayerCategory::*;
+        let offered = [Bronze, Silver, Bronze, Bronze, Gold, Bronze, Silver, Bronze];
+        let premium = CategoryMinimum { category_mask: CategoryMinimum::PREMIUM_MASK, count: 2 };
+
+        assert!(validate_draft_picks(&offered, &[0, 1, 2, 3, 4], &[premium]).is_ok());
+        // Only one premium athlete picked
+        assert!(validate_draft_picks(&offered, &[0, 1, 2, 3, 5], &[premium]).is_err());
+        // Repeated, out of range or wrong count
+        assert!(validate_draft_picks(&offered, &[1, 1, 4, 3, 5], &[premium]).is_err());
+        assert!(validate_draft_picks(&offered, &[1, 4, 2, 3, 8], &[]).is_err());
+        assert!(validate_draft_picks(&offered, &[1, 4, 2, 3], &[]).is_err());
+        // Overlapping minimums: the Gold must go to the Gold slot, the Silver to the premium one
+        let gold = CategoryMinimum { category_mask: 1 << Gold as u8, count: 1 };
+        let one_premium = CategoryMinimum { count: 1, ..premium };
+        assert!(validate_draft_picks(&offered, &[4, 1, 0, 2, 3], &[one_premium, gold]).is_ok());
+        assert!(validate_draft_picks(&offered, &[1, 6, 0, 2, 3], &[one_premium, gold]).is_err());
+        let picks = auto_draft_picks(&offered, &[one_premium, gold]);
+        assert!(validate_draft_picks(&offered, &picks, &[one_premium, gold]).is_ok());
+
+        // Best first, ties by offer order; always a valid pick
+        let picks = auto_draft_picks(&offered, &[premium]);
+        assert_eq!(picks, vec![4, 1, 6, 0, 2]);
+        assert!(validate_draft_picks(&offered, &picks, &[premium]).is_ok());
+
+        let bronze_only = CategoryMinimum { category_mask: 1 << Bronze as u8, count: 4 };
+        let picks = auto_draft_picks(&offered, &[bronze_only]);
+        assert_eq!(picks, vec![0, 2, 3, 5, 4]);
+        assert!(validate_draft_picks(&offered, &picks, &[bronze_only]).is_ok());
+    }
+
+    #[test]
+    fn test_take_unlocked_tranches() {
+        let mut tranches = vec![
+            SupplyTranche { unlock_timestamp: 100, amount: 10 },
+            SupplyTranche { unlock_timestamp: 300, amount: 30 },
+            SupplyTranche { unlock_timestamp: 200, amount: 20 },
+        ];
+
+        // Only tranches unlocked at 200 are released
+        let (amount, count) = take_unlocked_tranches(&mut tranches, 200).unwrap();
+        assert_eq!(amount, 30);
+        assert_eq!(count, 2);
+        assert_eq!(tranches, vec![SupplyTranche { unlock_timestamp: 300, amount: 30 }]);
+
+        // Running the crank again at the same time releases nothing
+        let (amount, count) = take_unlocked_tranches(&mut tranches, 200).unwrap();
+        assert_eq!(amount, 0);
+        assert_eq!(count, 0);
+        assert_eq!(tranches.len(), 1);
+    }
+    
     #[test]
     fn test_should_auto_transition_to_on_field() {
         // Team in WarmingUp state with timestamp from 25 hours ago
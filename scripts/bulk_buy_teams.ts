@@ -1,5 +1,5 @@
 import * as anchor from "@coral-xyz/anchor";
-import { PublicKey, SystemProgram, Connection, TransactionInstruction, Transaction, SYSVAR_CLOCK_PUBKEY, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
+import { PublicKey, SystemProgram, Connection, TransactionInstruction, Transaction, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
 import { TOKEN_PROGRAM_ID, ASSOCIATED_TOKEN_PROGRAM_ID, getAssociatedTokenAddress, getOrCreateAssociatedTokenAccount } from "@solana/spl-token";
 import * as dotenv from "dotenv";
 import * as bs58 from "bs58";
@@ -7,17 +7,24 @@ import fs from "fs";
 
 dotenv.config();
 
-// Util: crear ix buy_team manual (coincide con useSportsProgram.ts)
-function createBuyTeamInstruction(packId: number): Buffer {
-  // Discriminator sha256("global:buy_team")[0:8] => [214,130,38,71,24,33,31,15]
-  const discriminator = Buffer.from([214, 130, 38, 71, 24, 33, 31, 15]);
-  const packageBuffer = Buffer.alloc(1);
-  packageBuffer.writeUInt8(packId, 0); // 0=A,1=B,2=C
+// Util: crear ix buy_teams manual (varios equipos, un solo pago USDC)
+function createBuyTeamsInstruction(packId: number, count: number, maxPrice: bigint): Buffer {
+  // Discriminator sha256("global:buy_teams")[0:8] => [111,88,7,8,59,205,23,208]
+  const discriminator = Buffer.from([111, 88, 7, 8, 59, 205, 23, 208]);
+  const packageBuffer = Buffer.alloc(2);
+  packageBuffer.writeUInt16LE(packId, 0); // package_id: 0=A,1=B,2=C
+  const countBuffer = Buffer.alloc(1);
+  countBuffer.writeUInt8(count, 0);
   const acceptTermsBuffer = Buffer.alloc(1);
   acceptTermsBuffer.writeUInt8(1, 0); // true
-  return Buffer.concat([discriminator, packageBuffer, acceptTermsBuffer]);
+  const maxPriceBuffer = Buffer.alloc(8);
+  maxPriceBuffer.writeBigUInt64LE(maxPrice, 0); // max_price por pack (micro-USDC)
+  return Buffer.concat([discriminator, packageBuffer, countBuffer, acceptTermsBuffer, maxPriceBuffer]);
 }
 
+// Máximo de equipos por buy_teams (BuyTeams::MAX_TEAMS en el programa)
+const MAX_TEAMS_PER_IX = 4;
+
 async function main() {
   const rpcUrl = process.env.SOLANA_RPC_URL || "https://api.devnet.solana.com";
   const connection = new Connection(rpcUrl, { commitment: "confirmed" });
@@ -31,8 +38,10 @@ async function main() {
   const programId = new PublicKey(programIdStr);
 
   const count = Number(process.env.BULK_COUNT || 1000);
-  const pack = String(process.env.BULK_PACK || "A").toUpperCase(); // A|B|C
-  const packId = pack === "A" ? 0 : pack === "B" ? 1 : 2;
+  const pack = String(process.env.BULK_PACK || "A").toUpperCase(); // A|B|C|<package_id>
+  const packId = pack === "A" ? 0 : pack === "B" ? 1 : pack === "C" ? 2 : Number(pack);
+  // Precio máximo aceptado por pack (el precio puede subir con la curva dinámica)
+  const maxPrice = BigInt(process.env.BULK_MAX_PRICE || "10000000000");
 
   // PDAs
   const [gameState] = PublicKey.findProgramAddressSync(
@@ -81,8 +90,16 @@ async function main() {
   ], programId)[0];
 
   const programUsdcAuthority = getUsdcAuthorityPDA();
+  const packIdBuffer = Buffer.alloc(2);
+  packIdBuffer.writeUInt16LE(packId, 0);
+  const packageConfig = PublicKey.findProgramAddressSync([
+    Buffer.from('package'), packIdBuffer, gameState.toBuffer()
+  ], programId)[0];
+  const packHistory = PublicKey.findProgramAddressSync([
+    Buffer.from('pack_history'), owner.publicKey.toBuffer(), gameState.toBuffer()
+  ], programId)[0];
 
-  const batchSize = Number(process.env.BULK_BATCH || 1);
+  const batchSize = Math.min(Number(process.env.BULK_BATCH || MAX_TEAMS_PER_IX), MAX_TEAMS_PER_IX);
   const totalBatches = Math.ceil(count / batchSize);
 
   for (let b = 0; b < totalBatches; b++) {
@@ -92,13 +109,32 @@ async function main() {
 
     // compute budget
     const cu = Buffer.alloc(4);
-    cu.writeUInt32LE(400000, 0);
+    cu.writeUInt32LE(1400000, 0);
     tx.add(new TransactionInstruction({
       keys: [],
       programId: new PublicKey("ComputeBudget111111111111111111111111111111"),
       data: Buffer.concat([Buffer.from([2]), cu])
     }));
 
+    // Asegurar ATAs USDC existentes (crea con el programa correcto de forma segura)
+    const userUsdc = await getOrCreateAssociatedTokenAccount(
+      connection,
+      owner,
+      mintUsdc,
+      owner.publicKey,
+      false
+    );
+    const programUsdc = await getOrCreateAssociatedTokenAccount(
+      connection,
+      owner,
+      mintUsdc,
+      programUsdcAuthority,
+      true
+    );
+
+    // Cuentas por equipo en remaining_accounts: team, nft_mint, metadata, ATA del NFT
+    // (con EntropySourceKind::SlotHashes/Oracle la cuenta de entropía va antes de estas)
+    const teamKeys = [];
     for (let i = start; i < end; i++) {
       const teamId = nextTeamId; // usar y luego incrementar
       const teamAccount = getTeamPDA(teamId);
@@ -106,55 +142,44 @@ async function main() {
       const metadataPda = PublicKey.findProgramAddressSync([
         Buffer.from('metadata'), MPL_TOKEN_METADATA_PROGRAM_ID.toBuffer(), nftMint.toBuffer()
       ], MPL_TOKEN_METADATA_PROGRAM_ID)[0];
-
-      // Asegurar ATAs existentes (crea con el programa correcto de forma segura)
-      const userUsdc = await getOrCreateAssociatedTokenAccount(
-        connection,
-        owner,
-        mintUsdc,
-        owner.publicKey,
-        false
-      );
-      const programUsdc = await getOrCreateAssociatedTokenAccount(
-        connection,
-        owner,
-        mintUsdc,
-        programUsdcAuthority,
-        true
-      );
       // Para el NFT, NO crear ATA antes porque el mint aún no existe.
-      // Solo derivar la dirección y dejar que el programa la inicialice si corresponde.
+      // Solo derivar la dirección y dejar que el programa la inicialice.
       const userNftAddr = await getAssociatedTokenAddress(nftMint, owner.publicKey);
 
-      const buyIx = new TransactionInstruction({
-        keys: [
-          { pubkey: gameState, isSigner: false, isWritable: true },
-          { pubkey: teamAccount, isSigner: false, isWritable: true },
-          { pubkey: owner.publicKey, isSigner: true, isWritable: true },
-          { pubkey: userUsdc.address, isSigner: false, isWritable: true },
-          { pubkey: programUsdc.address, isSigner: false, isWritable: true },
-          { pubkey: programUsdcAuthority, isSigner: false, isWritable: false },
-          { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
-          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
-          { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-          { pubkey: nftMint, isSigner: false, isWritable: true },
-          { pubkey: metadataPda, isSigner: false, isWritable: true },
-          { pubkey: userNftAddr, isSigner: false, isWritable: true },
-          { pubkey: MPL_TOKEN_METADATA_PROGRAM_ID, isSigner: false, isWritable: false },
-          { pubkey: updateAuthority, isSigner: false, isWritable: false },
-          { pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
-          { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
-          { pubkey: CHAINLINK_SOL_USD_FEED, isSigner: false, isWritable: false },
-          { pubkey: CHAINLINK_PROGRAM_ID, isSigner: false, isWritable: false },
-        ],
-        programId,
-        data: createBuyTeamInstruction(packId),
-      });
-      tx.add(buyIx);
+      teamKeys.push(
+        { pubkey: teamAccount, isSigner: false, isWritable: true },
+        { pubkey: nftMint, isSigner: false, isWritable: true },
+        { pubkey: metadataPda, isSigner: false, isWritable: true },
+        { pubkey: userNftAddr, isSigner: false, isWritable: true },
+      );
 
       nextTeamId += 1; // anticipar siguiente
     }
 
+    const buyIx = new TransactionInstruction({
+      keys: [
+        { pubkey: gameState, isSigner: false, isWritable: true },
+        { pubkey: owner.publicKey, isSigner: true, isWritable: true },
+        { pubkey: userUsdc.address, isSigner: false, isWritable: true },
+        { pubkey: programUsdc.address, isSigner: false, isWritable: true },
+        { pubkey: programUsdcAuthority, isSigner: false, isWritable: false },
+        { pubkey: MPL_TOKEN_METADATA_PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: updateAuthority, isSigner: false, isWritable: false },
+        { pubkey: CHAINLINK_SOL_USD_FEED, isSigner: false, isWritable: false },
+        { pubkey: CHAINLINK_PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: packageConfig, isSigner: false, isWritable: true },
+        { pubkey: packHistory, isSigner: false, isWritable: true },
+        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: ASSOCIATED_TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+        { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+        ...teamKeys,
+      ],
+      programId,
+      data: createBuyTeamsInstruction(packId, end - start, maxPrice),
+    });
+    tx.add(buyIx);
+
     const { blockhash } = await connection.getLatestBlockhash();
     tx.recentBlockhash = blockhash;
     tx.feePayer = owner.publicKey;
@@ -196,6 +196,20 @@ describe("stake and unstake", () => {
       throw e;
     }
 
+    // buy_team vende PackageConfig PDAs: crear A/B/C desde los precios de GameState
+    await program.methods
+      .migrateLegacyPackages()
+      .accounts({
+        gameState,
+        packageA: packagePda(PACKAGE_IDS.a),
+        packageB: packagePda(PACKAGE_IDS.b),
+        packageC: packagePda(PACKAGE_IDS.c),
+        user: owner.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([owner])
+      .rpc();
+
     // Crear jugadores una sola vez
     console.log("Creating test players...");
     await createTestPlayers();
@@ -297,6 +311,90 @@ describe("stake and unstake", () => {
     );
   });
 
+  // PackageConfig ids of the legacy packages (PackageConfig::LEGACY_A/B/C)
+  const PACKAGE_IDS = { a: 0, b: 1, c: 2 };
+  // Most a test purchase accepts to pay ($1000, the package price cap)
+  const MAX_PRICE_USDC = new anchor.BN(1_000_000_000);
+
+  function packageId(packageType: any): number {
+    return PACKAGE_IDS[Object.keys(packageType)[0]];
+  }
+
+  function packagePda(id: number): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [Buffer.from("package"), new anchor.BN(id).toArrayLike(Buffer, "le", 2), gameState.toBuffer()],
+      program.programId
+    )[0];
+  }
+
+  // Todas las cuentas de buy_team para el próximo equipo, pagado en USDC por `user`
+  async function buyTeamAccounts(packageType: any): Promise<any> {
+    const gameStateAccount = await program.account.gameState.fetch(gameState);
+    const teamIdSeed = new anchor.BN(gameStateAccount.nextTeamId).toArrayLike(Buffer, "le", 8);
+    const metadataProgram = new PublicKey(MPL_TOKEN_METADATA_PROGRAM_ID);
+    const [teamAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("team"), teamIdSeed, gameState.toBuffer(), program.programId.toBuffer()],
+      program.programId
+    );
+    const [nftMint] = PublicKey.findProgramAddressSync(
+      [Buffer.from("nft_mint"), teamIdSeed, gameState.toBuffer(), program.programId.toBuffer()],
+      program.programId
+    );
+    const [metadataAccount] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), metadataProgram.toBuffer(), nftMint.toBuffer()],
+      metadataProgram
+    );
+    const [purchaseReceipt] = PublicKey.findProgramAddressSync(
+      [Buffer.from("purchase_receipt"), teamIdSeed, gameState.toBuffer()],
+      program.programId
+    );
+    const [packHistory] = PublicKey.findProgramAddressSync(
+      [Buffer.from("pack_history"), user.publicKey.toBuffer(), gameState.toBuffer()],
+      program.programId
+    );
+    const [referral] = PublicKey.findProgramAddressSync(
+      [Buffer.from("referral"), user.publicKey.toBuffer(), gameState.toBuffer()],
+      program.programId
+    );
+    return {
+      gameState,
+      teamAccount,
+      user: user.publicKey,
+      feePayer: user.publicKey,
+      sponsor: null,
+      sponsorFeeAccount: null,
+      userUsdcAccount,
+      programUsdcAccount,
+      paymentMintAccount: mintUsdc,
+      paymentTokenProgram: TOKEN_PROGRAM_ID,
+      acceptedMint: null,
+      coupon: null,
+      couponRedemption: null,
+      referral,
+      referrerAccount: null,
+      referrerReferral: null,
+      purchaseReceipt,
+      programUsdcAuthority,
+      clock: SYSVAR_CLOCK_PUBKEY,
+      systemProgram: SystemProgram.programId,
+      tokenProgram: TOKEN_PROGRAM_ID,
+      nftMint,
+      metadataAccount,
+      userNftAccount: getAssociatedTokenAddressSync(nftMint, user.publicKey),
+      recipient: null,
+      metadataProgram,
+      updateAuthority: updateAuthority.publicKey,
+      associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+      rent: SYSVAR_RENT_PUBKEY,
+      solUsdFeed: gameStateAccount.solUsdFeed,
+      chainlinkProgram: gameStateAccount.oracleProgram,
+      packageConfig: packagePda(packageId(packageType)),
+      priceHistory: null,
+      packHistory,
+      presalePurchases: null,
+    };
+  }
+
   async function setupTeamPurchase(teamId: number, packageType: any) {
     // Obtener el estado actual del juego para usar next_team_id
     const gameStateAccount = await program.account.gameState.fetch(gameState);
@@ -368,25 +466,8 @@ describe("stake and unstake", () => {
       const transaction = new anchor.web3.Transaction()
         .add(computeBudgetIx)
         .add(await program.methods
-          .buyTeam(packageType, termsAccepted)
-          .accounts({
-            gameState,
-            teamAccount,
-            user: user.publicKey,
-            userUsdcAccount,
-            programUsdcAccount,
-            programUsdcAuthority,
-            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
-            systemProgram: SystemProgram.programId,
-            tokenProgram: TOKEN_PROGRAM_ID,
-            nftMint,
-            metadataAccount,
-            userNftAccount,
-            metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-            updateAuthority: updateAuthority.publicKey,
-            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-            rent: SYSVAR_RENT_PUBKEY,
-          })
+          .buyTeam(packageId(packageType), termsAccepted, [], mintUsdc, MAX_PRICE_USDC, null, null)
+          .accounts(await buyTeamAccounts(packageType))
           .instruction());
 
       await provider.sendAndConfirm(transaction, [user], {
@@ -520,25 +601,8 @@ describe("stake and unstake", () => {
 
       try {
         await program.methods
-          .buyTeam(packageType, termsAccepted)
-          .accounts({
-            gameState,
-            teamAccount,
-            user: user.publicKey,
-            userUsdcAccount,
-            programUsdcAccount,
-            programUsdcAuthority,
-            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
-            systemProgram: SystemProgram.programId,
-            tokenProgram: TOKEN_PROGRAM_ID,
-            nftMint,
-            metadataAccount,
-            userNftAccount,
-            metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-            updateAuthority: updateAuthority.publicKey,
-            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-            rent: SYSVAR_RENT_PUBKEY,
-          })
+          .buyTeam(packageId(packageType), termsAccepted, [], mintUsdc, MAX_PRICE_USDC, null, null)
+          .accounts(await buyTeamAccounts(packageType))
           .signers([user])
           .rpc();
 
@@ -579,25 +643,8 @@ describe("stake and unstake", () => {
       const transaction = new anchor.web3.Transaction()
         .add(computeBudgetIx)
         .add(await program.methods
-          .buyTeam(packageType, termsAccepted)
-          .accounts({
-            gameState,
-            teamAccount,
-            user: user.publicKey,
-            userUsdcAccount,
-            programUsdcAccount,
-            programUsdcAuthority,
-            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
-            systemProgram: SystemProgram.programId,
-            tokenProgram: TOKEN_PROGRAM_ID,
-            nftMint,
-            metadataAccount,
-            userNftAccount,
-            metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-            updateAuthority: updateAuthority.publicKey,
-            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-            rent: SYSVAR_RENT_PUBKEY,
-          })
+          .buyTeam(packageId(packageType), termsAccepted, [], mintUsdc, MAX_PRICE_USDC, null, null)
+          .accounts(await buyTeamAccounts(packageType))
           .instruction());
 
       await provider.sendAndConfirm(transaction, [user], {
@@ -745,25 +792,8 @@ describe("stake and unstake", () => {
       const transaction = new anchor.web3.Transaction()
         .add(computeBudgetIx)
         .add(await program.methods
-          .buyTeam(packageType, termsAccepted)
-          .accounts({
-            gameState,
-            teamAccount,
-            user: user.publicKey,
-            userUsdcAccount,
-            programUsdcAccount,
-            programUsdcAuthority,
-            clock: anchor.web3.SYSVAR_CLOCK_PUBKEY,
-            systemProgram: SystemProgram.programId,
-            tokenProgram: TOKEN_PROGRAM_ID,
-            nftMint,
-            metadataAccount,
-            userNftAccount,
-            metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-            updateAuthority: updateAuthority.publicKey,
-            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-            rent: SYSVAR_RENT_PUBKEY,
-          })
+          .buyTeam(packageId(packageType), termsAccepted, [], mintUsdc, MAX_PRICE_USDC, null, null)
+          .accounts(await buyTeamAccounts(packageType))
           .instruction());
 
       await provider.sendAndConfirm(transaction, [user], {
@@ -1734,27 +1764,8 @@ describe("stake and unstake", () => {
 
       // Buy team
       await program.methods
-        .buyTeam(packageType, true)
-        .accounts({
-          gameState,
-          teamAccount: teamAccountPda,
-          user: user.publicKey,
-          usdcMint: mintUsdc,
-          userUsdcAccount: userUsdcAccount,
-          programUsdcAccount: programUsdcAccount,
-          nftMint: nftMintPda,
-          userNftAccount,
-          nftMetadata: nftMetadataPda,
-          nftMasterEdition: nftMasterEditionPda,
-          nftAuthority: nftAuthorityPda,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-          metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-          rent: SYSVAR_RENT_PUBKEY,
-          clock: SYSVAR_CLOCK_PUBKEY,
-          updateAuthority: updateAuthority.publicKey,
-        })
+        .buyTeam(packageId(packageType), true, [], mintUsdc, MAX_PRICE_USDC, null, null)
+        .accounts(await buyTeamAccounts(packageType))
         .preInstructions([anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
           units: 400_000,
         })])
@@ -1894,27 +1905,8 @@ describe("stake and unstake", () => {
 
       // Buy and stake team
       await program.methods
-        .buyTeam(packageType, true)
-        .accounts({
-          gameState,
-          teamAccount: teamAccountPda,
-          user: user.publicKey,
-          usdcMint: mintUsdc,
-          userUsdcAccount: userUsdcAccount,
-          programUsdcAccount: programUsdcAccount,
-          nftMint: nftMintPda,
-          userNftAccount,
-          nftMetadata: nftMetadataPda,
-          nftMasterEdition: nftMasterEditionPda,
-          nftAuthority: nftAuthorityPda,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-          metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-          rent: SYSVAR_RENT_PUBKEY,
-          clock: SYSVAR_CLOCK_PUBKEY,
-          updateAuthority: updateAuthority.publicKey,
-        })
+        .buyTeam(packageId(packageType), true, [], mintUsdc, MAX_PRICE_USDC, null, null)
+        .accounts(await buyTeamAccounts(packageType))
         .preInstructions([anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
           units: 400_000,
         })])
@@ -2062,27 +2054,8 @@ describe("stake and unstake", () => {
 
       // Buy, stake, refresh, and initiate withdrawal
       await program.methods
-        .buyTeam(packageType, true)
-        .accounts({
-          gameState,
-          teamAccount: teamAccountPda,
-          user: user.publicKey,
-          usdcMint: mintUsdc,
-          userUsdcAccount: userUsdcAccount,
-          programUsdcAccount: programUsdcAccount,
-          nftMint: nftMintPda,
-          userNftAccount,
-          nftMetadata: nftMetadataPda,
-          nftMasterEdition: nftMasterEditionPda,
-          nftAuthority: nftAuthorityPda,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-          metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-          rent: SYSVAR_RENT_PUBKEY,
-          clock: SYSVAR_CLOCK_PUBKEY,
-          updateAuthority: updateAuthority.publicKey,
-        })
+        .buyTeam(packageId(packageType), true, [], mintUsdc, MAX_PRICE_USDC, null, null)
+        .accounts(await buyTeamAccounts(packageType))
         .preInstructions([anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
           units: 400_000,
         })])
@@ -2230,27 +2203,8 @@ describe("stake and unstake", () => {
 
       // Only buy team (don't stake)
       await program.methods
-        .buyTeam(packageType, true)
-        .accounts({
-          gameState,
-          teamAccount: teamAccountPda,
-          user: user.publicKey,
-          usdcMint: mintUsdc,
-          userUsdcAccount: userUsdcAccount,
-          programUsdcAccount: programUsdcAccount,
-          nftMint: nftMintPda,
-          userNftAccount,
-          nftMetadata: nftMetadataPda,
-          nftMasterEdition: nftMasterEditionPda,
-          nftAuthority: nftAuthorityPda,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-          metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-          rent: SYSVAR_RENT_PUBKEY,
-          clock: SYSVAR_CLOCK_PUBKEY,
-          updateAuthority: updateAuthority.publicKey,
-        })
+        .buyTeam(packageId(packageType), true, [], mintUsdc, MAX_PRICE_USDC, null, null)
+        .accounts(await buyTeamAccounts(packageType))
         .preInstructions([anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
           units: 400_000,
         })])
@@ -2354,27 +2308,8 @@ describe("stake and unstake", () => {
 
       // Buy and stake team
       await program.methods
-        .buyTeam(packageType, true)
-        .accounts({
-          gameState,
-          teamAccount: teamAccountPda,
-          user: user.publicKey,
-          usdcMint: mintUsdc,
-          userUsdcAccount: userUsdcAccount,
-          programUsdcAccount: programUsdcAccount,
-          nftMint: nftMintPda,
-          userNftAccount,
-          nftMetadata: nftMetadataPda,
-          nftMasterEdition: nftMasterEditionPda,
-          nftAuthority: nftAuthorityPda,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-          metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-          rent: SYSVAR_RENT_PUBKEY,
-          clock: SYSVAR_CLOCK_PUBKEY,
-          updateAuthority: updateAuthority.publicKey,
-        })
+        .buyTeam(packageId(packageType), true, [], mintUsdc, MAX_PRICE_USDC, null, null)
+        .accounts(await buyTeamAccounts(packageType))
         .preInstructions([anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
           units: 400_000,
         })])
@@ -2530,27 +2465,8 @@ describe("stake and unstake", () => {
 
       // User buys and stakes team
       await program.methods
-        .buyTeam(packageType, true)
-        .accounts({
-          gameState,
-          teamAccount: teamAccountPda,
-          user: user.publicKey,
-          usdcMint: mintUsdc,
-          userUsdcAccount: userUsdcAccount,
-          programUsdcAccount: programUsdcAccount,
-          nftMint: nftMintPda,
-          userNftAccount,
-          nftMetadata: nftMetadataPda,
-          nftMasterEdition: nftMasterEditionPda,
-          nftAuthority: nftAuthorityPda,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-          metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-          rent: SYSVAR_RENT_PUBKEY,
-          clock: SYSVAR_CLOCK_PUBKEY,
-          updateAuthority: updateAuthority.publicKey,
-        })
+        .buyTeam(packageId(packageType), true, [], mintUsdc, MAX_PRICE_USDC, null, null)
+        .accounts(await buyTeamAccounts(packageType))
         .preInstructions([anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
           units: 400_000,
         })])
@@ -2748,27 +2664,8 @@ describe("stake and unstake", () => {
 
       // User buys and stakes team
       await program.methods
-        .buyTeam(packageType, true)
-        .accounts({
-          gameState,
-          teamAccount: teamAccountPda,
-          user: user.publicKey,
-          usdcMint: mintUsdc,
-          userUsdcAccount: userUsdcAccount,
-          programUsdcAccount: programUsdcAccount,
-          nftMint: nftMintPda,
-          userNftAccount,
-          nftMetadata: nftMetadataPda,
-          nftMasterEdition: nftMasterEditionPda,
-          nftAuthority: nftAuthorityPda,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
-          metadataProgram: MPL_TOKEN_METADATA_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-          rent: SYSVAR_RENT_PUBKEY,
-          clock: SYSVAR_CLOCK_PUBKEY,
-          updateAuthority: updateAuthority.publicKey,
-        })
+        .buyTeam(packageId(packageType), true, [], mintUsdc, MAX_PRICE_USDC, null, null)
+        .accounts(await buyTeamAccounts(packageType))
         .preInstructions([anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
           units: 400_000,
         })])